@@ -0,0 +1,341 @@
+use crate::rules::attack_data::{AttackDomain, download_bundle};
+use crate::rules::grab_mitre::{MitreSettings, generate_analytics, split_inputs};
+use crate::rules::hunt_pack::build_hunt_pack;
+use crate::settings::Settings;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+
+const USAGE: &str = "\
+Usage: detection-wizard mitre [options] <technique|tactic>...
+
+Generate Splunk analytics from ATT&CK techniques.
+Techniques: T1055, T1003.001   Tactics: TA0002, Execution, \"Credential Access\"
+
+Options (default to the saved settings):
+  --creator <name>        value of the creator field
+  --index <macro>         index macro results are collected into
+  --whitelist <macro>     whitelist macro applied before the table
+  --priority <level>      low | medium | high | critical
+  --version <v>           ATT&CK version stamped on the analytic
+  --out <dir>             output folder
+  --domain <matrix>       enterprise | ics | mobile
+  --stix <file>           local ATT&CK STIX bundle instead of the cached download
+  --refresh               re-download the cached bundle first
+  --online                scrape attack.mitre.org instead of using the bundle
+
+Usage: detection-wizard hunt [options] <group>
+
+Build a hunt pack for an ATT&CK group (G0016, APT29, \"Cozy Bear\"): the rules
+and IOC files covering its techniques or naming it, plus a Navigator layer.
+
+Options:
+  --rules <dir>           collected rules (default ./rule_output)
+  --iocs <dir>            collected IOC files (default ./ioc_output)
+  --out <dir>             where the pack folder is created (default ./hunt_packs)
+  --domain <matrix>       enterprise | ics | mobile
+  --stix <file>           local ATT&CK STIX bundle instead of the cached download
+  --refresh               re-download the cached bundle first";
+
+/// Run a command-line subcommand. Returns `None` when `args` hold no subcommand
+/// and the GUI should start, otherwise the process exit code.
+pub fn run(args: &[String]) -> Option<i32> {
+    match args.first().map(String::as_str) {
+        None => None,
+        Some("mitre") => Some(run_mitre(&args[1..])),
+        Some("hunt") => Some(run_hunt(&args[1..])),
+        Some("-h" | "--help" | "help") => {
+            println!("{}", USAGE);
+            Some(0)
+        }
+        Some(other) => {
+            eprintln!("Unknown command {:?}\n\n{}", other, USAGE);
+            Some(2)
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct MitreArgs {
+    inputs: Vec<String>,
+    refresh: bool,
+}
+
+/// Apply `--flag value` overrides to `settings`; everything else is an input.
+fn parse_mitre_args(args: &[String], settings: &mut MitreSettings) -> Result<MitreArgs, String> {
+    let mut parsed = MitreArgs::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let field = match arg.as_str() {
+            "--refresh" => {
+                parsed.refresh = true;
+                continue;
+            }
+            "--online" => {
+                settings.offline = false;
+                continue;
+            }
+            "--domain" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| format!("{} needs a value", arg))?;
+                settings.domain = AttackDomain::parse(value)
+                    .ok_or_else(|| format!("unknown matrix {}", value))?;
+                continue;
+            }
+            "--stix" => &mut settings.stix_file,
+            "--creator" => &mut settings.creator,
+            "--index" => &mut settings.index_macro,
+            "--whitelist" => &mut settings.whitelist_macro,
+            "--priority" => &mut settings.priority,
+            "--version" => &mut settings.mitre_version,
+            "--out" => &mut settings.output_dir,
+            flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+            _ => {
+                parsed.inputs.extend(split_inputs(arg));
+                continue;
+            }
+        };
+        *field = iter
+            .next()
+            .ok_or_else(|| format!("{} needs a value", arg))?
+            .clone();
+    }
+    Ok(parsed)
+}
+
+fn run_mitre(args: &[String]) -> i32 {
+    let mut settings = Settings::load().mitre;
+    let parsed = match parse_mitre_args(args, &mut settings) {
+        Ok(parsed) if !parsed.inputs.is_empty() => parsed,
+        Ok(_) => {
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+        Err(e) => {
+            eprintln!("❌ {}\n\n{}", e, USAGE);
+            return 2;
+        }
+    };
+
+    let rt = match tokio::runtime::Runtime::new() {
+        Ok(rt) => rt,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            return 1;
+        }
+    };
+    if parsed.refresh && settings.offline {
+        match rt.block_on(download_bundle(
+            settings.domain,
+            Path::new(&settings.cache_dir),
+        )) {
+            Ok(path) => println!("Downloaded {}", path.display()),
+            Err(e) => {
+                eprintln!("❌ {:#}", e);
+                return 1;
+            }
+        }
+    }
+
+    let cancel = AtomicBool::new(false);
+    let result = rt.block_on(generate_analytics(
+        &parsed.inputs,
+        &settings,
+        &cancel,
+        |done, total, current| {
+            if !current.is_empty() {
+                println!("[{}/{}] {}", done + 1, total, current);
+            }
+        },
+    ));
+
+    match result {
+        Ok(summary) => {
+            println!(
+                "✅ {} analytics written for {} techniques to {}",
+                summary.files.len(),
+                summary.techniques,
+                settings.output_dir
+            );
+            if let Some(version) = &summary.attack_version {
+                println!("ATT&CK {}", version);
+            }
+            for (id, err) in &summary.failures {
+                eprintln!("❌ {}: {}", id, err);
+            }
+            if summary.failures.is_empty() { 0 } else { 1 }
+        }
+        Err(e) => {
+            eprintln!("❌ {:#}", e);
+            1
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct HuntArgs {
+    group: String,
+    rules: PathBuf,
+    iocs: PathBuf,
+    out: PathBuf,
+    refresh: bool,
+}
+
+fn parse_hunt_args(args: &[String], settings: &mut MitreSettings) -> Result<HuntArgs, String> {
+    let mut group = Vec::new();
+    let mut parsed = HuntArgs {
+        group: String::new(),
+        rules: PathBuf::from("./rule_output"),
+        iocs: PathBuf::from("./ioc_output"),
+        out: PathBuf::from("./hunt_packs"),
+        refresh: false,
+    };
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--refresh" {
+            parsed.refresh = true;
+            continue;
+        }
+        if !arg.starts_with("--") {
+            group.push(arg.as_str());
+            continue;
+        }
+        let value = iter
+            .next()
+            .ok_or_else(|| format!("{} needs a value", arg))?;
+        match arg.as_str() {
+            "--rules" => parsed.rules = PathBuf::from(value),
+            "--iocs" => parsed.iocs = PathBuf::from(value),
+            "--out" => parsed.out = PathBuf::from(value),
+            "--stix" => settings.stix_file = value.clone(),
+            "--domain" => {
+                settings.domain =
+                    AttackDomain::parse(value).ok_or_else(|| format!("unknown matrix {}", value))?
+            }
+            flag => return Err(format!("unknown option {}", flag)),
+        }
+    }
+    parsed.group = group.join(" ");
+    Ok(parsed)
+}
+
+fn run_hunt(args: &[String]) -> i32 {
+    let mut settings = Settings::load().mitre;
+    let parsed = match parse_hunt_args(args, &mut settings) {
+        Ok(parsed) if !parsed.group.is_empty() => parsed,
+        Ok(_) => {
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+        Err(e) => {
+            eprintln!("❌ {}\n\n{}", e, USAGE);
+            return 2;
+        }
+    };
+
+    let result = tokio::runtime::Runtime::new()
+        .map_err(anyhow::Error::from)
+        .and_then(|rt| {
+            rt.block_on(build_hunt_pack(
+                &parsed.group,
+                &parsed.rules,
+                &parsed.iocs,
+                &parsed.out,
+                &settings,
+                parsed.refresh,
+            ))
+        });
+    match result {
+        Ok(pack) => {
+            let group = &pack.plan.group;
+            println!(
+                "✅ {} ({}) hunt pack written to {}",
+                group.name,
+                group.id,
+                pack.dir.display()
+            );
+            for (tool, count) in pack.plan.rule_counts() {
+                println!("{}: {} rule files", tool, count);
+            }
+            println!(
+                "{} IOC files, {} of {} techniques without rules",
+                pack.plan.iocs.len(),
+                pack.plan.gaps.len(),
+                group.techniques.len()
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("❌ {:#}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn flags_override_settings() {
+        let mut settings = MitreSettings::default();
+        let parsed = parse_mitre_args(
+            &args(&[
+                "--creator",
+                "SOC",
+                "T1055,T1003",
+                "--out",
+                "/tmp/x",
+                "Execution",
+            ]),
+            &mut settings,
+        )
+        .unwrap();
+        assert_eq!(parsed.inputs, vec!["T1055", "T1003", "Execution"]);
+        assert!(!parsed.refresh);
+        assert_eq!(settings.creator, "SOC");
+        assert_eq!(settings.output_dir, "/tmp/x");
+
+        let parsed = parse_mitre_args(
+            &args(&["--domain", "ics", "--refresh", "--online", "T0800"]),
+            &mut settings,
+        )
+        .unwrap();
+        assert!(parsed.refresh);
+        assert_eq!(settings.domain, AttackDomain::Ics);
+        assert!(!settings.offline);
+
+        assert!(parse_mitre_args(&args(&["--domain", "cloud"]), &mut settings).is_err());
+        assert!(parse_mitre_args(&args(&["--index"]), &mut settings).is_err());
+        assert!(parse_mitre_args(&args(&["--bogus", "x"]), &mut settings).is_err());
+        assert_eq!(run(&[]), None);
+    }
+
+    #[test]
+    fn hunt_args() {
+        let mut settings = MitreSettings::default();
+        let parsed = parse_hunt_args(
+            &args(&[
+                "Cozy",
+                "Bear",
+                "--rules",
+                "/r",
+                "--refresh",
+                "--domain",
+                "mobile",
+            ]),
+            &mut settings,
+        )
+        .unwrap();
+        assert_eq!(parsed.group, "Cozy Bear");
+        assert_eq!(parsed.rules, PathBuf::from("/r"));
+        assert_eq!(parsed.iocs, PathBuf::from("./ioc_output"));
+        assert!(parsed.refresh);
+        assert_eq!(settings.domain, AttackDomain::Mobile);
+        assert!(parse_hunt_args(&args(&["G0016", "--out"]), &mut settings).is_err());
+    }
+}
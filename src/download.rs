@@ -1,7 +1,22 @@
-use chrono::Local;
+use crate::ioc::csv_export::to_csv;
+use crate::ioc::database::{DATABASE_FILE, ExpirySettings, IocDatabase};
+use crate::ioc::defang::defang;
+use crate::ioc::feeds::find_feed;
+use crate::ioc::git_iocs::scan_repo;
+use crate::ioc::misp::{FEED_DIR, feed_event, write_feed_event};
+use crate::ioc::model::{Ioc, IocKind};
+use crate::ioc::stix::to_bundle;
+use crate::ioc::store::IocStore;
+use crate::ioc::suricata::{self, SuricataIocSettings};
+use crate::ioc::warninglist::{WarninglistSettings, Warninglists};
+use crate::ioc::yara::{self, YaraIocSettings};
+use chrono::{Local, Utc};
 use eframe::egui::{self, Context};
 use egui::Color32;
+use git2::FetchOptions;
+use git2::build::{CheckoutBuilder, RepoBuilder};
 use rfd::{MessageButtons, MessageDialog, MessageDialogResult, MessageLevel};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
@@ -19,14 +34,43 @@ use walkdir::WalkDir;
 pub enum DownloadFormat {
     Txt,
     Csv,
+    /// STIX 2.1 bundle.
+    Stix,
+    /// One event per type in a MISP feed folder.
+    Misp,
 }
 
+impl DownloadFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Txt => "txt",
+            Self::Csv => "csv",
+            Self::Stix | Self::Misp => "json",
+        }
+    }
+}
+
+/// Per-file predicate applied after the extension filter; returning false drops the file.
+pub type FileFilter = Arc<dyn Fn(&Path) -> bool + Send + Sync>;
+
+#[derive(Clone)]
 pub struct ToolSpec {
     pub name: &'static str,
     pub dest_subfolder: &'static str,
     pub repo_urls: &'static [&'static str],
     pub page_urls: &'static [&'static str],
     pub allowed_exts: &'static [&'static str],
+    pub file_filter: Option<FileFilter>,
+    /// Copy repo files to `<slug>/<path in repo>` instead of flattening them to
+    /// `<slug>__<file name>`, for sources whose files load each other by path.
+    pub keep_structure: bool,
+    /// `(repo url, path)` pairs limiting large repos to the folders holding the content;
+    /// only those paths are checked out and copied.
+    pub repo_paths: &'static [(&'static str, &'static str)],
+}
+
+fn filter_allows(path: &Path, file_filter: Option<&FileFilter>) -> bool {
+    file_filter.is_none_or(|f| f(path))
 }
 
 fn ext_allowed(file_name: &str, allowed_exts: &[&str]) -> bool {
@@ -42,10 +86,38 @@ fn ext_allowed(file_name: &str, allowed_exts: &[&str]) -> bool {
     allowed_exts.iter().any(|al| al.eq_ignore_ascii_case(ext))
 }
 
+pub type ProgressTriplet = Arc<Mutex<Option<(usize, usize, String)>>>; // (done, total, current)
+
+/// Show `label` as the current item, keeping done/total.
+pub fn set_progress_label(progress: &ProgressTriplet, ctx: &Context, label: &str) {
+    {
+        let mut p = progress.lock().unwrap();
+        let (cur, tot) = match *p {
+            Some((c, t, _)) => (c, t),
+            None => (0, 0),
+        };
+        *p = Some((cur, tot, label.to_string()));
+    }
+    ctx.request_repaint();
+}
+
+/// Count one finished unit of work and clear the current item.
+pub fn advance_progress(progress: &ProgressTriplet, ctx: &Context) {
+    {
+        let mut p = progress.lock().unwrap();
+        let (cur, tot) = match *p {
+            Some((c, t, _)) => (c, t),
+            None => (0, 0),
+        };
+        *p = Some((cur + 1, tot, String::new()));
+    }
+    ctx.request_repaint();
+}
+
 pub fn process_tool(
     spec: &ToolSpec,
     output_root: &Path,
-    progress: Arc<Mutex<Option<(usize, usize, String)>>>, // (done, total, current)
+    progress: ProgressTriplet,
     ctx: Context,
     cancel_flag: Arc<AtomicBool>,
 ) -> io::Result<()> {
@@ -53,81 +125,73 @@ pub fn process_tool(
     let dest_dir = output_root.join(spec.dest_subfolder);
     fs::create_dir_all(&dest_dir)?;
 
-    let spec_name = spec.name;
-    let allowed = spec.allowed_exts;
-    let repos = spec.repo_urls.to_vec();
-    let pages = spec.page_urls.to_vec();
-    let dest_dir_clone = dest_dir.clone();
-
+    let spec = spec.clone();
     thread::spawn(move || {
-        // 1) Repos
-        for repo_url in repos {
-            if cancel_flag.load(Ordering::Relaxed) {
-                break;
-            }
-            // show current item (keep existing done/total)
-            {
-                let mut p = progress.lock().unwrap();
-                let (cur, tot) = match *p {
-                    Some((c, t, _)) => (c, t),
-                    None => (0, 0),
-                };
-                *p = Some((cur, tot, repo_url.to_string()));
-            }
-            ctx.request_repaint();
+        fetch_tool_sources(&spec, &dest_dir, &progress, &ctx, &cancel_flag);
+    });
 
-            if let Err(e) = clone_and_copy_filtered(&repo_url, &dest_dir_clone, allowed) {
-                eprintln!("[{}] Repo failed {}: {}", spec_name, repo_url, e);
-            }
+    Ok(())
+}
 
-            // increment done after finishing the unit
-            {
-                let mut p = progress.lock().unwrap();
-                let (cur, tot) = match *p {
-                    Some((c, t, _)) => (c, t),
-                    None => (0, 0),
-                };
-                *p = Some((cur + 1, tot, String::new()));
-            }
-            ctx.request_repaint();
+/// Blocking body of [`process_tool`]: clone every repo, then fetch every page,
+/// advancing `progress` once per source. Returns the files written by this run.
+pub fn fetch_tool_sources(
+    spec: &ToolSpec,
+    dest_dir: &Path,
+    progress: &ProgressTriplet,
+    ctx: &Context,
+    cancel_flag: &AtomicBool,
+) -> Vec<PathBuf> {
+    let file_filter = spec.file_filter.as_ref();
+    let mut written = Vec::new();
+
+    // 1) Repos
+    for repo_url in spec.repo_urls {
+        if cancel_flag.load(Ordering::Relaxed) {
+            break;
+        }
+        set_progress_label(progress, ctx, repo_url);
+
+        let paths: Vec<&str> = spec
+            .repo_paths
+            .iter()
+            .filter(|(url, _)| url == repo_url)
+            .map(|(_, path)| *path)
+            .collect();
+        match clone_and_copy_filtered(
+            repo_url,
+            dest_dir,
+            spec.allowed_exts,
+            file_filter,
+            spec.keep_structure,
+            &paths,
+        ) {
+            Ok(files) => written.extend(files),
+            Err(e) => eprintln!("[{}] Repo failed {}: {}", spec.name, repo_url, e),
         }
 
-        // 2) Direct URLs (“wget”)
-        for page_url in pages {
-            if cancel_flag.load(Ordering::Relaxed) {
-                break;
-            }
-            // show current item (keep existing done/total)
-            {
-                let mut p = progress.lock().unwrap();
-                let (cur, tot) = match *p {
-                    Some((c, t, _)) => (c, t),
-                    None => (0, 0),
-                };
-                *p = Some((cur, tot, page_url.to_string()));
-            }
-            ctx.request_repaint();
+        // increment done after finishing the unit
+        advance_progress(progress, ctx);
+    }
 
-            match download_url_to_dir(&page_url, &dest_dir_clone, allowed) {
-                Ok(Some(_path)) => {}
-                Ok(None) => {} // filtered or overwrite-skip
-                Err(e) => eprintln!("[{}] URL failed {}: {}", spec_name, page_url, e),
-            }
+    // 2) Direct URLs (“wget”)
+    for page_url in spec.page_urls {
+        if cancel_flag.load(Ordering::Relaxed) {
+            break;
+        }
+        set_progress_label(progress, ctx, page_url);
 
-            // increment done after finishing the unit
-            {
-                let mut p = progress.lock().unwrap();
-                let (cur, tot) = match *p {
-                    Some((c, t, _)) => (c, t),
-                    None => (0, 0),
-                };
-                *p = Some((cur + 1, tot, String::new()));
-            }
-            ctx.request_repaint();
+        match download_url_to_dir(page_url, dest_dir, spec.allowed_exts, file_filter) {
+            Ok(Some(path)) => written.push(path),
+            Ok(None) => {} // filtered or overwrite-skip
+            Err(e) => eprintln!("[{}] URL failed {}: {}", spec.name, page_url, e),
         }
-    });
 
-    Ok(())
+        // increment done after finishing the unit
+        advance_progress(progress, ctx);
+    }
+
+    written
 }
 
 /// UI helper you already had — kept intact but simplified text
@@ -142,10 +206,9 @@ pub fn render_output_path_selector(
                 .fill(Color32::from_rgb(70, 130, 180)),
         )
         .clicked()
+        && let Some(path) = rfd::FileDialog::new().pick_folder()
     {
-        if let Some(path) = rfd::FileDialog::new().pick_folder() {
-            *custom_path = Some(path.display().to_string());
-        }
+        *custom_path = Some(path.display().to_string());
     }
 
     if let Some(path) = custom_path {
@@ -155,21 +218,42 @@ pub fn render_output_path_selector(
     }
 }
 
-/// Clone repo to a temp dir and copy only files with allowed extensions into dest_dir
+/// Shallow-clone `repo_url` into `dest`, checking out only `paths` when any are given.
+fn shallow_clone(repo_url: &str, dest: &Path, paths: &[String]) -> Result<(), git2::Error> {
+    let mut fetch = FetchOptions::new();
+    fetch.depth(1);
+    let mut checkout = CheckoutBuilder::new();
+    for path in paths {
+        checkout.path(path);
+    }
+    RepoBuilder::new()
+        .fetch_options(fetch)
+        .with_checkout(checkout)
+        .clone(repo_url, dest)
+        .map(|_| ())
+}
+
+/// Clone repo to a temp dir and copy only files with allowed extensions into dest_dir.
+/// With `paths`, only those folders of the repo are checked out and copied.
+/// Returns the copied files.
 fn clone_and_copy_filtered(
     repo_url: &str,
     dest_dir: &Path,
     allowed_exts: &[&str],
-) -> io::Result<()> {
+    file_filter: Option<&FileFilter>,
+    keep_structure: bool,
+    paths: &[&str],
+) -> io::Result<Vec<PathBuf>> {
     let tmp = tempfile::tempdir()?;
     let tmp_path = tmp.path().to_path_buf(); // kept for post-clone checks
     let clone_path = tmp_path.clone(); // moved into the closure
     let repo = repo_url.to_string();
+    let checkout_paths: Vec<String> = paths.iter().map(|p| p.to_string()).collect();
 
     // 30s timeout around the clone
     let finished =
         crate::download::run_with_timeout(std::time::Duration::from_secs(30), move || {
-            let _ = git2::Repository::clone(&repo, &clone_path);
+            let _ = shallow_clone(&repo, &clone_path, &checkout_paths);
         });
 
     if !finished {
@@ -181,17 +265,51 @@ fn clone_and_copy_filtered(
 
     // If clone failed or nothing was created, skip
     if !tmp_path.exists() || tmp_path.read_dir().map_or(true, |mut r| r.next().is_none()) {
-        return Err(io::Error::new(
-            io::ErrorKind::Other,
-            format!("git clone failed or empty: {}", repo_url),
-        ));
+        return Err(io::Error::other(format!(
+            "git clone failed or empty: {}",
+            repo_url
+        )));
     }
 
-    copy_filtered_files(&tmp_path, dest_dir, allowed_exts)
+    copy_filtered_files(
+        &tmp_path,
+        dest_dir,
+        allowed_exts,
+        file_filter,
+        &repo_slug(repo_url),
+        keep_structure,
+    )
+}
+
+/// `https://github.com/SigmaHQ/sigma.git` -> `SigmaHQ_sigma`.
+pub fn repo_slug(repo_url: &str) -> String {
+    let trimmed = repo_url.trim_end_matches('/').trim_end_matches(".git");
+    let parts: Vec<&str> = trimmed.rsplit('/').take(2).collect();
+    let slug: Vec<&str> = parts.into_iter().rev().collect();
+    sanitize(&slug.join("_"))
 }
 
-fn copy_filtered_files(src: &Path, dest_dir: &Path, allowed_exts: &[&str]) -> io::Result<()> {
+/// Separates the repo slug from the original file name in copied rule files.
+pub const SOURCE_SEPARATOR: &str = "__";
+
+/// Repo slug a copied rule file came from; `None` for direct page downloads.
+pub fn source_of(file_name: &str) -> Option<&str> {
+    file_name
+        .split_once(SOURCE_SEPARATOR)
+        .map(|(source, _)| source)
+        .filter(|source| !source.is_empty())
+}
+
+fn copy_filtered_files(
+    src: &Path,
+    dest_dir: &Path,
+    allowed_exts: &[&str],
+    file_filter: Option<&FileFilter>,
+    source: &str,
+    keep_structure: bool,
+) -> io::Result<Vec<PathBuf>> {
     fs::create_dir_all(dest_dir)?;
+    let mut written = Vec::new();
 
     for entry in WalkDir::new(src).into_iter().filter_map(Result::ok) {
         let path = entry.path();
@@ -204,15 +322,22 @@ fn copy_filtered_files(src: &Path, dest_dir: &Path, allowed_exts: &[&str]) -> io
             None => continue,
         };
 
-        if !ext_allowed(fname, allowed_exts) {
+        if !ext_allowed(fname, allowed_exts) || !filter_allows(path, file_filter) {
             continue;
         }
 
-        // Unique-ish name to avoid collisions: <topdir>_<filename>
-        let repo_top = src.file_name().unwrap_or_default().to_string_lossy();
-        let unique = format!("{}_{}", sanitize(&repo_top), fname);
-
-        let dest = dest_dir.join(unique);
+        let dest = if keep_structure {
+            // <owner>_<repo>/<path in repo>
+            let relative = path.strip_prefix(src).unwrap_or(path);
+            let dest = dest_dir.join(source).join(relative);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            dest
+        } else {
+            // Unique-ish name to avoid collisions: <owner>_<repo>__<filename>
+            dest_dir.join(format!("{}{}{}", source, SOURCE_SEPARATOR, fname))
+        };
 
         if dest.exists() {
             if !should_overwrite(&dest) {
@@ -221,12 +346,13 @@ fn copy_filtered_files(src: &Path, dest_dir: &Path, allowed_exts: &[&str]) -> io
             let _ = fs::remove_file(&dest);
         }
 
-        if let Err(e) = fs::copy(path, &dest) {
-            eprintln!("Failed to copy {:?} -> {:?}: {}", path, dest, e);
+        match fs::copy(path, &dest) {
+            Ok(_) => written.push(dest),
+            Err(e) => eprintln!("Failed to copy {:?} -> {:?}: {}", path, dest, e),
         }
     }
 
-    Ok(())
+    Ok(written)
 }
 
 /// Simple filename-safe sanitization
@@ -236,85 +362,305 @@ fn sanitize(s: &str) -> String {
         .collect()
 }
 
-/// Optional: still expose your TXT/CSV aggregation pipeline if you need it elsewhere
+/// Where and how collected IOCs are written.
+#[derive(Clone)]
+pub struct IocExportOptions {
+    pub format: DownloadFormat,
+    pub output_path: String,
+    /// Keep only indicators reported by at least this many feeds.
+    pub min_feeds: usize,
+    pub warninglists: WarninglistSettings,
+    /// Write `hxxp://evil[.]com` style values for sharing in tickets and emails.
+    pub defang: bool,
+    pub expiry: ExpirySettings,
+    /// Also generate Suricata rules from every active IOC.
+    pub suricata: SuricataIocSettings,
+    /// Also generate YARA hash and string rules from every active IOC.
+    pub yara: YaraIocSettings,
+}
+
+/// Indicator sources for one IOC run.
+#[derive(Clone, Default)]
+pub struct IocSources {
+    /// `(feed url, IOC type)` pairs from the feed registry.
+    pub feeds: Vec<(String, String)>,
+    /// Git repos to clone and scan for `repo_kinds`.
+    pub repos: Vec<String>,
+    pub repo_kinds: BTreeSet<IocKind>,
+}
+
+/// Optional: still expose your TXT/CSV aggregation pipeline if you need it elsewhere.
+/// Feeds and repo IOCs of the same type are collected into one [`IocStore`] and checked
+/// against the warninglists. Once finished they are recorded in the output folder's
+/// [`IocDatabase`], whose stored entries are checked against the lists again,
+/// expired indicators are dropped and the active ones of each type are
+/// written, with the changes since the last run in an `ioc-delta-<time>.csv`. One line
+/// per file goes to `summary`.
 pub fn start_download_iocs(
-    all_urls: Vec<(String, String)>,
-    format: DownloadFormat,
-    output_path: String,
+    sources: IocSources,
+    options: IocExportOptions,
     progress: Arc<Mutex<Option<(usize, usize)>>>,
+    summary: Arc<Mutex<Vec<String>>>,
     ctx: Context,
     cancel_flag: Arc<AtomicBool>,
 ) {
-    let total = all_urls.len();
+    let total = sources.feeds.len() + sources.repos.len();
     *progress.lock().unwrap() = Some((0, total));
+    summary.lock().unwrap().clear();
 
     thread::spawn(move || {
+        let mut stores: BTreeMap<String, IocStore> = BTreeMap::new();
         let mut completed = 0;
-        for (url, ioc_type) in all_urls {
-            if cancel_flag.load(Ordering::Relaxed) {
-                break;
-            }
-            fetch_and_append_to_file(&url, &ioc_type, &format, &output_path);
+        let mut advance = || {
             completed += 1;
+            // Hold the bar short of done until the files are written.
             if let Ok(mut p) = progress.lock() {
-                *p = Some((completed, total));
+                *p = Some((completed.min(total.saturating_sub(1)), total));
             }
             ctx.request_repaint();
+        };
+        for (url, ioc_type) in &sources.feeds {
+            if cancel_flag.load(Ordering::Relaxed) {
+                break;
+            }
+            if let Some(iocs) = fetch_feed_iocs(url, ioc_type) {
+                stores.entry(ioc_type.clone()).or_default().extend(iocs);
+            }
+            advance();
         }
+        for repo in &sources.repos {
+            if cancel_flag.load(Ordering::Relaxed) {
+                break;
+            }
+            match scan_repo(repo, &sources.repo_kinds, &cancel_flag) {
+                Ok(iocs) => {
+                    for ioc in iocs {
+                        stores
+                            .entry(ioc.kind.category().to_string())
+                            .or_default()
+                            .insert(ioc);
+                    }
+                }
+                Err(e) => eprintln!("❌ {}", e),
+            }
+            advance();
+        }
+
+        let lists = Warninglists::for_settings(&options.warninglists);
+        let mut lines = Vec::new();
+        let mut seen = IocStore::new();
+        let mut reports = BTreeMap::new();
+        for (ioc_type, mut store) in stores {
+            if let Some(lists) = &lists {
+                reports.insert(
+                    ioc_type.clone(),
+                    lists.apply(&mut store, options.warninglists.action),
+                );
+            }
+            seen.merge(store);
+        }
+
+        let output_dir = Path::new(&options.output_path);
+        let db_path = output_dir.join(DATABASE_FILE);
+        let now = Utc::now();
+        let mut db = match IocDatabase::load_or_backup(&db_path, now) {
+            Ok((db, None)) => db,
+            Ok((db, Some(backup))) => {
+                let line = format!(
+                    "⚠ {} could not be parsed and was moved to {}; starting a new database",
+                    db_path.display(),
+                    backup.display()
+                );
+                eprintln!("{}", line);
+                lines.push(line);
+                db
+            }
+            Err(e) => {
+                // Going on would reset every first/last seen date, so write nothing.
+                let line = format!("❌ Failed to read {}: {}", db_path.display(), e);
+                eprintln!("{}", line);
+                *summary.lock().unwrap() = vec![line];
+                if let Ok(mut p) = progress.lock() {
+                    *p = Some((total, total));
+                }
+                ctx.request_repaint();
+                return;
+            }
+        };
+        let mut delta = db.update(seen, &options.expiry, now);
+        db.recheck_warninglists(lists.as_ref(), options.warninglists.action, &mut delta);
+        if let Err(e) = fs::create_dir_all(output_dir).and_then(|_| db.save(&db_path)) {
+            eprintln!("❌ Failed to write {}: {}", db_path.display(), e);
+        }
+
+        let mut ioc_types: BTreeSet<String> =
+            sources.feeds.iter().map(|(_, t)| t.clone()).collect();
+        ioc_types.extend(sources.repo_kinds.iter().map(|k| k.category().to_string()));
+        for ioc_type in &ioc_types {
+            let written = write_ioc_file(&db.active(ioc_type), ioc_type, &options);
+            let (added, removed) = delta.counts(ioc_type);
+            lines.push(format!(
+                "{}: wrote {} IOCs (+{} new, -{} expired)",
+                ioc_type, written, added, removed
+            ));
+            if let Some(report) = reports.get(ioc_type) {
+                lines.extend(report.to_string().lines().map(|l| format!("  {}", l)));
+            }
+        }
+        if options.suricata.enabled {
+            let rules = suricata::generate(db.store.seen_in(options.min_feeds), &options.suricata);
+            let dir = output_dir.join(suricata::RULES_DIR);
+            match rules.write(&dir) {
+                Ok(()) => lines.push(format!(
+                    "Suricata: {} rules, {} list files in {}",
+                    rules.rules.len(),
+                    rules.lists.len(),
+                    dir.display()
+                )),
+                Err(e) => eprintln!("❌ Failed to write {}: {}", dir.display(), e),
+            }
+            if rules.skipped > 0 {
+                lines.push(format!(
+                    "  sid range exhausted, {} IOCs left without rules",
+                    rules.skipped
+                ));
+            }
+        }
+        if options.yara.enabled {
+            let files = yara::generate(
+                db.store.seen_in(options.min_feeds),
+                &options.yara,
+                Local::now().date_naive(),
+            );
+            let dir = Path::new(&options.yara.output_dir);
+            match yara::write_rules(dir, &files) {
+                Ok(written) => {
+                    lines.push(format!("YARA: {} rule files in {}", written, dir.display()))
+                }
+                Err(e) => eprintln!("❌ Failed to write YARA rules to {}: {}", dir.display(), e),
+            }
+        }
+        if !delta.is_empty() {
+            let name = format!("ioc-delta-{}.csv", Local::now().format("%Y-%m-%d-%H%M%S"));
+            let delta_path = output_dir.join(name);
+            match fs::write(&delta_path, delta.to_csv()) {
+                Ok(()) => lines.push(format!(
+                    "{} added, {} removed since the last run: {}",
+                    delta.added.len(),
+                    delta.removed.len(),
+                    delta_path.display()
+                )),
+                Err(e) => eprintln!("❌ Failed to write {}: {}", delta_path.display(), e),
+            }
+        }
+        for line in &lines {
+            println!("{}", line);
+        }
+        *summary.lock().unwrap() = lines;
+        if let Ok(mut p) = progress.lock() {
+            *p = Some((total, total));
+        }
+        ctx.request_repaint();
     });
 }
 
-/// Kept simple: append mode aggregation
-pub fn fetch_and_append_to_file(
-    url: &str,
-    ioc_type: &str,
-    format: &DownloadFormat,
-    base_path: &str,
-) {
-    let date_str = Local::now().format("%Y-%m-%d").to_string();
-    let extension = match format {
-        DownloadFormat::Txt => "txt",
-        DownloadFormat::Csv => "csv",
+/// Fetch one registry feed and parse it into validated IOCs.
+pub fn fetch_feed_iocs(url: &str, ioc_type: &str) -> Option<Vec<Ioc>> {
+    let Some(feed) = find_feed(url, ioc_type) else {
+        eprintln!("❌ {} is not a registered {} feed", url, ioc_type);
+        return None;
     };
-    let filename = format!("{}-{}.{}", ioc_type.to_lowercase(), date_str, extension);
-    let out_path = Path::new(base_path).join(&filename);
 
-    if let Some(parent) = out_path.parent() {
-        if let Err(e) = fs::create_dir_all(parent) {
-            eprintln!("❌ Failed to create directory {}: {}", parent.display(), e);
-            return;
+    let text = match reqwest::blocking::get(url) {
+        Ok(resp) if resp.status().is_success() => match resp.text() {
+            Ok(text) => text,
+            Err(e) => {
+                eprintln!("❌ Failed to read content from {}: {}", url, e);
+                return None;
+            }
+        },
+        Ok(resp) => {
+            eprintln!("❌ HTTP error {} for {}", resp.status(), url);
+            return None;
         }
+        Err(e) => {
+            eprintln!("❌ Request error for {}: {}", url, e);
+            return None;
+        }
+    };
+    let parsed = match feed.parse(&text) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            return None;
+        }
+    };
+    if parsed.rejected > 0 {
+        println!(
+            "⚠ {}: skipped {} invalid entries",
+            feed.name, parsed.rejected
+        );
     }
+    Some(parsed.iocs)
+}
 
-    match reqwest::blocking::get(url) {
-        Ok(resp) if resp.status().is_success() => match resp.text() {
-            Ok(text) => {
-                let existing = fs::read_to_string(&out_path).unwrap_or_default();
-                let combined = match format {
-                    DownloadFormat::Txt => {
-                        if existing.trim().is_empty() {
-                            text.trim().to_string()
-                        } else {
-                            format!("{}\n{}", existing.trim(), text.trim())
-                        }
-                    }
-                    DownloadFormat::Csv => {
-                        let new_csv = text.trim().replace('\n', ",");
-                        if existing.trim().is_empty() {
-                            new_csv
-                        } else {
-                            format!("{},{}", existing.trim(), new_csv)
-                        }
-                    }
-                };
-                if let Err(e) = fs::write(&out_path, combined.as_bytes()) {
-                    eprintln!("❌ Failed to write {}: {}", out_path.display(), e);
+/// Write the entries of `store` seen in at least `options.min_feeds` feeds to
+/// `<type>-<date>.<ext>` under the output path, replacing an earlier export of the
+/// same day. MISP exports replace the type's event in the `misp-feed` folder instead.
+/// Returns how many were written.
+pub fn write_ioc_file(store: &IocStore, ioc_type: &str, options: &IocExportOptions) -> usize {
+    let date_str = Local::now().format("%Y-%m-%d").to_string();
+    let filename = format!(
+        "{}-{}.{}",
+        ioc_type.to_lowercase(),
+        date_str,
+        options.format.extension()
+    );
+    let out_path = Path::new(&options.output_path).join(&filename);
+
+    if let Some(parent) = out_path.parent()
+        && let Err(e) = fs::create_dir_all(parent)
+    {
+        eprintln!("❌ Failed to create directory {}: {}", parent.display(), e);
+        return 0;
+    }
+
+    let iocs = store.to_iocs(options.min_feeds);
+    let written = iocs.len();
+    let contents = match options.format {
+        DownloadFormat::Csv => to_csv(&iocs, options.defang),
+        // STIX and MISP consumers match on the real values, so defanging does not apply.
+        DownloadFormat::Misp => return write_misp_feed(&iocs, ioc_type, options),
+        DownloadFormat::Stix => {
+            let name = format!("{}-{}", ioc_type, date_str);
+            serde_json::to_string_pretty(&to_bundle(&iocs, &name)).unwrap_or_default()
+        }
+        DownloadFormat::Txt => iocs
+            .into_iter()
+            .map(|ioc| {
+                if options.defang {
+                    defang(ioc.kind, &ioc.value)
+                } else {
+                    ioc.value
                 }
-            }
-            Err(e) => eprintln!("❌ Failed to read content from {}: {}", url, e),
-        },
-        Ok(resp) => eprintln!("❌ HTTP error {} for {}", resp.status(), url),
-        Err(e) => eprintln!("❌ Request error for {}: {}", url, e),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+    if let Err(e) = fs::write(&out_path, contents.as_bytes()) {
+        eprintln!("❌ Failed to write {}: {}", out_path.display(), e);
+    }
+    written
+}
+
+fn write_misp_feed(iocs: &[Ioc], ioc_type: &str, options: &IocExportOptions) -> usize {
+    let dir = Path::new(&options.output_path).join(FEED_DIR);
+    match write_feed_event(&dir, &feed_event(iocs, ioc_type, Utc::now())) {
+        Ok(()) => iocs.len(),
+        Err(e) => {
+            eprintln!("❌ Failed to write MISP feed {}: {}", dir.display(), e);
+            0
+        }
     }
 }
 
@@ -330,12 +676,12 @@ pub fn download_and_extract_git_repo(
         .into_iter()
         .collect();
     // Reuse the internal helper
-    clone_and_copy_filtered(repo_url, output_path, &allowed)
+    clone_and_copy_filtered(repo_url, output_path, &allowed, None, false, &[]).map(|_| ())
 }
 
 pub fn download_files_with_progress(
     urls: &[&str],
-    output_path: &PathBuf,
+    output_path: &Path,
     _label: &str,
     extension_filter: Option<&str>,
 ) {
@@ -346,7 +692,7 @@ pub fn download_files_with_progress(
         .collect();
 
     for url in urls {
-        if let Err(e) = download_url_to_dir(url, output_path, &allowed) {
+        if let Err(e) = download_url_to_dir(url, output_path, &allowed, None) {
             eprintln!("download {} failed: {}", url, e);
         }
     }
@@ -354,15 +700,15 @@ pub fn download_files_with_progress(
 
 // Back-compat wrapper used by ui_ioc.rs; delegates to the generic pipeline.
 pub fn start_download(
-    all_urls: Vec<(String, String)>,
-    format: DownloadFormat,
-    output_path: String,
+    sources: IocSources,
+    options: IocExportOptions,
     progress: std::sync::Arc<std::sync::Mutex<Option<(usize, usize)>>>,
+    summary: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
     ctx: eframe::egui::Context,
 ) {
     use std::sync::atomic::AtomicBool;
     let cancel_flag = std::sync::Arc::new(AtomicBool::new(false));
-    start_download_iocs(all_urls, format, output_path, progress, ctx, cancel_flag);
+    start_download_iocs(sources, options, progress, summary, ctx, cancel_flag);
 }
 
 use std::sync::OnceLock;
@@ -397,7 +743,7 @@ fn prompt_overwrite_native(path: &Path) -> OverwriteAction {
     let q1 = MessageDialog::new()
         .set_level(MessageLevel::Warning)
         .set_title("File already exists")
-        .set_description(&format!("Overwrite?\n{}", path.display()))
+        .set_description(format!("Overwrite?\n{}", path.display()))
         .set_buttons(MessageButtons::YesNo)
         .show();
 
@@ -467,39 +813,39 @@ fn download_url_to_dir(
     url: &str,
     dest_dir: &Path,
     allowed_exts: &[&str],
+    file_filter: Option<&FileFilter>,
 ) -> io::Result<Option<PathBuf>> {
     fs::create_dir_all(dest_dir)?;
 
-    let file_name = url.split('/').last().unwrap_or("download.bin");
+    let file_name = url.split('/').next_back().unwrap_or("download.bin");
     if !ext_allowed(file_name, allowed_exts) && !allowed_exts.is_empty() {
         return Ok(None);
     }
 
     let final_path = dest_dir.join(file_name);
-
-    if final_path.exists() {
-        if !should_overwrite(&final_path) {
-            return Ok(None);
-        }
-        let _ = fs::remove_file(&final_path);
+    if final_path.exists() && !should_overwrite(&final_path) {
+        return Ok(None);
     }
 
-    let resp = reqwest::blocking::get(url)
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("GET {}: {}", url, e)))?;
+    let resp =
+        reqwest::blocking::get(url).map_err(|e| io::Error::other(format!("GET {}: {}", url, e)))?;
 
     if !resp.status().is_success() {
-        return Err(io::Error::new(
-            io::ErrorKind::Other,
-            format!("HTTP {} for {}", resp.status(), url),
-        ));
+        return Err(io::Error::other(format!(
+            "HTTP {} for {}",
+            resp.status(),
+            url
+        )));
     }
 
     let tmp_dir = tempdir_in(dest_dir)?;
     let tmp_path = tmp_dir.path().join(format!("{}.part", file_name));
-    let text = resp
-        .text()
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    let text = resp.text().map_err(io::Error::other)?;
     fs::write(&tmp_path, text.as_bytes())?;
+    if !filter_allows(&tmp_path, file_filter) {
+        return Ok(None);
+    }
+    // Replaces the old file only now, so a rejected download keeps it.
     fs::rename(&tmp_path, &final_path)?;
 
     Ok(Some(final_path))
@@ -509,6 +855,7 @@ fn download_url_to_dir(
 /// - clone/copy from `repos` (with optional per-repo timeout)
 /// - download direct `pages` (filtered by `allowed_exts`)
 /// - report progress via `progress_callback`
+///
 /// `allowed_exts` should be like ["yml","yaml"] or ["rules","rule"] (no dots).
 pub fn process_sources(
     repos: &[&str],
@@ -540,13 +887,15 @@ pub fn process_sources(
         let finished = if let Some(secs) = repo_timeout_secs {
             run_with_timeout(Duration::from_secs(secs), move || {
                 let exts_as_str: Vec<&str> = exts_owned.iter().map(|s| s.as_str()).collect();
-                if let Err(e) = clone_and_copy_filtered(&repo, &dest, &exts_as_str) {
+                if let Err(e) =
+                    clone_and_copy_filtered(&repo, &dest, &exts_as_str, None, false, &[])
+                {
                     eprintln!("❌ Repo {} failed: {}", repo, e);
                 }
             })
         } else {
             let exts_as_str: Vec<&str> = exts_owned.iter().map(|s| s.as_str()).collect();
-            if let Err(e) = clone_and_copy_filtered(&repo, &dest, &exts_as_str) {
+            if let Err(e) = clone_and_copy_filtered(&repo, &dest, &exts_as_str, None, false, &[]) {
                 eprintln!("❌ Repo {} failed: {}", repo, e);
             }
             true
@@ -587,3 +936,33 @@ where
     });
     rx.recv_timeout(timeout).is_ok()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_repo_structure() {
+        let repo = tempfile::tempdir().unwrap();
+        for dir in ["scripts", "packages/ja3"] {
+            fs::create_dir_all(repo.path().join(dir)).unwrap();
+            fs::write(repo.path().join(dir).join("__load__.zeek"), dir).unwrap();
+        }
+        fs::write(repo.path().join("README.md"), "").unwrap();
+
+        let dest = tempfile::tempdir().unwrap();
+        let mut written =
+            copy_filtered_files(repo.path(), dest.path(), &["zeek"], None, "zeek_zeek", true)
+                .unwrap();
+        written.sort();
+        // Same-named files no longer collide.
+        assert_eq!(
+            written,
+            vec![
+                dest.path().join("zeek_zeek/packages/ja3/__load__.zeek"),
+                dest.path().join("zeek_zeek/scripts/__load__.zeek"),
+            ]
+        );
+        assert_eq!(fs::read_to_string(&written[1]).unwrap(), "scripts");
+    }
+}
@@ -0,0 +1,220 @@
+use super::defang::defang;
+use super::model::{DEFAULT_CONFIDENCE, Ioc, IocKind};
+use super::store::IocStore;
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Header of IOC CSV files, in column order.
+pub const CSV_COLUMNS: [&str; 7] = [
+    "value",
+    "type",
+    "source_feed",
+    "first_seen",
+    "last_seen",
+    "tags",
+    "confidence",
+];
+
+/// Separator for the multi-value `source_feed` and `tags` columns.
+pub const LIST_SEPARATOR: char = ';';
+
+/// Quote a field when it holds a comma, quote or line break (RFC 4180).
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// One CRLF-terminated record.
+pub fn csv_record<S: AsRef<str>>(fields: &[S]) -> String {
+    let fields: Vec<String> = fields.iter().map(|f| csv_field(f.as_ref())).collect();
+    format!("{}\r\n", fields.join(","))
+}
+
+/// Split RFC 4180 text into records, honouring quoted commas, quotes and newlines.
+pub fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if field.is_empty() => quoted = true,
+            (',', false) => record.push(std::mem::take(&mut field)),
+            ('\r', false) => {}
+            ('\n', false) => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+/// Collapse IOCs with the same kind and value: sources and tags are unioned,
+/// the seen window widened and the highest confidence kept. Sorted by kind, value.
+pub fn merge_iocs(iocs: impl IntoIterator<Item = Ioc>) -> Vec<Ioc> {
+    iocs.into_iter().collect::<IocStore>().to_iocs(0)
+}
+
+fn timestamp(t: &DateTime<Utc>) -> String {
+    t.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Header plus one record per IOC, with [`defang`]ed values when `defanged` is set.
+pub fn to_csv(iocs: &[Ioc], defanged: bool) -> String {
+    let mut out = csv_record(&CSV_COLUMNS);
+    for ioc in iocs {
+        out.push_str(&csv_record(&[
+            if defanged {
+                defang(ioc.kind, &ioc.value)
+            } else {
+                ioc.value.clone()
+            },
+            ioc.kind.to_string(),
+            ioc.source.clone(),
+            timestamp(&ioc.first_seen),
+            timestamp(&ioc.last_seen),
+            ioc.tags.join(&LIST_SEPARATOR.to_string()),
+            ioc.confidence.to_string(),
+        ]));
+    }
+    out
+}
+
+fn bad_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Read a file written by [`to_csv`], defanged or not. Rows that no longer validate
+/// are dropped.
+pub fn from_csv(text: &str) -> io::Result<Vec<Ioc>> {
+    let mut records = parse_csv(text).into_iter();
+    match records.next() {
+        None => return Ok(Vec::new()),
+        Some(header) if header == CSV_COLUMNS => {}
+        Some(header) => {
+            return Err(bad_data(format!(
+                "unexpected IOC CSV header {:?}",
+                header.join(",")
+            )));
+        }
+    }
+    let parse_time = |s: &str| {
+        DateTime::parse_from_rfc3339(s)
+            .map(|t| t.with_timezone(&Utc))
+            .ok()
+    };
+    let mut iocs = Vec::new();
+    for record in records.filter(|r| r.len() == CSV_COLUMNS.len()) {
+        let Some(kind) = IocKind::parse(&record[1]) else {
+            continue;
+        };
+        let Ok(ioc) = Ioc::new(kind, &record[0], &record[2]) else {
+            continue;
+        };
+        let now = ioc.first_seen;
+        let first = parse_time(&record[3]).unwrap_or(now);
+        let last = parse_time(&record[4]).unwrap_or(now);
+        let tags = record[5]
+            .split(LIST_SEPARATOR)
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(str::to_string)
+            .collect();
+        iocs.push(
+            ioc.with_seen(first, last)
+                .with_tags(tags)
+                .with_confidence(record[6].trim().parse().unwrap_or(DEFAULT_CONFIDENCE)),
+        );
+    }
+    Ok(iocs)
+}
+
+/// Merge `new` into the CSV at `path`, creating it if needed. A file in another
+/// layout (such as the old comma-joined output) is replaced. Returns the row count.
+pub fn merge_into_csv(path: &Path, new: Vec<Ioc>, defanged: bool) -> io::Result<usize> {
+    let existing = match fs::read_to_string(path) {
+        Ok(text) => from_csv(&text).unwrap_or_else(|e| {
+            eprintln!("⚠ Replacing {}: {}", path.display(), e);
+            Vec::new()
+        }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    let merged = merge_iocs(existing.into_iter().chain(new));
+    fs::write(path, to_csv(&merged, defanged))?;
+    Ok(merged.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn ioc(kind: IocKind, value: &str, source: &str, day: u32) -> Ioc {
+        let t = Utc.with_ymd_and_hms(2025, 1, day, 0, 0, 0).unwrap();
+        Ioc::new(kind, value, source).unwrap().with_seen(t, t)
+    }
+
+    #[test]
+    fn merges_duplicates_across_feeds() {
+        let merged = merge_iocs([
+            ioc(IocKind::Ipv4, "1.2.3.4", "feed-b", 3).with_tags(vec!["c2".into()]),
+            ioc(IocKind::Ipv4, "1.2.3.4", "feed-a", 1).with_confidence(80),
+            ioc(IocKind::Domain, "Evil.COM", "feed-a", 2),
+            ioc(IocKind::Domain, "evil.com", "feed-a", 2),
+        ]);
+        assert_eq!(merged.len(), 2);
+        let ip = &merged[0];
+        assert_eq!(ip.source, "feed-a;feed-b");
+        assert_eq!(ip.first_seen.format("%d").to_string(), "01");
+        assert_eq!(ip.last_seen.format("%d").to_string(), "03");
+        assert_eq!(ip.confidence, 80);
+        assert_eq!(ip.tags, vec!["c2"]);
+        assert_eq!(merged[1].source, "feed-a");
+    }
+
+    #[test]
+    fn writes_and_reads_rfc4180() {
+        let iocs = vec![
+            ioc(IocKind::Registry, "HKCU\\Run\\a,\"b\"", "feed", 1)
+                .with_tags(vec!["x".into(), "y".into()]),
+        ];
+        let csv = to_csv(&iocs, false);
+        assert!(csv.starts_with("value,type,source_feed,first_seen,last_seen,tags,confidence\r\n"));
+        assert!(csv.contains(
+            "\"HKEY_CURRENT_USER\\Run\\a,\"\"b\"\"\",registry,feed,2025-01-01T00:00:00Z,2025-01-01T00:00:00Z,x;y,50\r\n"
+        ));
+        assert_eq!(from_csv(&csv).unwrap(), iocs);
+        assert!(from_csv("1.2.3.4,5.6.7.8").is_err());
+        assert_eq!(parse_csv("a,\"b\nc\",d\n")[0], vec!["a", "b\nc", "d"]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ip.csv");
+        fs::write(&path, "1.2.3.4,5.6.7.8").unwrap();
+        let new = vec![ioc(IocKind::Ipv4, "9.9.9.9", "feed", 1)];
+        assert_eq!(merge_into_csv(&path, new.clone(), true).unwrap(), 1);
+        assert!(
+            fs::read_to_string(&path)
+                .unwrap()
+                .contains("9.9.9[.]9,ipv4")
+        );
+        assert_eq!(merge_into_csv(&path, new, true).unwrap(), 1);
+    }
+}
@@ -0,0 +1,316 @@
+use super::csv_export::{LIST_SEPARATOR, csv_record};
+use super::model::IocKind;
+use super::store::{IocStore, StoreEntry};
+use super::warninglist::{WarninglistAction, Warninglists, clear_flags};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// File name of the IOC database inside the output folder.
+pub const DATABASE_FILE: &str = "ioc-db.json";
+
+/// Days an indicator stays active after it was last seen, per IOC type as shown
+/// in the UI ("IP", "SHA256", ...). 0 or a missing type means it never expires.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExpirySettings {
+    pub days: BTreeMap<String, u32>,
+}
+
+impl Default for ExpirySettings {
+    fn default() -> Self {
+        let days = [
+            ("IP", 30),
+            ("Domain", 90),
+            ("URL", 30),
+            ("Email", 180),
+            ("MD5", 0),
+            ("SHA1", 0),
+            ("SHA256", 0),
+            ("Filename", 0),
+            ("Registry", 0),
+            ("CVE", 0),
+            ("JA3", 90),
+        ];
+        Self {
+            days: days.into_iter().map(|(t, d)| (t.to_string(), d)).collect(),
+        }
+    }
+}
+
+impl ExpirySettings {
+    /// How long `kind` stays active without being seen, `None` for never.
+    pub fn max_age(&self, kind: IocKind) -> Option<Duration> {
+        match self.days.get(kind.category()) {
+            Some(&days) if days > 0 => Some(Duration::days(days.into())),
+            _ => None,
+        }
+    }
+
+    pub fn is_expired(&self, entry: &StoreEntry, now: DateTime<Utc>) -> bool {
+        self.max_age(entry.kind)
+            .is_some_and(|age| now - entry.last_seen > age)
+    }
+}
+
+/// Indicators that became active or expired in one run.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Delta {
+    pub added: Vec<StoreEntry>,
+    pub removed: Vec<StoreEntry>,
+}
+
+impl Delta {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+
+    /// `(added, removed)` counts for one IOC type.
+    pub fn counts(&self, ioc_type: &str) -> (usize, usize) {
+        let count = |entries: &[StoreEntry]| {
+            entries
+                .iter()
+                .filter(|e| e.kind.category() == ioc_type)
+                .count()
+        };
+        (count(&self.added), count(&self.removed))
+    }
+
+    /// CSV with a `change` column (`added` or `removed`) before the IOC columns.
+    pub fn to_csv(&self) -> String {
+        let mut out = csv_record(&[
+            "change",
+            "value",
+            "type",
+            "source_feed",
+            "first_seen",
+            "last_seen",
+        ]);
+        let changes = self
+            .added
+            .iter()
+            .map(|e| ("added", e))
+            .chain(self.removed.iter().map(|e| ("removed", e)));
+        for (change, entry) in changes {
+            let sources: Vec<&str> = entry.sources.iter().map(String::as_str).collect();
+            out.push_str(&csv_record(&[
+                change,
+                &entry.value,
+                entry.kind.as_str(),
+                &sources.join(&LIST_SEPARATOR.to_string()),
+                &entry.first_seen.to_rfc3339_opts(SecondsFormat::Secs, true),
+                &entry.last_seen.to_rfc3339_opts(SecondsFormat::Secs, true),
+            ]));
+        }
+        out
+    }
+}
+
+/// Every active indicator collected into one output folder, kept across runs so
+/// first/last seen dates survive the daily export files.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IocDatabase {
+    pub last_run: Option<DateTime<Utc>>,
+    pub store: IocStore,
+}
+
+impl IocDatabase {
+    /// Read the database, starting empty when the file does not exist yet. A file
+    /// that does not parse is an `InvalidData` error.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Like [`load`](Self::load), but a file that does not parse is moved aside to
+    /// `<file>.<time>.bak` and an empty database returned with the backup's path, so
+    /// saving never overwrites the old history.
+    pub fn load_or_backup(path: &Path, now: DateTime<Utc>) -> io::Result<(Self, Option<PathBuf>)> {
+        match Self::load(path) {
+            Ok(db) => Ok((db, None)),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                let mut name = path.file_name().unwrap_or_default().to_os_string();
+                name.push(format!(".{}.bak", now.format("%Y-%m-%d-%H%M%S")));
+                let backup = path.with_file_name(name);
+                fs::rename(path, &backup)?;
+                Ok((Self::default(), Some(backup)))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string(self).map_err(io::Error::other)?;
+        // Write next to the target and rename, so a crash never leaves half a database.
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, path)
+    }
+
+    /// Record the indicators seen in this run and expire the ones not seen for
+    /// longer than `expiry` allows.
+    pub fn update(&mut self, seen: IocStore, expiry: &ExpirySettings, now: DateTime<Utc>) -> Delta {
+        let mut delta = Delta::default();
+        for entry in Vec::<StoreEntry>::from(seen) {
+            if self.store.get(entry.kind, &entry.value).is_none() {
+                delta.added.push(entry.clone());
+            }
+            self.store.insert(entry.to_ioc());
+        }
+        self.store.remove_where(|e| {
+            let expired = expiry.is_expired(e, now);
+            if expired {
+                delta.removed.push(e.clone());
+            }
+            expired
+        });
+        // Seen again after expiring in the same run is not a change.
+        delta
+            .added
+            .retain(|a| self.store.get(a.kind, &a.value).is_some());
+        self.last_run = Some(now);
+        delta
+    }
+
+    /// Check every stored entry against the current warninglists, not only this run's,
+    /// so entries stored before a list was enabled or updated follow it as well. Stale
+    /// flags are cleared, also when `lists` is `None` because filtering is off.
+    /// Dropped entries are recorded as removed in `delta`.
+    pub fn recheck_warninglists(
+        &mut self,
+        lists: Option<&Warninglists>,
+        action: WarninglistAction,
+        delta: &mut Delta,
+    ) {
+        clear_flags(&mut self.store);
+        match (lists, action) {
+            (Some(lists), WarninglistAction::Drop) => {
+                self.store.remove_where(|e| {
+                    let listed = lists.matching(e.kind, &e.value).is_some();
+                    if listed {
+                        delta.removed.push(e.clone());
+                    }
+                    listed
+                });
+                delta
+                    .added
+                    .retain(|a| self.store.get(a.kind, &a.value).is_some());
+            }
+            (Some(lists), action) => {
+                lists.apply(&mut self.store, action);
+            }
+            (None, _) => {}
+        }
+    }
+
+    /// Active entries of one IOC type.
+    pub fn active(&self, ioc_type: &str) -> IocStore {
+        let entries: Vec<StoreEntry> = self
+            .store
+            .entries()
+            .filter(|e| e.kind.category() == ioc_type)
+            .cloned()
+            .collect();
+        entries.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ioc::model::Ioc;
+
+    fn seen(kind: IocKind, value: &str, at: DateTime<Utc>) -> IocStore {
+        [Ioc::new(kind, value, "feed").unwrap().with_seen(at, at)]
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn ages_and_expires_per_type() {
+        let expiry = ExpirySettings::default();
+        let start = Utc::now() - Duration::days(40);
+        let hash = "d41d8cd98f00b204e9800998ecf8427e";
+        let mut db = IocDatabase::default();
+
+        let delta = db.update(seen(IocKind::Ipv4, "45.9.20.13", start), &expiry, start);
+        assert_eq!(delta.counts("IP"), (1, 0));
+        db.update(seen(IocKind::Md5, hash, start), &expiry, start);
+
+        let later = start + Duration::days(10);
+        let delta = db.update(seen(IocKind::Ipv4, "45.9.20.13", later), &expiry, later);
+        assert!(delta.is_empty());
+        let entry = db.store.get(IocKind::Ipv4, "45.9.20.13").unwrap();
+        assert_eq!((entry.first_seen, entry.last_seen), (start, later));
+
+        // 31 days after the last sighting the IP expires; the hash never does.
+        let now = later + Duration::days(31);
+        let delta = db.update(seen(IocKind::Domain, "evil.example.com", now), &expiry, now);
+        assert_eq!(delta.counts("IP"), (0, 1));
+        assert_eq!(delta.counts("Domain"), (1, 0));
+        assert_eq!(db.active("IP").len(), 0);
+        assert_eq!(db.active("MD5").len(), 1);
+        assert!(delta.to_csv().contains("removed,45.9.20.13,ipv4,feed,"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DATABASE_FILE);
+        db.save(&path).unwrap();
+        assert_eq!(IocDatabase::load(&path).unwrap(), db);
+        assert_eq!(
+            IocDatabase::load(&dir.path().join("missing.json")).unwrap(),
+            IocDatabase::default()
+        );
+
+        // A corrupt file is kept as a backup instead of being overwritten.
+        fs::write(&path, "{not json").unwrap();
+        let (fresh, backup) = IocDatabase::load_or_backup(&path, now).unwrap();
+        assert_eq!(fresh, IocDatabase::default());
+        let backup = backup.unwrap();
+        assert!(!path.exists());
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "{not json");
+        assert!(backup.to_string_lossy().ends_with(&format!(
+            "{}.{}.bak",
+            DATABASE_FILE,
+            now.format("%Y-%m-%d-%H%M%S")
+        )));
+    }
+
+    #[test]
+    fn rechecks_stored_entries_against_warninglists() {
+        let expiry = ExpirySettings::default();
+        let now = Utc::now();
+        let mut db = IocDatabase::default();
+        db.store.insert(
+            Ioc::new(IocKind::Domain, "evil-c2.ru", "feed")
+                .unwrap()
+                .with_tags(vec!["warninglist:Removed list".into()]),
+        );
+        db.store
+            .insert(Ioc::new(IocKind::Domain, "google.com", "feed").unwrap());
+        let mut delta = db.update(IocStore::new(), &expiry, now);
+
+        let lists = Warninglists::bundled();
+        db.recheck_warninglists(Some(&lists), WarninglistAction::Flag, &mut delta);
+        let tags = |db: &IocDatabase, value: &str| -> Vec<String> {
+            let entry = db.store.get(IocKind::Domain, value).unwrap();
+            entry.tags.iter().cloned().collect()
+        };
+        assert!(tags(&db, "evil-c2.ru").is_empty());
+        assert_eq!(tags(&db, "google.com"), vec!["warninglist:Top domains"]);
+
+        db.recheck_warninglists(None, WarninglistAction::Off, &mut delta);
+        assert!(tags(&db, "google.com").is_empty());
+
+        db.recheck_warninglists(Some(&lists), WarninglistAction::Drop, &mut delta);
+        assert_eq!(db.active("Domain").len(), 1);
+        assert_eq!(delta.counts("Domain"), (0, 1));
+    }
+}
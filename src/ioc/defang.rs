@@ -0,0 +1,120 @@
+use super::model::IocKind;
+use regex::Regex;
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+fn refang_rules() -> &'static [(Regex, &'static str)] {
+    static RULES: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    RULES.get_or_init(|| {
+        [
+            // hxxp://, hXXps[://], meow://, fxp://
+            (
+                r"(?i)\b(?:hxxp|hxtp|htxp|h\[tt\]p|meow)(s?)(?:\[:\]//|\[://\]|://)",
+                "http$1://",
+            ),
+            (r"(?i)\bfxp(s?)(?:\[:\]//|\[://\]|://)", "ftp$1://"),
+            (r"(?i)\b(https?|ftp)(?:\[:\]//|\[://\])", "$1://"),
+            // evil[.]com, evil(.)com, evil{.}com, evil[dot]com
+            (r"(?i)\s?[\[({]\s*(?:\.|dot)\s*[\])}]\s?", "."),
+            // user[@]evil.com, user[at]evil.com
+            (r"(?i)\s?[\[({]\s*(?:@|at)\s*[\])}]\s?", "@"),
+            (r"\[:\]", ":"),
+        ]
+        .into_iter()
+        .map(|(re, to)| (Regex::new(re).unwrap(), to))
+        .collect()
+    })
+}
+
+/// Undo the usual defanging (`hxxp://`, `[.]`, `(dot)`, `[@]`, `[:]`) so the
+/// indicators in threat reports can be extracted and validated.
+pub fn refang(text: &str) -> Cow<'_, str> {
+    let mut out = Cow::Borrowed(text);
+    for (re, to) in refang_rules() {
+        let replaced = match re.replace_all(&out, *to) {
+            Cow::Owned(s) => Some(s),
+            Cow::Borrowed(_) => None,
+        };
+        if let Some(s) = replaced {
+            out = Cow::Owned(s);
+        }
+    }
+    out
+}
+
+/// Make a normalized IOC unclickable for sharing in tickets and emails:
+/// `hxxps://evil[.]com/a.php`, `1.2.3[.]4`, `user[@]evil[.]com`. Hashes, file
+/// names, registry keys and CVE IDs are returned unchanged.
+pub fn defang(kind: IocKind, value: &str) -> String {
+    let dots = |s: &str| s.replace('.', "[.]");
+    match kind {
+        IocKind::Ipv4 | IocKind::Cidr => match value.rsplit_once('.') {
+            Some((head, tail)) => format!("{}[.]{}", head, tail),
+            None => value.replace(':', "[:]"),
+        },
+        IocKind::Ipv6 => value.replace(':', "[:]"),
+        IocKind::Domain => dots(value),
+        IocKind::Email => match value.rsplit_once('@') {
+            Some((local, domain)) => format!("{}[@]{}", local, dots(domain)),
+            None => value.to_string(),
+        },
+        IocKind::Url => {
+            let (scheme, rest) = value.split_once("://").unwrap_or(("", value));
+            let scheme = match scheme {
+                "http" => "hxxp",
+                "https" => "hxxps",
+                "ftp" => "fxp",
+                other => other,
+            };
+            let split = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+            let (authority, path) = rest.split_at(split);
+            format!("{}://{}{}", scheme, dots(authority), path)
+        }
+        IocKind::Md5
+        | IocKind::Sha1
+        | IocKind::Sha256
+        | IocKind::Filename
+        | IocKind::Registry
+        | IocKind::Cve
+        | IocKind::Ja3 => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refangs_and_defangs() {
+        assert_eq!(
+            refang("get hxxps[://]evil[.]com/a.php or hXXp://1.2.3[.]4:8080/x"),
+            "get https://evil.com/a.php or http://1.2.3.4:8080/x"
+        );
+        assert_eq!(
+            refang("mail admin[at]bad(dot)org or x [@] y{.}net, fxp://h[.]io"),
+            "mail admin@bad.org or x@y.net, ftp://h.io"
+        );
+        assert!(matches!(refang("nothing to see"), Cow::Borrowed(_)));
+
+        let cases = [
+            (
+                IocKind::Url,
+                "https://evil.com/a.php?x=1.2",
+                "hxxps://evil[.]com/a.php?x=1.2",
+            ),
+            (IocKind::Domain, "sub.evil.com", "sub[.]evil[.]com"),
+            (IocKind::Ipv4, "1.2.3.4", "1.2.3[.]4"),
+            (IocKind::Ipv6, "2001:db8::1", "2001[:]db8[:][:]1"),
+            (IocKind::Email, "a.b@evil.com", "a.b[@]evil[.]com"),
+            (
+                IocKind::Md5,
+                "d41d8cd98f00b204e9800998ecf8427e",
+                "d41d8cd98f00b204e9800998ecf8427e",
+            ),
+        ];
+        for (kind, value, defanged) in cases {
+            assert_eq!(defang(kind, value), defanged);
+            assert_eq!(kind.validate(defanged).unwrap(), value, "{}", defanged);
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use super::git_iocs::extract_iocs;
+use super::misp;
+use super::model::{Ioc, IocKind, detect_kind};
+use super::stix;
+use crate::xml::{XmlElement, parse_xml};
+use scraper::{Html, Node};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// How a report file is read for IOCs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentFormat {
+    Text,
+    Html,
+    Pdf,
+    OpenIoc,
+    Stix,
+    Misp,
+}
+
+impl DocumentFormat {
+    /// Pick a format from the extension, sniffing JSON and XML content to tell
+    /// STIX, MISP and OpenIOC apart from plain data.
+    pub fn detect(path: &Path, bytes: &[u8]) -> Self {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let head = String::from_utf8_lossy(&bytes[..bytes.len().min(4096)]).to_lowercase();
+        match ext.as_str() {
+            "pdf" => Self::Pdf,
+            "html" | "htm" | "xhtml" => Self::Html,
+            "ioc" => Self::OpenIoc,
+            "xml" if head.contains("schemas.mandiant.com") || head.contains("<ioc ") => {
+                Self::OpenIoc
+            }
+            "json" => match serde_json::from_slice::<Value>(bytes) {
+                Ok(doc) if stix::is_stix(&doc) => Self::Stix,
+                Ok(doc) if misp::is_misp_event(&doc) => Self::Misp,
+                _ => Self::Text,
+            },
+            _ if bytes.starts_with(b"%PDF-") => Self::Pdf,
+            _ => Self::Text,
+        }
+    }
+}
+
+/// Visible text of an HTML page, one text node per line, without scripts and styles.
+pub fn html_text(html: &str) -> String {
+    let document = Html::parse_document(html);
+    let mut lines = Vec::new();
+    for node in document.tree.nodes() {
+        let Node::Text(text) = node.value() else {
+            continue;
+        };
+        let hidden = node
+            .parent()
+            .and_then(|p| p.value().as_element())
+            .is_some_and(|e| matches!(e.name(), "script" | "style" | "noscript"));
+        let text = text.trim();
+        if !hidden && !text.is_empty() {
+            lines.push(text.to_string());
+        }
+    }
+    lines.join("\n")
+}
+
+/// Text layer of a PDF. Malformed files make the parser panic, which is treated
+/// as "no text".
+pub fn pdf_text(bytes: &[u8]) -> Option<String> {
+    std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem(bytes))
+        .ok()?
+        .ok()
+}
+
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// IOC kind of an OpenIOC `Context/@search` term such as `FileItem/Md5sum`.
+fn openioc_kind(search: &str) -> Option<IocKind> {
+    let term = search.to_ascii_lowercase();
+    let kind = if term.ends_with("md5sum") || term.ends_with("/md5") {
+        IocKind::Md5
+    } else if term.ends_with("sha1sum") || term.ends_with("/sha1") {
+        IocKind::Sha1
+    } else if term.ends_with("sha256sum") || term.ends_with("/sha256") {
+        IocKind::Sha256
+    } else if term.starts_with("registryitem/") && term.contains("path") {
+        IocKind::Registry
+    } else if term.ends_with("/filename") || term == "processitem/name" {
+        IocKind::Filename
+    } else if term.ends_with("/uri") || term.ends_with("/url") {
+        IocKind::Url
+    } else if term.contains("email") && (term.ends_with("/from") || term.ends_with("/to")) {
+        IocKind::Email
+    } else if term.ends_with("/dns") || term.ends_with("/host") || term.ends_with("/hostname") {
+        IocKind::Domain
+    } else {
+        // IP terms included: detection picks IPv4, IPv6 or CIDR from the value.
+        return None;
+    };
+    Some(kind)
+}
+
+fn collect_indicator_items(element: &XmlElement, out: &mut Vec<(Option<IocKind>, String)>) {
+    if local_name(&element.name) == "IndicatorItem" {
+        let search = element
+            .children
+            .iter()
+            .find(|c| local_name(&c.name) == "Context")
+            .and_then(|c| c.attr("search"));
+        let content = element
+            .children
+            .iter()
+            .find(|c| local_name(&c.name) == "Content");
+        if let Some(content) = content {
+            out.push((
+                search.and_then(openioc_kind),
+                content.text.trim().to_string(),
+            ));
+        }
+    }
+    for child in &element.children {
+        collect_indicator_items(child, out);
+    }
+}
+
+/// IOCs in the `IndicatorItem`s of an OpenIOC 1.0/1.1 document.
+pub fn openioc_iocs(xml: &str, source: &str) -> Vec<Ioc> {
+    let Ok(root) = parse_xml(xml) else {
+        return Vec::new();
+    };
+    let mut items = Vec::new();
+    collect_indicator_items(&root, &mut items);
+    items
+        .into_iter()
+        .filter_map(|(kind, value)| {
+            let kind = kind.or_else(|| detect_kind(&value))?;
+            Ioc::new(kind, &value, source).ok()
+        })
+        .collect()
+}
+
+/// IOCs of `kinds` in one report file, read according to its [`DocumentFormat`].
+pub fn document_iocs(
+    path: &Path,
+    bytes: &[u8],
+    kinds: &BTreeSet<IocKind>,
+    source: &str,
+) -> Vec<Ioc> {
+    let text = || String::from_utf8(bytes.to_vec()).ok();
+    let iocs = match DocumentFormat::detect(path, bytes) {
+        DocumentFormat::Text => text()
+            .map(|t| extract_iocs(&t, kinds, source))
+            .unwrap_or_default(),
+        DocumentFormat::Html => {
+            extract_iocs(&html_text(&String::from_utf8_lossy(bytes)), kinds, source)
+        }
+        DocumentFormat::Pdf => pdf_text(bytes)
+            .map(|t| extract_iocs(&t, kinds, source))
+            .unwrap_or_default(),
+        DocumentFormat::OpenIoc => openioc_iocs(&String::from_utf8_lossy(bytes), source),
+        DocumentFormat::Stix | DocumentFormat::Misp => {
+            let doc: Value = serde_json::from_slice(bytes).unwrap_or_default();
+            if stix::is_stix(&doc) {
+                stix::bundle_iocs(&doc, source)
+            } else {
+                misp::event_iocs(&doc, source)
+            }
+        }
+    };
+    iocs.into_iter()
+        .filter(|ioc| kinds.contains(&ioc.kind))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds() -> BTreeSet<IocKind> {
+        IocKind::ALL.into_iter().collect()
+    }
+
+    fn values(name: &str, body: &str) -> Vec<String> {
+        document_iocs(Path::new(name), body.as_bytes(), &kinds(), "repo")
+            .into_iter()
+            .map(|i| i.value)
+            .collect()
+    }
+
+    #[test]
+    fn reads_html_and_openioc() {
+        let html = "<html><head><style>.a{}</style><script>var x='8.8.8.8';</script></head>\
+                    <body><p>C2: <code>45.9.20[.]13</code></p></body></html>";
+        assert_eq!(values("report.html", html), vec!["45.9.20.13"]);
+
+        let openioc = r#"<?xml version="1.0"?>
+            <ioc xmlns="http://schemas.mandiant.com/2010/ioc" id="x">
+              <definition><Indicator operator="OR">
+                <IndicatorItem condition="is">
+                  <Context document="FileItem" search="FileItem/Md5sum" type="mir"/>
+                  <Content type="md5">D41D8CD98F00B204E9800998ECF8427E</Content>
+                </IndicatorItem>
+                <IndicatorItem condition="is">
+                  <Context document="PortItem" search="PortItem/remoteIP" type="mir"/>
+                  <Content type="IP">45.9.20.13</Content>
+                </IndicatorItem>
+                <IndicatorItem condition="contains">
+                  <Context document="FileItem" search="FileItem/FileName" type="mir"/>
+                  <Content type="string">svch0st.exe</Content>
+                </IndicatorItem>
+              </Indicator></definition>
+            </ioc>"#;
+        assert_eq!(
+            values("apt.ioc", openioc),
+            vec![
+                "d41d8cd98f00b204e9800998ecf8427e",
+                "45.9.20.13",
+                "svch0st.exe"
+            ]
+        );
+        assert_eq!(
+            DocumentFormat::detect(Path::new("a.json"), br#"{"type": "bundle", "objects": []}"#),
+            DocumentFormat::Stix
+        );
+        assert_eq!(
+            DocumentFormat::detect(Path::new("blob"), b"%PDF-1.7\n..."),
+            DocumentFormat::Pdf
+        );
+        assert_eq!(pdf_text(b"%PDF-1.7 truncated"), None);
+    }
+}
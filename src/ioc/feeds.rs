@@ -0,0 +1,708 @@
+use super::csv_export::parse_csv;
+use super::git_iocs::GIT_IOC_REPOS;
+use super::model::{Ioc, IocKind};
+use crate::download::repo_slug;
+use serde_json::Value;
+use std::io;
+
+/// How a feed's response body is laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedFormat {
+    /// One value per line; `#`, `;` and `//` comments, trailing notes after whitespace.
+    Plain,
+    /// `0.0.0.0 domain [domain...]` hosts-file lines.
+    Hosts,
+    /// RFC 4180 CSV, taking one column. Lines starting with `#` are skipped.
+    Csv { column: usize, has_header: bool },
+    /// Dotted path into a JSON document; `[]` walks an array, e.g. `[].ip_address`.
+    JsonPath(&'static str),
+    /// Addresses from Suricata rule headers and `dns.query`/`tls.sni` contents.
+    SuricataRules,
+    /// FireHOL-style list of addresses and CIDR networks.
+    Netset,
+}
+
+/// A feed in the registry: where it lives, which IOC type it serves in the
+/// selector and how to pull clean values out of it.
+#[derive(Debug, Clone, Copy)]
+pub struct Feed {
+    pub name: &'static str,
+    pub url: &'static str,
+    /// IOC type the selector lists the feed under (`IP`, `Domain`, ...).
+    pub category: &'static str,
+    /// Kinds accepted from this feed, tried in order.
+    pub kinds: &'static [IocKind],
+    pub format: FeedFormat,
+    /// Labels copied onto every IOC from the feed (`c2`, `tor`, ...).
+    pub tags: &'static [&'static str],
+}
+
+const IPS: &[IocKind] = &[IocKind::Ipv4, IocKind::Ipv6, IocKind::Cidr];
+const DOMAINS: &[IocKind] = &[IocKind::Domain];
+const URLS: &[IocKind] = &[IocKind::Url];
+
+const fn feed(
+    name: &'static str,
+    url: &'static str,
+    category: &'static str,
+    kinds: &'static [IocKind],
+    format: FeedFormat,
+) -> Feed {
+    Feed {
+        name,
+        url,
+        category,
+        kinds,
+        format,
+        tags: &[],
+    }
+}
+
+use FeedFormat::{Hosts, JsonPath, Netset, Plain, SuricataRules};
+
+pub static FEEDS: &[Feed] = &[
+    feed(
+        "botvrij-filename",
+        "https://www.botvrij.eu/data/ioclist.filename",
+        "Filename",
+        &[IocKind::Filename],
+        Plain,
+    ),
+    feed(
+        "botvrij-sha256",
+        "https://www.botvrij.eu/data/ioclist.sha256",
+        "SHA256",
+        &[IocKind::Sha256],
+        Plain,
+    ),
+    feed(
+        "botvrij-sha1",
+        "https://www.botvrij.eu/data/ioclist.sha1",
+        "SHA1",
+        &[IocKind::Sha1],
+        Plain,
+    ),
+    feed(
+        "bitdefender-dark-nexus",
+        "https://raw.githubusercontent.com/bitdefender/malware-ioc/refs/heads/master/dark_nexus/all_bots.txt",
+        "SHA1",
+        &[IocKind::Sha1],
+        Plain,
+    ).tagged(&["botnet"]),
+    feed(
+        "botvrij-md5",
+        "https://www.botvrij.eu/data/ioclist.md5",
+        "MD5",
+        &[IocKind::Md5],
+        Plain,
+    ),
+    feed(
+        "binarydefense-banlist",
+        "https://www.binarydefense.com/banlist.txt",
+        "IP",
+        IPS,
+        Plain,
+    ),
+    feed(
+        "botvrij-ip-dst",
+        "https://www.botvrij.eu/data/ioclist.ip-dst",
+        "IP",
+        IPS,
+        Plain,
+    ),
+    feed(
+        "cinsscore-badguys",
+        "https://cinsscore.com/list/ci-badguys.txt",
+        "IP",
+        IPS,
+        Plain,
+    ),
+    feed(
+        "bambenek-c2-ips",
+        "https://osint.bambenekconsulting.com/feeds/c2-ipmasterlist.txt",
+        "IP",
+        IPS,
+        FeedFormat::Csv {
+            column: 0,
+            has_header: false,
+        },
+    ).tagged(&["c2"]),
+    feed(
+        "et-block-ips",
+        "https://rules.emergingthreats.net/fwrules/emerging-Block-IPs.txt",
+        "IP",
+        IPS,
+        Netset,
+    ),
+    feed(
+        "feodotracker-ipblocklist",
+        "https://feodotracker.abuse.ch/downloads/ipblocklist.json",
+        "IP",
+        IPS,
+        JsonPath("[].ip_address"),
+    ).tagged(&["botnet", "c2"]),
+    feed(
+        "feodotracker-aggressive",
+        "https://feodotracker.abuse.ch/downloads/ipblocklist_aggressive.txt",
+        "IP",
+        IPS,
+        Plain,
+    ).tagged(&["botnet", "c2"]),
+    feed(
+        "firehol-level1",
+        "https://iplists.firehol.org/files/firehol_level1.netset",
+        "IP",
+        IPS,
+        Netset,
+    ),
+    feed(
+        "firehol-level2",
+        "https://iplists.firehol.org/files/firehol_level2.netset",
+        "IP",
+        IPS,
+        Netset,
+    ),
+    feed(
+        "firehol-level3",
+        "https://iplists.firehol.org/files/firehol_level3.netset",
+        "IP",
+        IPS,
+        Netset,
+    ),
+    feed(
+        "blocklist-de-all",
+        "https://lists.blocklist.de/lists/all.txt",
+        "IP",
+        IPS,
+        Plain,
+    ).tagged(&["bruteforce"]),
+    feed(
+        "et-compromised-ips",
+        "https://rules.emergingthreats.net/blockrules/compromised-ips.txt",
+        "IP",
+        IPS,
+        Plain,
+    ).tagged(&["compromised"]),
+    feed(
+        "et-compromised-rules",
+        "https://rules.emergingthreats.net/blockrules/emerging-compromised.rules",
+        "IP",
+        IPS,
+        SuricataRules,
+    ).tagged(&["compromised"]),
+    feed(
+        "bbcan177-ips",
+        "https://gist.githubusercontent.com/BBcan177/bf29d47ea04391cb3eb0/raw/",
+        "IP",
+        IPS,
+        Plain,
+    ),
+    feed(
+        "bruteforceblocker",
+        "https://danger.rulez.sk/projects/bruteforceblocker/blist.php",
+        "IP",
+        IPS,
+        Plain,
+    ).tagged(&["bruteforce"]),
+    feed(
+        "greensnow",
+        "https://blocklist.greensnow.co/greensnow.txt",
+        "IP",
+        IPS,
+        Plain,
+    ),
+    feed(
+        "linuxtracker-hancitor",
+        "https://raw.githubusercontent.com/LinuxTracker/Blocklists/master/HancitorIPs.txt",
+        "IP",
+        IPS,
+        Plain,
+    ).tagged(&["hancitor"]),
+    feed(
+        "dan-tor-list",
+        "https://www.dan.me.uk/torlist/",
+        "IP",
+        IPS,
+        Plain,
+    ).tagged(&["tor"]),
+    feed(
+        "zerodot1-bad-ips",
+        "https://zerodot1.deteque.com/main/ipfeeds/bad/ZeroDot1sBadIPs.txt",
+        "IP",
+        IPS,
+        Plain,
+    ),
+    feed(
+        "zerodot1-miner-ips",
+        "https://zerodot1.deteque.com/main/ipfeeds/mining/ZeroDot1sMinerIPsLATEST.txt",
+        "IP",
+        IPS,
+        Plain,
+    ).tagged(&["cryptomining"]),
+    feed(
+        "tesla-consulting-iplist",
+        "https://raw.githubusercontent.com/tesla-consulting/ioc-list/refs/heads/main/iplist.csv",
+        "IP",
+        IPS,
+        FeedFormat::Csv {
+            column: 0,
+            has_header: true,
+        },
+    ),
+    feed(
+        "ssc-killnet-ddos",
+        "https://raw.githubusercontent.com/securityscorecard/SSC-Threat-Intel-IoCs/refs/heads/master/KillNet-DDoS-Blocklist/ipblocklist.txt",
+        "IP",
+        IPS,
+        Plain,
+    ).tagged(&["ddos", "killnet"]),
+    feed(
+        "botvrij-domain",
+        "https://www.botvrij.eu/data/ioclist.domain",
+        "Domain",
+        DOMAINS,
+        Plain,
+    ),
+    feed(
+        "bbcan177-domains",
+        "https://gist.githubusercontent.com/BBcan177/4a8bf37c131be4803cb2/raw",
+        "Domain",
+        DOMAINS,
+        Plain,
+    ),
+    feed(
+        "joewein-dom-bl",
+        "https://www.joewein.net/dl/bl/dom-bl.txt",
+        "Domain",
+        DOMAINS,
+        Plain,
+    ),
+    feed(
+        "bbcan177-mixed",
+        "https://gist.githubusercontent.com/BBcan177/bf29d47ea04391cb3eb0/raw/",
+        "Domain",
+        DOMAINS,
+        Plain,
+    ),
+    feed(
+        "minerchk-hosts",
+        "https://raw.githubusercontent.com/Hestat/minerchk/master/hostslist.txt",
+        "Domain",
+        DOMAINS,
+        Hosts,
+    ).tagged(&["cryptomining"]),
+    feed(
+        "sans-suspicious-high",
+        "https://isc.sans.edu/feeds/suspiciousdomains_High.txt",
+        "Domain",
+        DOMAINS,
+        Plain,
+    ),
+    feed(
+        "sans-suspicious-medium",
+        "https://isc.sans.edu/feeds/suspiciousdomains_Medium.txt",
+        "Domain",
+        DOMAINS,
+        Plain,
+    ),
+    feed(
+        "bitdefender-metamorfo",
+        "https://raw.githubusercontent.com/bitdefender/malware-ioc/refs/heads/master/metamorfo_malware/domains.txt",
+        "Domain",
+        DOMAINS,
+        Plain,
+    ).tagged(&["metamorfo"]),
+    feed(
+        "botvrij-url",
+        "https://www.botvrij.eu/data/ioclist.url",
+        "URL",
+        URLS,
+        Plain,
+    ),
+    feed(
+        "openphish",
+        "https://raw.githubusercontent.com/openphish/public_feed/refs/heads/main/feed.txt",
+        "URL",
+        URLS,
+        Plain,
+    ).tagged(&["phishing"]),
+    feed(
+        "urlhaus",
+        "https://urlhaus.abuse.ch/downloads/text/",
+        "URL",
+        URLS,
+        Plain,
+    ).tagged(&["malware-distribution"]),
+    feed(
+        "urlhaus-recent",
+        "https://urlhaus.abuse.ch/downloads/text_recent/",
+        "URL",
+        URLS,
+        Plain,
+    ).tagged(&["malware-distribution"]),
+    feed(
+        "botvrij-email-src",
+        "https://www.botvrij.eu/data/ioclist.email-src",
+        "Email",
+        &[IocKind::Email],
+        Plain,
+    ),
+    feed(
+        "wstnphx-malware-emails",
+        "https://raw.githubusercontent.com/WSTNPHX/scripts-n-tools/master/malware-email-addresses.txt",
+        "Email",
+        &[IocKind::Email],
+        Plain,
+    ),
+    feed(
+        "botvrij-regkey",
+        "https://www.botvrij.eu/data/ioclist.regkey",
+        "Registry",
+        &[IocKind::Registry],
+        Plain,
+    ),
+    feed(
+        "sslbl-ja3",
+        "https://sslbl.abuse.ch/blacklist/ja3_fingerprints.csv",
+        "JA3",
+        &[IocKind::Ja3],
+        FeedFormat::Csv {
+            column: 0,
+            has_header: false,
+        },
+    ).tagged(&["malware-tls"]),
+];
+
+/// Feeds listed under a selector type such as `IP`.
+pub fn feeds_for(category: &str) -> impl Iterator<Item = &'static Feed> + '_ {
+    FEEDS.iter().filter(move |f| f.category == category)
+}
+
+/// Registry entry for `url` under `category`; one URL can serve several types.
+pub fn find_feed(url: &str, category: &str) -> Option<&'static Feed> {
+    FEEDS
+        .iter()
+        .find(|f| f.url == url && f.category == category)
+}
+
+/// Where an IOC `source` points: the URL of a registry feed, or the page of a
+/// vendor git repo for a repo slug.
+pub fn source_url(source: &str) -> Option<String> {
+    if let Some(feed) = FEEDS.iter().find(|f| f.name == source) {
+        return Some(feed.url.to_string());
+    }
+    GIT_IOC_REPOS
+        .iter()
+        .find(|repo| repo_slug(repo) == source)
+        .map(|repo| repo.trim_end_matches(".git").to_string())
+}
+
+/// Values pulled from one response.
+#[derive(Debug, Default)]
+pub struct ParsedFeed {
+    pub iocs: Vec<Ioc>,
+    /// Candidate values that failed validation.
+    pub rejected: usize,
+}
+
+impl Feed {
+    const fn tagged(mut self, tags: &'static [&'static str]) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// Extract and validate the values in `text`. HTML bodies (error and
+    /// login pages served with a 200) are rejected outright.
+    pub fn parse(&self, text: &str) -> io::Result<ParsedFeed> {
+        if looks_like_html(text) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} returned an HTML page instead of a feed", self.name),
+            ));
+        }
+        let candidates = match self.format {
+            Plain => plain_values(text),
+            Hosts => hosts_values(text),
+            FeedFormat::Csv { column, has_header } => csv_values(text, column, has_header),
+            JsonPath(path) => {
+                let doc: Value = serde_json::from_str(text)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                json_path(&doc, path)
+            }
+            SuricataRules => suricata_values(text),
+            Netset => plain_values(text),
+        };
+        let mut parsed = ParsedFeed::default();
+        for candidate in candidates {
+            match self.accept(&candidate) {
+                Some(ioc) => parsed.iocs.push(ioc),
+                None => parsed.rejected += 1,
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Validate `raw` against each kind the feed declares; a plain line may
+    /// carry a trailing note, so its first token is tried as well.
+    fn accept(&self, raw: &str) -> Option<Ioc> {
+        let first = raw.split_whitespace().next().unwrap_or_default();
+        [raw, first]
+            .into_iter()
+            .find_map(|value| {
+                self.kinds
+                    .iter()
+                    .find_map(|kind| Ioc::new(*kind, value, self.name).ok())
+            })
+            .map(|ioc| ioc.with_tags(self.tags.iter().map(|t| t.to_string()).collect()))
+    }
+}
+
+fn looks_like_html(text: &str) -> bool {
+    let head: String = text
+        .trim_start()
+        .chars()
+        .take(15)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    head.starts_with("<!doctype html") || head.starts_with("<html") || head.starts_with("<head")
+}
+
+fn strip_comment(line: &str) -> &str {
+    let line = line.trim();
+    if line.starts_with(['#', ';']) || line.starts_with("//") {
+        return "";
+    }
+    // Inline comments need whitespace in front so URL fragments survive.
+    let cut = [" #", "\t#", " ;", "\t;", " //"]
+        .iter()
+        .filter_map(|marker| line.find(marker))
+        .min()
+        .unwrap_or(line.len());
+    line[..cut].trim()
+}
+
+fn plain_values(text: &str) -> Vec<String> {
+    text.lines()
+        .map(strip_comment)
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+const HOSTS_SKIP: [&str; 4] = [
+    "localhost",
+    "localhost.localdomain",
+    "local",
+    "broadcasthost",
+];
+
+fn hosts_values(text: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    for line in text.lines().map(strip_comment).filter(|l| !l.is_empty()) {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        // Bare domain lines are accepted too; many "hosts" lists mix both.
+        let names = if tokens.len() > 1 && tokens[0].parse::<std::net::IpAddr>().is_ok() {
+            &tokens[1..]
+        } else {
+            &tokens[..]
+        };
+        values.extend(
+            names
+                .iter()
+                .filter(|n| !HOSTS_SKIP.contains(&n.to_ascii_lowercase().as_str()))
+                .map(|n| n.to_string()),
+        );
+    }
+    values
+}
+
+fn csv_values(text: &str, column: usize, has_header: bool) -> Vec<String> {
+    let body: String = text
+        .lines()
+        .filter(|l| !l.trim_start().starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n");
+    parse_csv(&body)
+        .into_iter()
+        .skip(usize::from(has_header))
+        .filter_map(|mut record| (column < record.len()).then(|| record.swap_remove(column)))
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+/// Values at `path` in `doc`: `.`-separated keys, `[]` iterates an array.
+pub fn json_path(doc: &Value, path: &str) -> Vec<String> {
+    let mut current = vec![doc];
+    for segment in path.split('.').filter(|s| !s.is_empty()) {
+        let (key, iterate) = match segment.strip_suffix("[]") {
+            Some(key) => (key, true),
+            None => (segment, false),
+        };
+        current = current
+            .into_iter()
+            .filter_map(|v| if key.is_empty() { Some(v) } else { v.get(key) })
+            .flat_map(|v| match (iterate, v) {
+                (true, Value::Array(items)) => items.iter().collect(),
+                (true, _) => Vec::new(),
+                (false, v) => vec![v],
+            })
+            .collect();
+    }
+    current
+        .into_iter()
+        .filter_map(|v| match v {
+            Value::String(s) => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn suricata_values(text: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((header, options)) = line.split_once('(') else {
+            continue;
+        };
+        // action proto src sport direction dst dport
+        let fields: Vec<&str> = header.split_whitespace().collect();
+        for addr in [fields.get(2), fields.get(5)].into_iter().flatten() {
+            values.extend(
+                addr.trim_matches(['[', ']'])
+                    .split(',')
+                    .map(|a| a.trim().trim_start_matches('!'))
+                    .filter(|a| !a.is_empty() && !a.starts_with('$') && *a != "any")
+                    .map(str::to_string),
+            );
+        }
+        let mut sticky = "";
+        for option in options.split(';').map(str::trim) {
+            match option.split_once(':') {
+                None => sticky = option,
+                Some(("content", value)) if matches!(sticky, "dns.query" | "tls.sni") => {
+                    values.push(
+                        value
+                            .trim()
+                            .trim_matches('"')
+                            .trim_start_matches('.')
+                            .to_string(),
+                    );
+                }
+                Some(_) => {}
+            }
+        }
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_feed(kinds: &'static [IocKind], format: FeedFormat) -> Feed {
+        feed("test", "https://example.com/feed", "IP", kinds, format)
+    }
+
+    fn values(feed: &Feed, text: &str) -> Vec<String> {
+        feed.parse(text)
+            .unwrap()
+            .iocs
+            .into_iter()
+            .map(|i| i.value)
+            .collect()
+    }
+
+    #[test]
+    fn parses_plain_hosts_and_netset() {
+        let ips = test_feed(IPS, Plain);
+        assert_eq!(
+            values(
+                &ips,
+                "# header\n1.2.3.4  # seen 3x\n\n5.6.7.8\t12\n10.0.0.0/8\nnot-an-ip\n2001:db8::1\n"
+            ),
+            vec!["1.2.3.4", "5.6.7.8", "10.0.0.0/8", "2001:db8::1"]
+        );
+        assert_eq!(ips.parse("1.2.3.4\nnope\n").unwrap().rejected, 1);
+
+        let hosts = test_feed(DOMAINS, Hosts);
+        assert_eq!(
+            values(
+                &hosts,
+                "127.0.0.1 localhost\n0.0.0.0 evil.com www.evil.com\nbare.example.org\n"
+            ),
+            vec!["evil.com", "www.evil.com", "bare.example.org"]
+        );
+
+        let urls = test_feed(URLS, Plain);
+        assert_eq!(
+            values(&urls, "http://a.example.com/x#frag # comment\n"),
+            vec!["http://a.example.com/x#frag"]
+        );
+        assert!(
+            ips.parse("<!DOCTYPE html><html>rate limited</html>")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn parses_csv_json_and_suricata() {
+        let csv = test_feed(
+            IPS,
+            FeedFormat::Csv {
+                column: 1,
+                has_header: true,
+            },
+        );
+        assert_eq!(
+            values(
+                &csv,
+                "# comment\nid,ip,note\n1,1.2.3.4,\"a, \"\"quoted\"\" note\"\n2,5.6.7.8,x\n"
+            ),
+            vec!["1.2.3.4", "5.6.7.8"]
+        );
+
+        let json = test_feed(IPS, JsonPath("data[].hosts[]"));
+        assert_eq!(
+            values(
+                &json,
+                r#"{"data": [{"hosts": ["1.1.1.1", "2.2.2.2"]}, {"hosts": ["3.3.3.3"]}]}"#
+            ),
+            vec!["1.1.1.1", "2.2.2.2", "3.3.3.3"]
+        );
+        let doc: Value = serde_json::from_str(r#"[{"ip_address": "9.9.9.9"}]"#).unwrap();
+        assert_eq!(json_path(&doc, "[].ip_address"), vec!["9.9.9.9"]);
+
+        let rules = test_feed(
+            &[IocKind::Ipv4, IocKind::Cidr, IocKind::Domain],
+            SuricataRules,
+        );
+        let text = concat!(
+            "# comment\n",
+            "alert ip [1.2.3.4,5.6.0.0/16] any -> $HOME_NET any (msg:\"bad\"; sid:1;)\n",
+            "alert dns $HOME_NET any -> any any (msg:\"c2\"; dns.query; content:\"evil.example.com\"; nocase; sid:2;)\n",
+        );
+        assert_eq!(
+            values(&rules, text),
+            vec!["1.2.3.4", "5.6.0.0/16", "evil.example.com"]
+        );
+    }
+
+    #[test]
+    fn registry_urls_are_unique_per_category() {
+        for (i, a) in FEEDS.iter().enumerate() {
+            for b in &FEEDS[i + 1..] {
+                assert!(a.name != b.name, "duplicate feed name {}", a.name);
+                assert!(
+                    a.url != b.url || a.category != b.category,
+                    "duplicate feed {}",
+                    a.url
+                );
+            }
+        }
+        assert!(feeds_for("IP").count() > 10);
+    }
+}
@@ -0,0 +1,271 @@
+use super::defang::refang;
+use super::documents::document_iocs;
+use super::model::{Ioc, IocKind};
+use crate::download::repo_slug;
+use git2::build::RepoBuilder;
+use git2::{FetchOptions, RemoteCallbacks};
+use regex::Regex;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use walkdir::WalkDir;
+
+/// Public repos of vendor and researcher IOC dumps.
+pub const GIT_IOC_REPOS: &[&str] = &[
+    "https://github.com/avast/ioc.git",
+    "https://github.com/DoctorWebLtd/malware-iocs.git",
+    "https://github.com/eset/malware-ioc.git",
+    "https://github.com/mandiant/iocs.git",
+    "https://github.com/GoSecure/malware-ioc.git",
+    "https://github.com/Neo23x0/signature-base.git",
+    "https://github.com/advanced-threat-research/IOCs.git",
+    "https://github.com/pan-unit42/iocs.git",
+    "https://github.com/prodaft/malware-ioc.git",
+    "https://github.com/RedDrip7/APT_Digital_Weapon.git",
+    "https://github.com/sophoslabs/IoCs.git",
+    "https://github.com/StrangerealIntel/DailyIOC.git",
+    "https://github.com/Infinit3i/IOC-Detections.git",
+];
+
+/// Kinds that can be pulled out of free text. File names and registry keys only come
+/// from structured files (OpenIOC, STIX, MISP).
+pub const EXTRACTABLE: [IocKind; 9] = [
+    IocKind::Ipv4,
+    IocKind::Ipv6,
+    IocKind::Domain,
+    IocKind::Url,
+    IocKind::Email,
+    IocKind::Md5,
+    IocKind::Sha1,
+    IocKind::Sha256,
+    IocKind::Cve,
+];
+
+/// Files larger than this are skipped.
+const MAX_FILE_SIZE: u64 = 20 * 1024 * 1024;
+
+fn pattern(kind: IocKind) -> &'static Regex {
+    static PATTERNS: OnceLock<Vec<(IocKind, Regex)>> = OnceLock::new();
+    let patterns = PATTERNS.get_or_init(|| {
+        [
+            (IocKind::Ipv4, r"\b\d{1,3}(?:\.\d{1,3}){3}\b"),
+            (IocKind::Ipv6, r"[0-9A-Fa-f]{0,4}(?::[0-9A-Fa-f]{0,4}){2,7}"),
+            (
+                IocKind::Domain,
+                r"(?i)\b(?:[a-z0-9](?:[a-z0-9-]{0,61}[a-z0-9])?\.)+[a-z]{2,}\b",
+            ),
+            (
+                IocKind::Url,
+                r#"(?i)\b(?:https?|ftp)://[^\s"'<>`()\[\]{}|\\^]+"#,
+            ),
+            (
+                IocKind::Email,
+                r"(?i)\b[a-z0-9._%+-]+@(?:[a-z0-9-]+\.)+[a-z]{2,}\b",
+            ),
+            (IocKind::Md5, r"\b[a-fA-F0-9]{32}\b"),
+            (IocKind::Sha1, r"\b[a-fA-F0-9]{40}\b"),
+            (IocKind::Sha256, r"\b[a-fA-F0-9]{64}\b"),
+            (IocKind::Cve, r"(?i)\bCVE-\d{4}-\d{4,7}\b"),
+        ]
+        .into_iter()
+        .map(|(kind, re)| (kind, Regex::new(re).unwrap()))
+        .collect()
+    });
+    &patterns.iter().find(|(k, _)| *k == kind).unwrap().1
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Rejects regex hits that are usually something else: version numbers for IPv4,
+/// times, MACs and C++ paths for IPv6.
+fn plausible(kind: IocKind, line: &str, start: usize, end: usize) -> bool {
+    let before = line[..start].chars().next_back();
+    let after = line[end..].chars().next();
+    match kind {
+        IocKind::Ipv4 => {
+            let prefix = line[..start].trim_end().to_ascii_lowercase();
+            // Single-digit quads such as 1.2.3.4 in prose are nearly always versions.
+            !line[start..end].split('.').all(|octet| octet.len() == 1)
+                && !matches!(before, Some('v' | 'V' | '.'))
+                // `1.2.3.4.5` is a version, `C2 was 1.2.3.4.` ends a sentence.
+                && !line[end..]
+                    .strip_prefix('.')
+                    .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+                && !prefix.ends_with("version")
+        }
+        IocKind::Ipv6 => {
+            let value = &line[start..end];
+            !before.is_some_and(|c| is_word_char(c) || c == ':')
+                && !after.is_some_and(|c| is_word_char(c) || c == '.')
+                && (value.matches(':').count() == 7
+                    || (value.contains("::") && value.chars().any(|c| c.is_ascii_digit())))
+        }
+        _ => true,
+    }
+}
+
+/// Validated IOCs of `kinds` found in `text` after refanging, attributed to `source`.
+pub fn extract_iocs(text: &str, kinds: &BTreeSet<IocKind>, source: &str) -> Vec<Ioc> {
+    let mut iocs = Vec::new();
+    for line in refang(text).lines() {
+        for &kind in kinds.iter().filter(|k| EXTRACTABLE.contains(k)) {
+            for m in pattern(kind).find_iter(line) {
+                let raw = match kind {
+                    IocKind::Url => m.as_str().trim_end_matches(['.', ',', ';', ':', '!', '?']),
+                    _ => m.as_str(),
+                };
+                if !plausible(kind, line, m.start(), m.start() + raw.len()) {
+                    continue;
+                }
+                // Regex hits are only candidates; keep the ones that validate, normalized.
+                if let Ok(ioc) = Ioc::new(kind, raw, source) {
+                    iocs.push(ioc);
+                }
+            }
+        }
+    }
+    iocs
+}
+
+/// Shallow-clone `repo_url` into `dest`. The clone stops early once `cancel` is set.
+fn shallow_clone(repo_url: &str, dest: &Path, cancel: &AtomicBool) -> io::Result<()> {
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.transfer_progress(|_| !cancel.load(Ordering::Relaxed));
+    let mut fetch = FetchOptions::new();
+    fetch.depth(1).remote_callbacks(callbacks);
+    RepoBuilder::new()
+        .fetch_options(fetch)
+        .clone(repo_url, dest)
+        .map(|_| ())
+        .map_err(|e| io::Error::other(format!("clone {}: {}", repo_url, e)))
+}
+
+/// IOCs of `kinds` in every report file of `dir`, skipping `.git`. Each IOC records
+/// the file it came from, relative to `dir`, in [`Ioc::files`].
+pub fn scan_dir(dir: &Path, kinds: &BTreeSet<IocKind>, source: &str) -> Vec<Ioc> {
+    let mut iocs = Vec::new();
+    let files = WalkDir::new(dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git")
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| e.metadata().is_ok_and(|m| m.len() <= MAX_FILE_SIZE));
+    for entry in files {
+        let Ok(bytes) = fs::read(entry.path()) else {
+            continue;
+        };
+        let relative = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+        let file = relative.to_string_lossy().replace('\\', "/");
+        iocs.extend(
+            document_iocs(entry.path(), &bytes, kinds, source)
+                .into_iter()
+                .map(|ioc| ioc.with_files(vec![file.clone()])),
+        );
+    }
+    iocs
+}
+
+/// Clone `repo_url` into a temporary folder, extract IOCs of `kinds` attributed to
+/// the repo slug and remove the clone again.
+pub fn scan_repo(
+    repo_url: &str,
+    kinds: &BTreeSet<IocKind>,
+    cancel: &AtomicBool,
+) -> io::Result<Vec<Ioc>> {
+    let tmp = tempfile::tempdir()?;
+    shallow_clone(repo_url, tmp.path(), cancel)?;
+    Ok(scan_dir(tmp.path(), kinds, &repo_slug(repo_url)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(text: &str, kind: IocKind) -> Vec<String> {
+        extract_iocs(text, &BTreeSet::from([kind]), "repo")
+            .into_iter()
+            .map(|i| i.value)
+            .collect()
+    }
+
+    #[test]
+    fn extracts_each_kind() {
+        let text = "C2 at 45.9.20.13 and fe80::1ff:fe23:4567:890a, built with v1.2.3.4 / version 2.0.0.1, fixed in 6.1.7.0 build 12.34.56.78.9, then 91.92.93.94.\n\
+                    Payload: https://evil.example.com/a.php?id=1. Mail admin@Evil-Mail.com about CVE-2024-3094.\n\
+                    12:30:45 00:1a:2b:3c:4d:5e std::vector 2001:db8::1\n\
+                    md5 D41D8CD98F00B204E9800998ECF8427E";
+
+        assert_eq!(
+            values(text, IocKind::Ipv4),
+            vec!["45.9.20.13", "91.92.93.94"]
+        );
+        assert_eq!(
+            values(text, IocKind::Ipv6),
+            vec!["fe80::1ff:fe23:4567:890a", "2001:db8::1"]
+        );
+        assert_eq!(
+            values(text, IocKind::Url),
+            vec!["https://evil.example.com/a.php?id=1"]
+        );
+        assert_eq!(values(text, IocKind::Email), vec!["admin@evil-mail.com"]);
+        assert_eq!(values(text, IocKind::Cve), vec!["CVE-2024-3094"]);
+        assert_eq!(
+            values(text, IocKind::Md5),
+            vec!["d41d8cd98f00b204e9800998ecf8427e"]
+        );
+        assert!(values(text, IocKind::Domain).contains(&"evil.example.com".to_string()));
+        assert!(values(text, IocKind::Sha256).is_empty());
+        assert_eq!(
+            values(
+                "seen hxxps[://]bad[.]example[.]net/x and 45.9.20[.]13",
+                IocKind::Url
+            ),
+            vec!["https://bad.example.net/x"]
+        );
+        assert_eq!(
+            values("seen 45.9.20[.]13", IocKind::Ipv4),
+            vec!["45.9.20.13"]
+        );
+    }
+
+    #[test]
+    fn scans_text_files_outside_git() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        fs::write(dir.path().join(".git").join("config"), "url = 8.8.4.4").unwrap();
+        fs::write(dir.path().join("iocs.txt"), "103.1.2.3\nevil.example.org").unwrap();
+        fs::write(dir.path().join("blob.bin"), [0xff, 0xfe, 0x00, 0x31]).unwrap();
+        fs::create_dir(dir.path().join("misp")).unwrap();
+        fs::write(
+            dir.path().join("misp").join("event.json"),
+            r#"{"Event": {"Attribute": [{"type": "ip-dst", "value": "103.4.5.6"},
+                {"type": "filename", "value": "dropper.exe"}]}}"#,
+        )
+        .unwrap();
+
+        let kinds = BTreeSet::from([IocKind::Ipv4, IocKind::Domain]);
+        let iocs = scan_dir(dir.path(), &kinds, "owner_repo");
+        let found: Vec<(&str, &str)> = iocs
+            .iter()
+            .map(|i| (i.value.as_str(), i.files[0].as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("103.1.2.3", "iocs.txt"),
+                ("evil.example.org", "iocs.txt"),
+                ("103.4.5.6", "misp/event.json"),
+            ]
+        );
+        assert!(
+            iocs.iter()
+                .all(|i| i.source == "owner_repo" && i.tags.is_empty())
+        );
+    }
+}
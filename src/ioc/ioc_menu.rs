@@ -1,3 +1,12 @@
+use super::database::ExpirySettings;
+use super::git_iocs::GIT_IOC_REPOS;
+use super::model::IocKind;
+use super::suricata::SuricataIocSettings;
+use super::warninglist::WarninglistSettings;
+use super::yara::YaraIocSettings;
+use crate::download::{DownloadFormat, IocExportOptions, IocSources};
+use crate::settings::Settings;
+use std::collections::BTreeSet;
 use std::sync::{Arc, Mutex};
 
 // In ioc_menu.rs
@@ -6,6 +15,18 @@ pub struct IOCSelectorApp {
     pub selected: Vec<bool>,
     pub output_format: OutputFormat,
     pub custom_path: Option<String>,
+    /// Minimum number of feeds that must report an indicator.
+    pub min_feeds: usize,
+    /// Also clone the vendor IOC repos and extract the selected types from them.
+    pub scan_git_repos: bool,
+    pub defang: bool,
+    pub warninglists: WarninglistSettings,
+    /// Days each type stays in the exports after it was last seen.
+    pub expiry: ExpirySettings,
+    pub suricata: SuricataIocSettings,
+    pub yara: YaraIocSettings,
+    /// Per-file results of the last download.
+    pub summary: Arc<Mutex<Vec<String>>>,
     pub progress: Arc<Mutex<Option<(usize, usize)>>>,
     pub confirm_overwrite: bool,
     pub pending_urls: Option<Vec<(String, String)>>,
@@ -19,19 +40,30 @@ pub struct IOCSelectorApp {
 pub enum OutputFormat {
     Txt,
     Csv,
+    Stix,
+    Misp,
 }
 
 impl Default for IOCSelectorApp {
     fn default() -> Self {
+        let settings = Settings::load();
         Self {
             ioc_types: vec![
                 "Filename", "SHA256", "SHA1", "MD5", "IP", "Domain", "URL", "Email", "Registry",
-                "All",
+                "CVE", "JA3", "All",
             ],
-            selected: vec![false; 10],
+            selected: vec![false; 12],
             progress: Arc::new(Mutex::new(None)),
             output_format: OutputFormat::Txt,
             custom_path: None,
+            min_feeds: 1,
+            scan_git_repos: false,
+            defang: false,
+            warninglists: settings.warninglists,
+            expiry: settings.ioc_expiry,
+            suricata: settings.ioc_suricata,
+            yara: settings.ioc_yara,
+            summary: Arc::new(Mutex::new(Vec::new())),
             confirm_overwrite: false,
             pending_urls: None,
             overwrite_queue: Vec::new(),
@@ -41,3 +73,61 @@ impl Default for IOCSelectorApp {
         }
     }
 }
+
+impl IOCSelectorApp {
+    pub fn selected_types(&self) -> Vec<&'static str> {
+        self.ioc_types
+            .iter()
+            .zip(&self.selected)
+            .filter(|(name, selected)| **selected && **name != "All")
+            .map(|(name, _)| *name)
+            .collect()
+    }
+
+    /// Registry feeds in `feeds`, plus the git repos when they can supply a selected type.
+    pub fn sources(&self, feeds: Vec<(String, String)>) -> IocSources {
+        let repo_kinds: BTreeSet<IocKind> = self
+            .selected_types()
+            .into_iter()
+            .flat_map(IocKind::for_category)
+            .collect();
+        let repos = if self.scan_git_repos && !repo_kinds.is_empty() {
+            GIT_IOC_REPOS.iter().map(|r| r.to_string()).collect()
+        } else {
+            Vec::new()
+        };
+        IocSources {
+            feeds,
+            repos,
+            repo_kinds,
+        }
+    }
+
+    pub fn output_path(&self) -> String {
+        self.custom_path
+            .clone()
+            .unwrap_or_else(|| "ioc_output".to_string())
+    }
+
+    pub fn download_format(&self) -> DownloadFormat {
+        match self.output_format {
+            OutputFormat::Txt => DownloadFormat::Txt,
+            OutputFormat::Csv => DownloadFormat::Csv,
+            OutputFormat::Stix => DownloadFormat::Stix,
+            OutputFormat::Misp => DownloadFormat::Misp,
+        }
+    }
+
+    pub fn export_options(&self) -> IocExportOptions {
+        IocExportOptions {
+            format: self.download_format(),
+            output_path: self.output_path(),
+            min_feeds: self.min_feeds,
+            warninglists: self.warninglists.clone(),
+            defang: self.defang,
+            expiry: self.expiry.clone(),
+            suricata: self.suricata.clone(),
+            yara: self.yara.clone(),
+        }
+    }
+}
@@ -0,0 +1,334 @@
+use super::csv_export::LIST_SEPARATOR;
+use super::model::{Ioc, IocKind, PRODUCER_NAME, detect_kind, stable_uuid};
+use chrono::{DateTime, Utc};
+use md5::{Digest, Md5};
+use serde_json::{Map, Value, json};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Folder of the MISP feed inside the output path.
+pub const FEED_DIR: &str = "misp-feed";
+
+/// MISP attribute types an IOC kind is matched as, for `matching_attributes`.
+pub fn misp_attribute_types(kind: IocKind) -> &'static [&'static str] {
+    match kind {
+        IocKind::Ipv4 | IocKind::Ipv6 | IocKind::Cidr => &[
+            "ip-src",
+            "ip-dst",
+            "ip-src|port",
+            "ip-dst|port",
+            "domain|ip",
+        ],
+        IocKind::Domain => &["domain", "hostname", "domain|ip"],
+        IocKind::Url => &["url", "uri", "link"],
+        IocKind::Email => &["email", "email-src", "email-dst"],
+        IocKind::Md5 => &["md5", "filename|md5"],
+        IocKind::Sha1 => &["sha1", "filename|sha1"],
+        IocKind::Sha256 => &["sha256", "filename|sha256"],
+        IocKind::Filename => &["filename"],
+        IocKind::Registry => &["regkey", "regkey|value"],
+        IocKind::Cve => &["vulnerability"],
+        IocKind::Ja3 => &["ja3-fingerprint-md5"],
+    }
+}
+
+fn ip_kind(value: &str) -> IocKind {
+    if value.contains('/') {
+        IocKind::Cidr
+    } else if value.contains(':') {
+        IocKind::Ipv6
+    } else {
+        IocKind::Ipv4
+    }
+}
+
+/// Indicators carried by one MISP attribute. Composite types such as
+/// `filename|sha256` yield both halves; unmapped types yield nothing.
+pub fn attribute_values(misp_type: &str, value: &str) -> Vec<(IocKind, String)> {
+    let (left, right) = value.split_once('|').unwrap_or((value, ""));
+    let one = |kind: IocKind, v: &str| vec![(kind, v.to_string())];
+    match misp_type {
+        "ip-src" | "ip-dst" => one(ip_kind(value), value),
+        "ip-src|port" | "ip-dst|port" => one(ip_kind(left), left),
+        "domain" | "hostname" => one(IocKind::Domain, value),
+        "hostname|port" => one(IocKind::Domain, left),
+        "domain|ip" => vec![
+            (IocKind::Domain, left.to_string()),
+            (ip_kind(right), right.to_string()),
+        ],
+        "url" | "uri" | "link" => one(IocKind::Url, value),
+        "email" | "email-src" | "email-dst" => one(IocKind::Email, value),
+        "md5" => one(IocKind::Md5, value),
+        "sha1" => one(IocKind::Sha1, value),
+        "sha256" => one(IocKind::Sha256, value),
+        "filename" => one(IocKind::Filename, value),
+        "filename|md5" => vec![
+            (IocKind::Filename, left.to_string()),
+            (IocKind::Md5, right.to_string()),
+        ],
+        "filename|sha1" => vec![
+            (IocKind::Filename, left.to_string()),
+            (IocKind::Sha1, right.to_string()),
+        ],
+        "filename|sha256" => vec![
+            (IocKind::Filename, left.to_string()),
+            (IocKind::Sha256, right.to_string()),
+        ],
+        "regkey" | "regkey|value" => one(IocKind::Registry, left),
+        "vulnerability" => one(IocKind::Cve, value),
+        "ja3-fingerprint-md5" => one(IocKind::Ja3, value),
+        // Free-form comments and text fields sometimes hold a bare indicator.
+        "text" | "other" => detect_kind(value)
+            .map(|kind| one(kind, value))
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+fn events(doc: &Value) -> Vec<&Value> {
+    match doc {
+        Value::Array(items) => items.iter().flat_map(events).collect(),
+        Value::Object(map) if map.contains_key("Event") => vec![&map["Event"]],
+        Value::Object(map) if map.contains_key("response") => events(&map["response"]),
+        Value::Object(map) if map.contains_key("Attribute") => vec![doc],
+        _ => Vec::new(),
+    }
+}
+
+/// Whether a JSON document looks like a MISP event export.
+pub fn is_misp_event(doc: &Value) -> bool {
+    !events(doc).is_empty()
+}
+
+/// IOCs in the attributes of every event (and its objects) in a MISP JSON export.
+pub fn event_iocs(doc: &Value, source: &str) -> Vec<Ioc> {
+    let mut iocs = Vec::new();
+    for event in events(doc) {
+        let objects = event["Object"].as_array().into_iter().flatten();
+        let attributes = event["Attribute"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .chain(objects.flat_map(|o| o["Attribute"].as_array().into_iter().flatten()));
+        for attr in attributes {
+            let (Some(misp_type), Some(value)) = (attr["type"].as_str(), attr["value"].as_str())
+            else {
+                continue;
+            };
+            for (kind, raw) in attribute_values(misp_type, value) {
+                if let Ok(ioc) = Ioc::new(kind, &raw, source) {
+                    iocs.push(ioc);
+                }
+            }
+        }
+    }
+    iocs
+}
+
+/// MISP attribute `(type, category)` an IOC is exported as.
+pub fn export_attribute(kind: IocKind) -> (&'static str, &'static str) {
+    match kind {
+        IocKind::Ipv4 | IocKind::Ipv6 | IocKind::Cidr => ("ip-dst", "Network activity"),
+        IocKind::Domain => ("domain", "Network activity"),
+        IocKind::Url => ("url", "Network activity"),
+        IocKind::Email => ("email-src", "Payload delivery"),
+        IocKind::Md5 => ("md5", "Payload delivery"),
+        IocKind::Sha1 => ("sha1", "Payload delivery"),
+        IocKind::Sha256 => ("sha256", "Payload delivery"),
+        IocKind::Filename => ("filename", "Payload delivery"),
+        IocKind::Registry => ("regkey", "Persistence mechanism"),
+        IocKind::Cve => ("vulnerability", "External analysis"),
+        IocKind::Ja3 => ("ja3-fingerprint-md5", "Network activity"),
+    }
+}
+
+fn tags(names: impl IntoIterator<Item = String>) -> Vec<Value> {
+    names
+        .into_iter()
+        .map(|name| json!({"name": name}))
+        .collect()
+}
+
+/// `feed:<name>` for every feed that reported the IOC.
+fn feed_tags(ioc: &Ioc) -> impl Iterator<Item = String> + '_ {
+    ioc.source
+        .split(LIST_SEPARATOR)
+        .filter(|s| !s.is_empty())
+        .map(|feed| format!("feed:{}", feed))
+}
+
+/// One MISP event holding the IOCs of one type. The event UUID only depends on the
+/// type, so every run replaces the previous event with the current indicators.
+pub fn feed_event(iocs: &[Ioc], ioc_type: &str, now: DateTime<Utc>) -> Value {
+    let timestamp = now.timestamp().to_string();
+    let mut event_tags = BTreeSet::new();
+    let attributes: Vec<Value> = iocs
+        .iter()
+        .map(|ioc| {
+            let (misp_type, category) = export_attribute(ioc.kind);
+            let attribute_tags: Vec<String> = feed_tags(ioc).chain(ioc.tags.clone()).collect();
+            event_tags.extend(feed_tags(ioc));
+            json!({
+                "uuid": stable_uuid(&format!("{}:{}", ioc.kind.as_str(), ioc.value)),
+                "type": misp_type,
+                "category": category,
+                "value": ioc.value,
+                "to_ids": ioc.kind != IocKind::Cve,
+                "timestamp": ioc.last_seen.timestamp().to_string(),
+                "first_seen": ioc.first_seen.to_rfc3339(),
+                "last_seen": ioc.last_seen.to_rfc3339(),
+                "comment": "",
+                "Tag": tags(attribute_tags),
+            })
+        })
+        .collect();
+    json!({
+        "Event": {
+            "uuid": stable_uuid(&format!("misp-event:{}", ioc_type)),
+            "info": format!("{} {} indicators", PRODUCER_NAME, ioc_type),
+            "date": now.format("%Y-%m-%d").to_string(),
+            "timestamp": timestamp,
+            "publish_timestamp": timestamp,
+            "published": true,
+            "threat_level_id": "4",
+            "analysis": "2",
+            "Orgc": {"name": PRODUCER_NAME, "uuid": stable_uuid(PRODUCER_NAME)},
+            "Tag": tags(event_tags),
+            "Attribute": attributes,
+        }
+    })
+}
+
+/// Write `event` as `<uuid>.json` into the feed folder `dir` and rebuild the index.
+pub fn write_feed_event(dir: &Path, event: &Value) -> io::Result<()> {
+    let uuid = event["Event"]["uuid"].as_str().unwrap_or_default();
+    fs::create_dir_all(dir)?;
+    let json = serde_json::to_string_pretty(event).map_err(io::Error::other)?;
+    fs::write(dir.join(format!("{}.json", uuid)), json)?;
+    rebuild_feed_index(dir)
+}
+
+/// Regenerate `manifest.json` and `hashes.csv` from the event files in `dir`.
+pub fn rebuild_feed_index(dir: &Path) -> io::Result<()> {
+    let mut paths: Vec<_> = fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "json"))
+        .filter(|p| p.file_name().is_some_and(|n| n != "manifest.json"))
+        .collect();
+    paths.sort();
+
+    let mut manifest = Map::new();
+    let mut hashes = String::new();
+    for path in paths {
+        let Ok(doc) = serde_json::from_str::<Value>(&fs::read_to_string(&path)?) else {
+            continue;
+        };
+        let event = &doc["Event"];
+        let Some(uuid) = event["uuid"].as_str() else {
+            continue;
+        };
+        let mut summary = Map::new();
+        for key in [
+            "info",
+            "date",
+            "timestamp",
+            "analysis",
+            "threat_level_id",
+            "Orgc",
+            "Tag",
+        ] {
+            summary.insert(key.to_string(), event[key].clone());
+        }
+        manifest.insert(uuid.to_string(), Value::Object(summary));
+        for attribute in event["Attribute"].as_array().into_iter().flatten() {
+            let value = attribute["value"].as_str().unwrap_or_default();
+            // MISP hashes each half of composite values such as filename|sha256.
+            for part in value.split('|') {
+                hashes.push_str(&format!("{:x},{}\n", Md5::digest(part.as_bytes()), uuid));
+            }
+        }
+    }
+    let manifest = serde_json::to_string_pretty(&manifest).map_err(io::Error::other)?;
+    fs::write(dir.join("manifest.json"), manifest)?;
+    fs::write(dir.join("hashes.csv"), hashes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_event_attributes_and_objects() {
+        let doc: Value = serde_json::from_str(
+            r#"{"response": [{"Event": {
+                "info": "Campaign",
+                "Attribute": [
+                    {"type": "ip-dst|port", "value": "45.9.20.13|443"},
+                    {"type": "domain|ip", "value": "evil.example.com|2001:db8::1"},
+                    {"type": "comment", "value": "not an indicator"}
+                ],
+                "Object": [{"name": "file", "Attribute": [
+                    {"type": "filename|sha256", "value": "a.exe|AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"},
+                    {"type": "regkey|value", "value": "HKLM\\Software\\Run|evil"}
+                ]}]
+            }}]}"#,
+        )
+        .unwrap();
+        assert!(is_misp_event(&doc));
+        let found: Vec<(IocKind, String)> = event_iocs(&doc, "event.json")
+            .into_iter()
+            .map(|i| (i.kind, i.value))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (IocKind::Ipv4, "45.9.20.13".to_string()),
+                (IocKind::Domain, "evil.example.com".to_string()),
+                (IocKind::Ipv6, "2001:db8::1".to_string()),
+                (IocKind::Filename, "a.exe".to_string()),
+                (IocKind::Sha256, "a".repeat(64)),
+                (
+                    IocKind::Registry,
+                    "HKEY_LOCAL_MACHINE\\Software\\Run".to_string()
+                ),
+            ]
+        );
+        assert!(!is_misp_event(&serde_json::json!({"type": "bundle"})));
+    }
+
+    #[test]
+    fn writes_a_feed_that_reads_back() {
+        let iocs = vec![
+            Ioc::new(IocKind::Ipv4, "45.9.20.13", "firehol-level1;greensnow").unwrap(),
+            Ioc::new(IocKind::Registry, "HKCU\\Software\\Run", "vendor_repo").unwrap(),
+        ];
+        let event = feed_event(&iocs, "IP", Utc::now());
+        let attributes = event["Event"]["Attribute"].as_array().unwrap();
+        assert_eq!(attributes[0]["type"], "ip-dst");
+        assert_eq!(attributes[0]["Tag"][1]["name"], "feed:greensnow");
+        assert_eq!(attributes[1]["type"], "regkey");
+        assert_eq!(event["Event"]["Tag"].as_array().unwrap().len(), 3);
+
+        let dir = tempfile::tempdir().unwrap();
+        write_feed_event(dir.path(), &event).unwrap();
+        write_feed_event(dir.path(), &feed_event(&iocs[..1], "IP", Utc::now())).unwrap();
+        let uuid = event["Event"]["uuid"].as_str().unwrap();
+        let manifest: Value =
+            serde_json::from_str(&fs::read_to_string(dir.path().join("manifest.json")).unwrap())
+                .unwrap();
+        assert_eq!(manifest.as_object().unwrap().len(), 1);
+        assert_eq!(manifest[uuid]["info"], "Detection Wizard IP indicators");
+        let hashes = fs::read_to_string(dir.path().join("hashes.csv")).unwrap();
+        assert_eq!(
+            hashes,
+            format!("{:x},{}\n", Md5::digest(b"45.9.20.13"), uuid)
+        );
+
+        let event_file = fs::read(dir.path().join(format!("{}.json", uuid))).unwrap();
+        let doc: Value = serde_json::from_slice(&event_file).unwrap();
+        assert_eq!(event_iocs(&doc, "feed")[0].value, "45.9.20.13");
+    }
+}
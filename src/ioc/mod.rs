@@ -1,2 +1,15 @@
+pub mod csv_export;
+pub mod database;
+pub mod defang;
+pub mod documents;
+pub mod feeds;
+pub mod git_iocs;
 pub mod ioc_menu;
-pub mod ui_ioc;
\ No newline at end of file
+pub mod misp;
+pub mod model;
+pub mod stix;
+pub mod store;
+pub mod suricata;
+pub mod ui_ioc;
+pub mod warninglist;
+pub mod yara;
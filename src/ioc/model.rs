@@ -0,0 +1,551 @@
+use super::defang::refang;
+use chrono::{DateTime, Utc};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::sync::OnceLock;
+use url::{Host, Url};
+use uuid::Uuid;
+
+/// Confidence given to IOCs when a feed does not say otherwise.
+pub const DEFAULT_CONFIDENCE: u8 = 50;
+
+/// Author of exported STIX and MISP objects.
+pub const PRODUCER_NAME: &str = "Detection Wizard";
+
+/// Name-based UUID, stable across exports so a TIP or MISP updates the same object
+/// when an IOC is exported again.
+pub fn stable_uuid(name: &str) -> Uuid {
+    let namespace = Uuid::new_v5(
+        &Uuid::NAMESPACE_URL,
+        b"https://github.com/Infinit3i/Detection-Wizard",
+    );
+    Uuid::new_v5(&namespace, name.as_bytes())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IocKind {
+    Ipv4,
+    Ipv6,
+    Cidr,
+    Domain,
+    Url,
+    Email,
+    Md5,
+    Sha1,
+    Sha256,
+    Filename,
+    Registry,
+    Cve,
+    /// JA3 TLS client fingerprint (MD5 of the ClientHello fields).
+    Ja3,
+}
+
+impl IocKind {
+    pub const ALL: [IocKind; 13] = [
+        Self::Ipv4,
+        Self::Ipv6,
+        Self::Cidr,
+        Self::Domain,
+        Self::Url,
+        Self::Email,
+        Self::Md5,
+        Self::Sha1,
+        Self::Sha256,
+        Self::Filename,
+        Self::Registry,
+        Self::Cve,
+        Self::Ja3,
+    ];
+
+    /// Lowercase name used in files and exports, e.g. `sha256`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ipv4 => "ipv4",
+            Self::Ipv6 => "ipv6",
+            Self::Cidr => "cidr",
+            Self::Domain => "domain",
+            Self::Url => "url",
+            Self::Email => "email",
+            Self::Md5 => "md5",
+            Self::Sha1 => "sha1",
+            Self::Sha256 => "sha256",
+            Self::Filename => "filename",
+            Self::Registry => "registry",
+            Self::Cve => "cve",
+            Self::Ja3 => "ja3",
+        }
+    }
+
+    /// IOC type shown in the UI and used for output file names, e.g. `IP`.
+    pub fn category(&self) -> &'static str {
+        match self {
+            Self::Ipv4 | Self::Ipv6 | Self::Cidr => "IP",
+            Self::Domain => "Domain",
+            Self::Url => "URL",
+            Self::Email => "Email",
+            Self::Md5 => "MD5",
+            Self::Sha1 => "SHA1",
+            Self::Sha256 => "SHA256",
+            Self::Filename => "Filename",
+            Self::Registry => "Registry",
+            Self::Cve => "CVE",
+            Self::Ja3 => "JA3",
+        }
+    }
+
+    /// Kinds collected under a UI category.
+    pub fn for_category(category: &str) -> Vec<IocKind> {
+        Self::ALL
+            .into_iter()
+            .filter(|k| k.category() == category)
+            .collect()
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|k| k.as_str().eq_ignore_ascii_case(name.trim()))
+    }
+
+    pub fn is_hash(&self) -> bool {
+        matches!(self, Self::Md5 | Self::Sha1 | Self::Sha256)
+    }
+
+    pub fn is_network(&self) -> bool {
+        matches!(self, Self::Ipv4 | Self::Ipv6 | Self::Cidr)
+    }
+
+    /// Check `raw` strictly as this kind and return its normalized form. Defanged
+    /// network indicators (`evil[.]com`, `hxxp://`) are refanged first.
+    pub fn validate(&self, raw: &str) -> Result<String, IocError> {
+        let refanged = match self {
+            Self::Ipv4 | Self::Ipv6 | Self::Cidr | Self::Domain | Self::Url | Self::Email => {
+                refang(raw)
+            }
+            _ => Cow::Borrowed(raw),
+        };
+        let value = refanged.trim();
+        if value.is_empty() {
+            return Err(IocError::Empty);
+        }
+        let invalid = |reason: &'static str| IocError::Invalid {
+            kind: *self,
+            value: value.to_string(),
+            reason,
+        };
+        match self {
+            Self::Ipv4 => value
+                .parse::<Ipv4Addr>()
+                .map(|ip| ip.to_string())
+                .map_err(|_| invalid("not a dotted-quad address with octets 0-255")),
+            Self::Ipv6 => value
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse::<Ipv6Addr>()
+                .map(|ip| ip.to_string())
+                .map_err(|_| invalid("not an IPv6 address")),
+            Self::Cidr => {
+                if !value.contains('/') {
+                    return Err(invalid("missing /prefix"));
+                }
+                value
+                    .parse::<IpNet>()
+                    .map(|net| net.trunc().to_string())
+                    .map_err(|_| invalid("not an address/prefix network"))
+            }
+            Self::Domain => {
+                normalize_domain(value).ok_or_else(|| invalid("not a domain with a known TLD"))
+            }
+            Self::Url => normalize_url(value)
+                .ok_or_else(|| invalid("not an http(s)/ftp URL with a valid host")),
+            Self::Email => {
+                normalize_email(value).ok_or_else(|| invalid("not a mailbox at a valid domain"))
+            }
+            Self::Md5 => normalize_hex(value, 32).ok_or_else(|| invalid("not 32 hex characters")),
+            Self::Sha1 => normalize_hex(value, 40).ok_or_else(|| invalid("not 40 hex characters")),
+            Self::Sha256 => {
+                normalize_hex(value, 64).ok_or_else(|| invalid("not 64 hex characters"))
+            }
+            Self::Filename => {
+                let bad = value.len() > 255
+                    || value == "."
+                    || value == ".."
+                    || value.contains(['/', '\\', '<', '>', ':', '"', '|', '?', '*'])
+                    || value.chars().any(char::is_control);
+                if bad {
+                    Err(invalid("not a bare file name"))
+                } else {
+                    Ok(value.to_string())
+                }
+            }
+            Self::Registry => normalize_registry(value)
+                .ok_or_else(|| invalid("does not start with a registry hive")),
+            Self::Cve => normalize_cve(value).ok_or_else(|| invalid("not CVE-YYYY-NNNN")),
+            Self::Ja3 => normalize_hex(value, 32).ok_or_else(|| invalid("not 32 hex characters")),
+        }
+    }
+}
+
+impl fmt::Display for IocKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IocError {
+    Empty,
+    Invalid {
+        kind: IocKind,
+        value: String,
+        reason: &'static str,
+    },
+    /// `Ioc::detect` found no kind that accepts the value.
+    Unrecognized(String),
+}
+
+impl fmt::Display for IocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("empty indicator"),
+            Self::Invalid {
+                kind,
+                value,
+                reason,
+            } => write!(f, "invalid {} {:?}: {}", kind, value, reason),
+            Self::Unrecognized(value) => write!(f, "unrecognized indicator {:?}", value),
+        }
+    }
+}
+
+impl std::error::Error for IocError {}
+
+/// A validated indicator. `value` is always in the normalized form of its kind.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ioc {
+    pub kind: IocKind,
+    pub value: String,
+    /// Feed or file the indicator came from.
+    pub source: String,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    /// 0-100.
+    pub confidence: u8,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Report files the indicator was found in, relative to its repo. Kept for
+    /// context only; exports leave it out.
+    #[serde(default)]
+    pub files: Vec<String>,
+}
+
+impl Ioc {
+    pub fn new(kind: IocKind, raw: &str, source: &str) -> Result<Self, IocError> {
+        let value = kind.validate(raw)?;
+        let now = Utc::now();
+        Ok(Self {
+            kind,
+            value,
+            source: source.to_string(),
+            first_seen: now,
+            last_seen: now,
+            confidence: DEFAULT_CONFIDENCE,
+            tags: Vec::new(),
+            files: Vec::new(),
+        })
+    }
+
+    /// Build an IOC from a value of unknown kind, see [`detect_kind`].
+    pub fn detect(raw: &str, source: &str) -> Result<Self, IocError> {
+        let kind = detect_kind(raw).ok_or_else(|| match raw.trim() {
+            "" => IocError::Empty,
+            value => IocError::Unrecognized(value.to_string()),
+        })?;
+        Self::new(kind, raw, source)
+    }
+
+    pub fn with_confidence(mut self, confidence: u8) -> Self {
+        self.confidence = confidence.min(100);
+        self
+    }
+
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn with_files(mut self, files: Vec<String>) -> Self {
+        self.files = files;
+        self
+    }
+
+    pub fn with_seen(mut self, first_seen: DateTime<Utc>, last_seen: DateTime<Utc>) -> Self {
+        self.first_seen = first_seen.min(last_seen);
+        self.last_seen = last_seen.max(first_seen);
+        self
+    }
+}
+
+/// Guess the kind of a bare value. Hashes, addresses, networks and registry keys
+/// are tried before URLs, emails and domains; anything else with a dot that is
+/// a legal file name counts as a file name.
+pub fn detect_kind(raw: &str) -> Option<IocKind> {
+    let value = raw.trim();
+    if value.is_empty() {
+        return None;
+    }
+    let order = [
+        IocKind::Md5,
+        IocKind::Sha1,
+        IocKind::Sha256,
+        IocKind::Ipv4,
+        IocKind::Ipv6,
+        IocKind::Cidr,
+        IocKind::Registry,
+        IocKind::Cve,
+    ];
+    if let Some(kind) = order.into_iter().find(|k| k.validate(value).is_ok()) {
+        return Some(kind);
+    }
+    if value.contains("://") {
+        return IocKind::Url.validate(value).ok().map(|_| IocKind::Url);
+    }
+    if value.contains('@') {
+        return IocKind::Email.validate(value).ok().map(|_| IocKind::Email);
+    }
+    if IocKind::Domain.validate(value).is_ok() {
+        return Some(IocKind::Domain);
+    }
+    (value.contains('.') && IocKind::Filename.validate(value).is_ok()).then_some(IocKind::Filename)
+}
+
+/// `cve-2021-44228` -> `CVE-2021-44228`.
+fn normalize_cve(raw: &str) -> Option<String> {
+    let upper = raw.to_ascii_uppercase();
+    let mut parts = upper.strip_prefix("CVE-")?.split('-');
+    let (year, id) = (parts.next()?, parts.next()?);
+    let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    (parts.next().is_none()
+        && year.len() == 4
+        && digits(year)
+        && (4..=7).contains(&id.len())
+        && digits(id))
+    .then_some(upper)
+}
+
+fn tlds() -> &'static HashSet<&'static str> {
+    static TLDS: OnceLock<HashSet<&'static str>> = OnceLock::new();
+    TLDS.get_or_init(|| {
+        include_str!("tlds.txt")
+            .lines()
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .collect()
+    })
+}
+
+/// Whether `tld` (ASCII, lowercase or not) is a delegated top-level domain.
+pub fn is_known_tld(tld: &str) -> bool {
+    tlds().contains(tld.to_ascii_lowercase().as_str())
+}
+
+/// Lowercase, punycode-encode and check label syntax and the TLD.
+pub fn normalize_domain(raw: &str) -> Option<String> {
+    let raw = raw.trim().trim_end_matches('.');
+    if raw.is_empty() || raw.contains(['/', ':', '@', ' ']) {
+        return None;
+    }
+    let ascii = match Host::parse(raw).ok()? {
+        Host::Domain(d) => d,
+        _ => return None,
+    };
+    if ascii.len() > 253 {
+        return None;
+    }
+    let labels: Vec<&str> = ascii.split('.').collect();
+    if labels.len() < 2 {
+        return None;
+    }
+    let valid_label = |l: &&str| {
+        !l.is_empty()
+            && l.len() <= 63
+            && !l.starts_with('-')
+            && !l.ends_with('-')
+            && l.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    };
+    if !labels.iter().all(valid_label) {
+        return None;
+    }
+    let tld = labels.last()?;
+    (is_known_tld(tld) && !tld.chars().all(|c| c.is_ascii_digit())).then_some(ascii)
+}
+
+fn normalize_url(raw: &str) -> Option<String> {
+    let url = Url::parse(raw.trim()).ok()?;
+    if !matches!(url.scheme(), "http" | "https" | "ftp") {
+        return None;
+    }
+    match url.host()? {
+        Host::Domain(d) => {
+            normalize_domain(d)?;
+        }
+        Host::Ipv4(_) | Host::Ipv6(_) => {}
+    }
+    Some(url.to_string())
+}
+
+fn normalize_email(raw: &str) -> Option<String> {
+    let value = raw.trim().trim_start_matches("mailto:");
+    let (local, domain) = value.rsplit_once('@')?;
+    let local_ok = !local.is_empty()
+        && local.len() <= 64
+        && !local.starts_with('.')
+        && !local.ends_with('.')
+        && !local.contains("..")
+        && local
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+/=?^_`{|}~.-".contains(c));
+    if !local_ok {
+        return None;
+    }
+    Some(format!("{}@{}", local, normalize_domain(domain)?))
+}
+
+fn normalize_hex(raw: &str, len: usize) -> Option<String> {
+    (raw.len() == len && raw.chars().all(|c| c.is_ascii_hexdigit()))
+        .then(|| raw.to_ascii_lowercase())
+}
+
+/// Short hive aliases and the names they normalize to.
+const HIVES: [(&str, &str); 6] = [
+    ("HKLM", "HKEY_LOCAL_MACHINE"),
+    ("HKCU", "HKEY_CURRENT_USER"),
+    ("HKCR", "HKEY_CLASSES_ROOT"),
+    ("HKU", "HKEY_USERS"),
+    ("HKCC", "HKEY_CURRENT_CONFIG"),
+    ("HKPD", "HKEY_PERFORMANCE_DATA"),
+];
+
+fn normalize_registry(raw: &str) -> Option<String> {
+    let value = raw.trim();
+    let (hive, rest) = value.split_once('\\')?;
+    let full = HIVES
+        .iter()
+        .find(|(short, long)| hive.eq_ignore_ascii_case(short) || hive.eq_ignore_ascii_case(long))
+        .map(|(_, long)| *long)?;
+    let rest = rest.trim_matches('\\');
+    if rest.is_empty() || rest.contains("\\\\") || rest.chars().any(char::is_control) {
+        return None;
+    }
+    Some(format!("{}\\{}", full, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_network_kinds() {
+        assert_eq!(IocKind::Ipv4.validate(" 8.8.8.8 ").unwrap(), "8.8.8.8");
+        assert!(IocKind::Ipv4.validate("256.1.1.1").is_err());
+        assert!(IocKind::Ipv4.validate("1.2.3").is_err());
+        assert!(IocKind::Ipv4.validate("01.2.3.4").is_err());
+        assert_eq!(
+            IocKind::Ipv6.validate("2001:DB8:0:0::1").unwrap(),
+            "2001:db8::1"
+        );
+        assert_eq!(IocKind::Cidr.validate("10.1.2.3/8").unwrap(), "10.0.0.0/8");
+        assert!(IocKind::Cidr.validate("10.0.0.0/33").is_err());
+        assert!(IocKind::Cidr.validate("10.0.0.0").is_err());
+    }
+
+    #[test]
+    fn validates_names_and_urls() {
+        assert_eq!(
+            IocKind::Domain.validate("Evil.Example.COM.").unwrap(),
+            "evil.example.com"
+        );
+        assert_eq!(
+            IocKind::Domain.validate("bücher.de").unwrap(),
+            "xn--bcher-kva.de"
+        );
+        assert!(IocKind::Domain.validate("malware.exe").is_err());
+        assert!(IocKind::Domain.validate("localhost").is_err());
+        assert!(IocKind::Domain.validate("-bad.com").is_err());
+        assert_eq!(
+            IocKind::Url.validate("HTTP://Evil.com:8080/a?b=1").unwrap(),
+            "http://evil.com:8080/a?b=1"
+        );
+        assert!(IocKind::Url.validate("http://evil.notatld/x").is_err());
+        assert!(IocKind::Url.validate("javascript:alert(1)").is_err());
+        assert_eq!(
+            IocKind::Email
+                .validate("Bob.Smith@Mail.Example.org")
+                .unwrap(),
+            "Bob.Smith@mail.example.org"
+        );
+        assert!(IocKind::Email.validate("a..b@example.org").is_err());
+        assert!(IocKind::Email.validate("user@example.invalidtld").is_err());
+    }
+
+    #[test]
+    fn validates_hashes_files_and_registry() {
+        let md5 = "D41D8CD98F00B204E9800998ECF8427E";
+        assert_eq!(
+            IocKind::Md5.validate(md5).unwrap(),
+            md5.to_ascii_lowercase()
+        );
+        assert!(IocKind::Sha1.validate(md5).is_err());
+        assert!(IocKind::Sha256.validate(&"g".repeat(64)).is_err());
+        assert_eq!(
+            IocKind::Filename.validate("invoice.pdf.exe").unwrap(),
+            "invoice.pdf.exe"
+        );
+        assert!(IocKind::Filename.validate("C:\\temp\\a.exe").is_err());
+        assert_eq!(
+            IocKind::Registry.validate("hklm\\Software\\Run\\").unwrap(),
+            "HKEY_LOCAL_MACHINE\\Software\\Run"
+        );
+        assert!(IocKind::Registry.validate("HKXX\\Software").is_err());
+        assert_eq!(
+            IocKind::Cve.validate("cve-2021-44228").unwrap(),
+            "CVE-2021-44228"
+        );
+        assert!(IocKind::Cve.validate("CVE-21-44228").is_err());
+    }
+
+    #[test]
+    fn detects_kinds() {
+        let cases = [
+            ("1.2.3.4", Some(IocKind::Ipv4)),
+            ("::1", Some(IocKind::Ipv6)),
+            ("192.168.0.0/16", Some(IocKind::Cidr)),
+            ("https://a.example.com/x", Some(IocKind::Url)),
+            ("x@example.com", Some(IocKind::Email)),
+            ("example.co.uk", Some(IocKind::Domain)),
+            ("payload.dll", Some(IocKind::Filename)),
+            ("HKCU\\Software\\x", Some(IocKind::Registry)),
+            (&"a".repeat(40), Some(IocKind::Sha1)),
+            ("CVE-2024-3094", Some(IocKind::Cve)),
+            ("hello", None),
+        ];
+        for (value, kind) in cases {
+            assert_eq!(detect_kind(value), kind, "{}", value);
+        }
+
+        let ioc = Ioc::detect("ABCDEF0123456789ABCDEF0123456789", "feed").unwrap();
+        assert_eq!(ioc.kind, IocKind::Md5);
+        assert_eq!(ioc.value, "abcdef0123456789abcdef0123456789");
+        assert_eq!(ioc.with_confidence(250).confidence, 100);
+        assert_eq!(Ioc::detect("  ", "feed"), Err(IocError::Empty));
+        assert!(matches!(
+            Ioc::detect("nope", "feed"),
+            Err(IocError::Unrecognized(_))
+        ));
+    }
+}
@@ -0,0 +1,314 @@
+use super::csv_export::LIST_SEPARATOR;
+use super::feeds::source_url;
+use super::model::{Ioc, IocKind, PRODUCER_NAME, stable_uuid};
+use chrono::{DateTime, SecondsFormat, Utc};
+use regex::Regex;
+use serde_json::{Value, json};
+use std::sync::OnceLock;
+
+/// IOC kind of a STIX 2.x object path such as `file:hashes.'SHA-256'`.
+fn path_kind(object: &str, property: &str) -> Option<IocKind> {
+    let property = property.replace(['\'', '"'], "").to_ascii_uppercase();
+    match (object, property.as_str()) {
+        ("ipv4-addr", "VALUE") => Some(IocKind::Ipv4),
+        ("ipv6-addr", "VALUE") => Some(IocKind::Ipv6),
+        ("domain-name", "VALUE") => Some(IocKind::Domain),
+        ("url", "VALUE") => Some(IocKind::Url),
+        ("email-addr", "VALUE") | ("email-message", "FROM_REF.VALUE") => Some(IocKind::Email),
+        ("file", "HASHES.MD5") => Some(IocKind::Md5),
+        ("file", "HASHES.SHA-1" | "HASHES.SHA1") => Some(IocKind::Sha1),
+        ("file", "HASHES.SHA-256" | "HASHES.SHA256") => Some(IocKind::Sha256),
+        ("file", "NAME") => Some(IocKind::Filename),
+        ("windows-registry-key", "KEY") => Some(IocKind::Registry),
+        ("x-ja3-fingerprint", "VALUE") => Some(IocKind::Ja3),
+        _ => None,
+    }
+}
+
+/// `(kind, value)` for every `object:path = 'value'` or `ISSUBSET 'cidr'` comparison
+/// in a STIX pattern.
+pub fn pattern_values(pattern: &str) -> Vec<(IocKind, String)> {
+    static COMPARISON: OnceLock<Regex> = OnceLock::new();
+    let re = COMPARISON.get_or_init(|| {
+        Regex::new(r"([a-z0-9-]+):([A-Za-z0-9_.'\-]+)(?:\s*=\s*|\s+ISSUBSET\s+)'((?:[^'\\]|\\.)*)'")
+            .unwrap()
+    });
+    re.captures_iter(pattern)
+        .filter_map(|c| {
+            let mut kind = path_kind(&c[1], &c[2])?;
+            let value = c[3].replace("\\'", "'").replace("\\\\", "\\");
+            if kind.is_network() && value.contains('/') {
+                kind = IocKind::Cidr;
+            }
+            Some((kind, value))
+        })
+        .collect()
+}
+
+/// Whether a JSON document looks like a STIX 2.x bundle or object list.
+pub fn is_stix(doc: &Value) -> bool {
+    doc["type"] == "bundle" || doc["spec_version"].is_string()
+}
+
+/// IOCs from the indicator patterns, cyber observables and vulnerabilities of a
+/// STIX 2.x bundle.
+pub fn bundle_iocs(doc: &Value, source: &str) -> Vec<Ioc> {
+    let objects: Vec<&Value> = match &doc["objects"] {
+        Value::Array(objects) => objects.iter().collect(),
+        _ => vec![doc],
+    };
+    let mut found = Vec::new();
+    for object in objects {
+        let object_type = object["type"].as_str().unwrap_or_default();
+        match object_type {
+            "indicator" => {
+                if let Some(pattern) = object["pattern"].as_str() {
+                    found.extend(pattern_values(pattern));
+                }
+            }
+            "vulnerability" => {
+                if let Some(name) = object["name"].as_str() {
+                    found.push((IocKind::Cve, name.to_string()));
+                }
+            }
+            "file" => {
+                if let Some(hashes) = object["hashes"].as_object() {
+                    for (algo, hash) in hashes {
+                        let path = format!("hashes.{}", algo);
+                        if let (Some(kind), Some(hash)) = (path_kind("file", &path), hash.as_str())
+                        {
+                            found.push((kind, hash.to_string()));
+                        }
+                    }
+                }
+                if let Some(name) = object["name"].as_str() {
+                    found.push((IocKind::Filename, name.to_string()));
+                }
+            }
+            _ => {
+                let value = object["value"].as_str().or(object["key"].as_str());
+                let property = if object["key"].is_string() {
+                    "key"
+                } else {
+                    "value"
+                };
+                if let (Some(kind), Some(value)) = (path_kind(object_type, property), value) {
+                    found.push((kind, value.to_string()));
+                }
+            }
+        }
+    }
+    found
+        .into_iter()
+        .filter_map(|(kind, value)| Ioc::new(kind, &value, source).ok())
+        .collect()
+}
+
+fn stix_id(object_type: &str, name: &str) -> String {
+    format!("{}--{}", object_type, stable_uuid(name))
+}
+
+fn stix_time(t: &DateTime<Utc>) -> String {
+    t.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// STIX pattern matching an IOC, `None` for CVEs, which become Vulnerabilities.
+pub fn indicator_pattern(kind: IocKind, value: &str) -> Option<String> {
+    let path = match kind {
+        IocKind::Ipv4 => "ipv4-addr:value",
+        IocKind::Ipv6 => "ipv6-addr:value",
+        IocKind::Cidr if value.contains(':') => "ipv6-addr:value",
+        IocKind::Cidr => "ipv4-addr:value",
+        IocKind::Domain => "domain-name:value",
+        IocKind::Url => "url:value",
+        IocKind::Email => "email-addr:value",
+        IocKind::Md5 => "file:hashes.MD5",
+        IocKind::Sha1 => "file:hashes.'SHA-1'",
+        IocKind::Sha256 => "file:hashes.'SHA-256'",
+        IocKind::Filename => "file:name",
+        IocKind::Registry => "windows-registry-key:key",
+        // STIX has no JA3 object; this custom one is what most TIPs use.
+        IocKind::Ja3 => "x-ja3-fingerprint:value",
+        IocKind::Cve => return None,
+    };
+    // Networks match any address inside them, not a literal `a.b.c.d/n` value.
+    let operator = if kind == IocKind::Cidr {
+        "ISSUBSET"
+    } else {
+        "="
+    };
+    Some(format!("[{} {} {}]", path, operator, quote(value)))
+}
+
+/// The Identity object exported IOCs are created by.
+pub fn identity() -> Value {
+    json!({
+        "type": "identity",
+        "spec_version": "2.1",
+        "id": stix_id("identity", PRODUCER_NAME),
+        "created": "2024-01-01T00:00:00.000Z",
+        "modified": "2024-01-01T00:00:00.000Z",
+        "name": PRODUCER_NAME,
+        "identity_class": "system",
+    })
+}
+
+/// One external reference per feed that reported the IOC.
+fn feed_references(ioc: &Ioc) -> Vec<Value> {
+    ioc.source
+        .split(LIST_SEPARATOR)
+        .filter(|s| !s.is_empty())
+        .map(|feed| match source_url(feed) {
+            Some(url) => json!({"source_name": feed, "url": url}),
+            None => json!({"source_name": feed}),
+        })
+        .collect()
+}
+
+/// An Indicator for the IOC, or a Vulnerability for a CVE ID.
+pub fn ioc_object(ioc: &Ioc, created_by: &str) -> Value {
+    let mut references = feed_references(ioc);
+    let mut object = match indicator_pattern(ioc.kind, &ioc.value) {
+        Some(pattern) => json!({
+            "type": "indicator",
+            "id": stix_id("indicator", &format!("{}:{}", ioc.kind.as_str(), ioc.value)),
+            "name": ioc.value,
+            "indicator_types": ["malicious-activity"],
+            "pattern": pattern,
+            "pattern_type": "stix",
+            "valid_from": stix_time(&ioc.first_seen),
+        }),
+        None => {
+            references.insert(0, json!({"source_name": "cve", "external_id": ioc.value}));
+            json!({
+                "type": "vulnerability",
+                "id": stix_id("vulnerability", &ioc.value),
+                "name": ioc.value,
+            })
+        }
+    };
+    // Only the IOC type: free-form tags (warninglist hits, feed notes) are not labels.
+    let labels = vec![ioc.kind.as_str().to_string()];
+    object["spec_version"] = json!("2.1");
+    object["created_by_ref"] = json!(created_by);
+    object["created"] = json!(stix_time(&ioc.first_seen));
+    object["modified"] = json!(stix_time(&ioc.last_seen));
+    object["labels"] = json!(labels);
+    object["confidence"] = json!(ioc.confidence);
+    if !references.is_empty() {
+        object["external_references"] = json!(references);
+    }
+    object
+}
+
+/// STIX 2.1 bundle with the Detection Wizard Identity and one object per IOC.
+/// `name` keeps bundle ids of different exports apart.
+pub fn to_bundle(iocs: &[Ioc], name: &str) -> Value {
+    let identity = identity();
+    let created_by = identity["id"].as_str().unwrap_or_default().to_string();
+    let mut objects = vec![identity];
+    objects.extend(iocs.iter().map(|ioc| ioc_object(ioc, &created_by)));
+    json!({
+        "type": "bundle",
+        "id": stix_id("bundle", name),
+        "objects": objects,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_patterns_and_observables() {
+        assert_eq!(
+            pattern_values(
+                "[ipv4-addr:value = '198.51.100.0/24'] OR [file:hashes.'SHA-256' = 'ab'] \
+                 AND [windows-registry-key:key = 'HKCU\\\\Run\\\\x'] \
+                 OR [ipv6-addr:value ISSUBSET '2001:db8::/32']"
+            ),
+            vec![
+                (IocKind::Cidr, "198.51.100.0/24".to_string()),
+                (IocKind::Sha256, "ab".to_string()),
+                (IocKind::Registry, "HKCU\\Run\\x".to_string()),
+                (IocKind::Cidr, "2001:db8::/32".to_string()),
+            ]
+        );
+
+        let doc = serde_json::json!({
+            "type": "bundle",
+            "objects": [
+                {"type": "indicator", "pattern": "[domain-name:value = 'evil.example.com']"},
+                {"type": "url", "value": "http://evil.example.com/x"},
+                {"type": "file", "name": "dropper.exe", "hashes": {"MD5": "D41D8CD98F00B204E9800998ECF8427E"}},
+                {"type": "vulnerability", "name": "CVE-2024-3094"},
+                {"type": "identity", "name": "Vendor"}
+            ]
+        });
+        assert!(is_stix(&doc));
+        let values: Vec<String> = bundle_iocs(&doc, "bundle.json")
+            .into_iter()
+            .map(|i| i.value)
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                "evil.example.com",
+                "http://evil.example.com/x",
+                "d41d8cd98f00b204e9800998ecf8427e",
+                "dropper.exe",
+                "CVE-2024-3094"
+            ]
+        );
+    }
+
+    #[test]
+    fn exports_indicators_that_read_back() {
+        let iocs = vec![
+            Ioc::new(IocKind::Sha256, &"A".repeat(64), "botvrij-sha256").unwrap(),
+            Ioc::new(IocKind::Registry, "HKCU\\Software\\It's", "vendor_repo").unwrap(),
+            Ioc::new(IocKind::Cve, "CVE-2024-3094", "").unwrap(),
+            Ioc::new(IocKind::Cidr, "10.0.0.0/8", "firehol-level1")
+                .unwrap()
+                .with_tags(vec!["warninglist:RFC 1918".into()]),
+        ];
+        let bundle = to_bundle(&iocs, "SHA256-2024-06-01");
+        let objects = bundle["objects"].as_array().unwrap();
+        assert_eq!(objects[0]["name"], PRODUCER_NAME);
+        assert_eq!(
+            objects[1]["pattern"],
+            format!("[file:hashes.'SHA-256' = '{}']", "a".repeat(64))
+        );
+        assert_eq!(objects[1]["created_by_ref"], objects[0]["id"]);
+        assert_eq!(objects[1]["valid_from"], objects[1]["created"]);
+        assert_eq!(
+            objects[1]["external_references"][0]["url"],
+            "https://www.botvrij.eu/data/ioclist.sha256"
+        );
+        assert_eq!(objects[2]["labels"][0], "registry");
+        assert_eq!(objects[3]["type"], "vulnerability");
+        assert_eq!(
+            objects[4]["pattern"],
+            "[ipv4-addr:value ISSUBSET '10.0.0.0/8']"
+        );
+        assert_eq!(objects[4]["labels"], serde_json::json!(["cidr"]));
+
+        let values: Vec<String> = bundle_iocs(&bundle, "x")
+            .into_iter()
+            .map(|i| i.value)
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                "a".repeat(64),
+                "HKEY_CURRENT_USER\\Software\\It's".to_string(),
+                "CVE-2024-3094".to_string(),
+                "10.0.0.0/8".to_string()
+            ]
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use super::csv_export::LIST_SEPARATOR;
+use super::model::{Ioc, IocKind};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Report files kept per entry; a hash quoted in hundreds of reports keeps the first.
+pub const MAX_FILES: usize = 20;
+
+/// One indicator with every feed that reported it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoreEntry {
+    pub kind: IocKind,
+    pub value: String,
+    pub sources: BTreeSet<String>,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub confidence: u8,
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+    /// Up to [`MAX_FILES`] report files, see [`Ioc::files`].
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub files: BTreeSet<String>,
+}
+
+impl StoreEntry {
+    fn add_files(&mut self, files: Vec<String>) {
+        for file in files {
+            if self.files.len() >= MAX_FILES {
+                break;
+            }
+            self.files.insert(file);
+        }
+    }
+
+    /// "Seen in N feeds".
+    pub fn feed_count(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// Flatten back to an [`Ioc`] whose `source` lists every feed.
+    pub fn to_ioc(&self) -> Ioc {
+        Ioc {
+            kind: self.kind,
+            value: self.value.clone(),
+            source: self
+                .sources
+                .iter()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(&LIST_SEPARATOR.to_string()),
+            first_seen: self.first_seen,
+            last_seen: self.last_seen,
+            confidence: self.confidence,
+            tags: self.tags.iter().cloned().collect(),
+            files: self.files.iter().cloned().collect(),
+        }
+    }
+}
+
+/// IOCs deduplicated by kind and normalized value across feeds. Serialized as a
+/// plain list of entries.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "Vec<StoreEntry>", into = "Vec<StoreEntry>")]
+pub struct IocStore {
+    entries: BTreeMap<(IocKind, String), StoreEntry>,
+}
+
+impl IocStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add one observation. A `source` holding several `;`-separated feeds
+    /// (as read back from CSV) counts each of them.
+    pub fn insert(&mut self, ioc: Ioc) {
+        let sources = ioc
+            .source
+            .split(LIST_SEPARATOR)
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string);
+        match self.entries.get_mut(&(ioc.kind, ioc.value.clone())) {
+            Some(entry) => {
+                entry.sources.extend(sources);
+                entry.first_seen = entry.first_seen.min(ioc.first_seen);
+                entry.last_seen = entry.last_seen.max(ioc.last_seen);
+                entry.confidence = entry.confidence.max(ioc.confidence);
+                entry.tags.extend(ioc.tags);
+                entry.add_files(ioc.files);
+            }
+            None => {
+                let mut entry = StoreEntry {
+                    kind: ioc.kind,
+                    value: ioc.value.clone(),
+                    sources: sources.collect(),
+                    first_seen: ioc.first_seen,
+                    last_seen: ioc.last_seen,
+                    confidence: ioc.confidence,
+                    tags: ioc.tags.into_iter().collect(),
+                    files: BTreeSet::new(),
+                };
+                entry.add_files(ioc.files);
+                self.entries.insert((ioc.kind, ioc.value), entry);
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: IocStore) {
+        for entry in other.entries.into_values() {
+            self.insert(entry.to_ioc());
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, kind: IocKind, value: &str) -> Option<&StoreEntry> {
+        self.entries.get(&(kind, value.to_string()))
+    }
+
+    /// Entries sorted by kind, then value.
+    pub fn entries(&self) -> impl Iterator<Item = &StoreEntry> {
+        self.entries.values()
+    }
+
+    pub fn entries_mut(&mut self) -> impl Iterator<Item = &mut StoreEntry> {
+        self.entries.values_mut()
+    }
+
+    /// Entries reported by at least `min_feeds` feeds.
+    pub fn seen_in(&self, min_feeds: usize) -> impl Iterator<Item = &StoreEntry> {
+        self.entries().filter(move |e| e.feed_count() >= min_feeds)
+    }
+
+    /// Flattened IOCs reported by at least `min_feeds` feeds.
+    pub fn to_iocs(&self, min_feeds: usize) -> Vec<Ioc> {
+        self.seen_in(min_feeds).map(StoreEntry::to_ioc).collect()
+    }
+
+    /// Number of entries per feed count, for a "seen in N feeds" histogram.
+    pub fn feed_count_histogram(&self) -> BTreeMap<usize, usize> {
+        let mut histogram = BTreeMap::new();
+        for entry in self.entries() {
+            *histogram.entry(entry.feed_count()).or_insert(0) += 1;
+        }
+        histogram
+    }
+
+    /// Drop entries matching `predicate`, returning how many were removed.
+    pub fn remove_where(&mut self, mut predicate: impl FnMut(&StoreEntry) -> bool) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|_, e| !predicate(e));
+        before - self.entries.len()
+    }
+}
+
+impl From<Vec<StoreEntry>> for IocStore {
+    fn from(entries: Vec<StoreEntry>) -> Self {
+        let entries = entries
+            .into_iter()
+            .map(|e| ((e.kind, e.value.clone()), e))
+            .collect();
+        Self { entries }
+    }
+}
+
+impl From<IocStore> for Vec<StoreEntry> {
+    fn from(store: IocStore) -> Self {
+        store.entries.into_values().collect()
+    }
+}
+
+impl FromIterator<Ioc> for IocStore {
+    fn from_iter<T: IntoIterator<Item = Ioc>>(iter: T) -> Self {
+        let mut store = Self::new();
+        iter.into_iter().for_each(|ioc| store.insert(ioc));
+        store
+    }
+}
+
+impl Extend<Ioc> for IocStore {
+    fn extend<T: IntoIterator<Item = Ioc>>(&mut self, iter: T) {
+        iter.into_iter().for_each(|ioc| self.insert(ioc));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(value: &str, source: &str) -> Ioc {
+        Ioc::new(IocKind::Ipv4, value, source).unwrap()
+    }
+
+    #[test]
+    fn dedupes_and_counts_feeds() {
+        let store: IocStore = [
+            ip("1.2.3.4", "firehol-level1"),
+            ip("1.2.3.4", "blocklist-de-all"),
+            ip("1.2.3.4", "firehol-level1"),
+            ip("1.2.3.4", "cinsscore-badguys;et-compromised-ips"),
+            ip("5.6.7.8", "greensnow").with_tags(vec!["scanner".into()]),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(store.len(), 2);
+        let entry = store.get(IocKind::Ipv4, "1.2.3.4").unwrap();
+        assert_eq!(entry.feed_count(), 4);
+        assert_eq!(store.seen_in(2).count(), 1);
+        assert_eq!(store.to_iocs(1).len(), 2);
+        assert_eq!(
+            store.to_iocs(2)[0].source,
+            "blocklist-de-all;cinsscore-badguys;et-compromised-ips;firehol-level1"
+        );
+        assert_eq!(
+            store.feed_count_histogram(),
+            BTreeMap::from([(1, 1), (4, 1)])
+        );
+
+        let mut other = IocStore::new();
+        other.insert(ip("5.6.7.8", "dan-tor-list"));
+        let mut merged = store.clone();
+        merged.merge(other);
+        assert_eq!(
+            merged.get(IocKind::Ipv4, "5.6.7.8").unwrap().feed_count(),
+            2
+        );
+        assert_eq!(merged.remove_where(|e| e.feed_count() < 3), 1);
+
+        let quoted: IocStore = (0..MAX_FILES + 5)
+            .map(|n| ip("9.9.9.9", "repo").with_files(vec![format!("report-{}.txt", n)]))
+            .collect();
+        let entry = quoted.get(IocKind::Ipv4, "9.9.9.9").unwrap();
+        assert_eq!(entry.files.len(), MAX_FILES);
+        assert!(entry.tags.is_empty());
+    }
+}
@@ -0,0 +1,543 @@
+use super::model::IocKind;
+use super::store::StoreEntry;
+use super::warninglist::is_flagged;
+use chrono::{DateTime, Utc};
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use url::Url;
+
+/// Folder of the generated rules inside the IOC output path.
+pub const RULES_DIR: &str = "suricata";
+pub const RULES_FILE: &str = "detection-wizard-iocs.rules";
+/// IP reputation categories and list, for `reputation-categories-file` and
+/// `reputation-files` in suricata.yaml.
+pub const IPREP_CATEGORIES_FILE: &str = "ioc-iprep-categories.txt";
+pub const IPREP_FILE: &str = "ioc-iprep.list";
+
+/// Suricata allows at most 60 reputation categories; feeds past the last one share it.
+const MAX_IPREP_CATEGORIES: usize = 60;
+
+/// IOC to Suricata rule generation. Sids are taken from `sid_start` to `sid_end`;
+/// Suricata keeps 1000000-1999999 for local rules.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SuricataIocSettings {
+    pub enabled: bool,
+    pub sid_start: u32,
+    pub sid_end: u32,
+}
+
+impl Default for SuricataIocSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sid_start: 1_000_000,
+            sid_end: 1_999_999,
+        }
+    }
+}
+
+/// Rules plus the hash lists their `filemd5`/`filesha1`/`filesha256` keywords load and
+/// the IP reputation files their `iprep` keywords need.
+#[derive(Debug, Default)]
+pub struct SuricataRuleSet {
+    pub rules: Vec<String>,
+    /// List file name and its lines: one hash, reputation entry or category per line.
+    pub lists: BTreeMap<&'static str, Vec<String>>,
+    /// IOCs left out because the sid range is full.
+    pub skipped: usize,
+}
+
+impl SuricataRuleSet {
+    /// Write the rules file, the hash lists and the reputation files into `dir`.
+    pub fn write(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let mut text = String::from(
+            "# Generated by Detection Wizard from collected IOCs.\n\
+             # Hash rules need file hashing (force-hash: [md5, sha1, sha256]) and the\n\
+             # .list files next to this file. IP rules need reputation-categories-file:\n\
+             # ioc-iprep-categories.txt and reputation-files: [ioc-iprep.list].\n",
+        );
+        for rule in &self.rules {
+            text.push_str(rule);
+            text.push('\n');
+        }
+        fs::write(dir.join(RULES_FILE), text)?;
+        for (name, hashes) in &self.lists {
+            fs::write(dir.join(name), hashes.join("\n") + "\n")?;
+        }
+        Ok(())
+    }
+}
+
+/// Escape a `content` value: quotes, `;`, `\` and `|` become hex bytes.
+fn content(value: &str) -> String {
+    let mut out = String::new();
+    for c in value.chars() {
+        match c {
+            '"' | ';' | '\\' | '|' => out.push_str(&format!("|{:02X}|", c as u32)),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Escape free text for `msg`.
+fn msg_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace(';', "\\;")
+}
+
+/// Metadata values may not hold commas, semicolons or spaces.
+fn metadata_value(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "-_.".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// `metadata:` naming every source feed, plus the sighting dates.
+fn metadata<'a>(feeds: impl IntoIterator<Item = &'a String>, seen: Option<&StoreEntry>) -> String {
+    let mut pairs: Vec<String> = feeds
+        .into_iter()
+        .map(|feed| format!("source_feed {}", metadata_value(feed)))
+        .collect();
+    if let Some(entry) = seen {
+        pairs.push(format!(
+            "created_at {}",
+            entry.first_seen.format("%Y_%m_%d")
+        ));
+        pairs.push(format!("updated_at {}", entry.last_seen.format("%Y_%m_%d")));
+    }
+    format!("metadata:{};", pairs.join(", "))
+}
+
+/// Header, message and detection keywords of one rule, before sid and metadata.
+struct Draft {
+    header: String,
+    msg: String,
+    body: String,
+}
+
+fn draft(header: &str, msg: String, body: String) -> Draft {
+    Draft {
+        header: header.to_string(),
+        msg,
+        body,
+    }
+}
+
+fn domain_drafts(domain: &str) -> Vec<Draft> {
+    // `dotprefix` makes `.evil.com` match the domain and all of its subdomains.
+    let suffix = content(&format!(".{}", domain));
+    vec![
+        draft(
+            "dns $HOME_NET any -> any any",
+            format!("DNS query for {}", domain),
+            format!(
+                "dns.query; dotprefix; content:\"{}\"; nocase; endswith;",
+                suffix
+            ),
+        ),
+        draft(
+            "tls $HOME_NET any -> $EXTERNAL_NET any",
+            format!("TLS SNI {}", domain),
+            format!(
+                "tls.sni; dotprefix; content:\"{}\"; nocase; endswith;",
+                suffix
+            ),
+        ),
+        draft(
+            "http $HOME_NET any -> $EXTERNAL_NET any",
+            format!("HTTP host {}", domain),
+            format!("http.host; dotprefix; content:\"{}\"; endswith;", suffix),
+        ),
+    ]
+}
+
+/// HTTP URLs match on host and URI. Paths of HTTPS URLs are encrypted, so those
+/// match on the SNI once per host.
+fn url_drafts(value: &str, sni_hosts: &mut BTreeSet<String>) -> Vec<Draft> {
+    let Ok(url) = Url::parse(value) else {
+        return Vec::new();
+    };
+    let Some(host) = url.host_str().map(str::to_string) else {
+        return Vec::new();
+    };
+    match url.scheme() {
+        "http" => {
+            let uri = &value[url[..url::Position::BeforePath].len()..];
+            vec![draft(
+                "http $HOME_NET any -> $EXTERNAL_NET any",
+                format!("HTTP request for {}", value),
+                format!(
+                    "http.host; content:\"{}\"; startswith; endswith; http.uri; content:\"{}\"; startswith;",
+                    content(&host),
+                    content(uri)
+                ),
+            )]
+        }
+        "https" if url.domain().is_some() && sni_hosts.insert(host.clone()) => {
+            vec![draft(
+                "tls $HOME_NET any -> $EXTERNAL_NET any",
+                format!("TLS SNI {}", host),
+                format!(
+                    "tls.sni; content:\"{}\"; nocase; startswith; endswith;",
+                    content(&host)
+                ),
+            )]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Addresses and networks go to the reputation list instead of a rule each.
+fn entry_drafts(entry: &StoreEntry, sni_hosts: &mut BTreeSet<String>) -> Vec<Draft> {
+    let value = &entry.value;
+    match entry.kind {
+        IocKind::Domain => domain_drafts(value),
+        IocKind::Url => url_drafts(value, sni_hosts),
+        IocKind::Ja3 => vec![draft(
+            "tls $HOME_NET any -> $EXTERNAL_NET any",
+            format!("JA3 fingerprint {}", value),
+            format!("ja3.hash; content:\"{}\";", value),
+        )],
+        _ => Vec::new(),
+    }
+}
+
+/// Hash list file and the keyword that loads it.
+fn hash_list(kind: IocKind) -> Option<(&'static str, &'static str)> {
+    match kind {
+        IocKind::Md5 => Some(("ioc-md5.list", "filemd5")),
+        IocKind::Sha1 => Some(("ioc-sha1.list", "filesha1")),
+        IocKind::Sha256 => Some(("ioc-sha256.list", "filesha256")),
+        _ => None,
+    }
+}
+
+/// Rules of one IOC (or one hash list) that are emitted together or not at all.
+struct Unit {
+    drafts: Vec<Draft>,
+    metadata: String,
+    /// Last sighting as `YYYYMMDD`, so `rev` grows whenever the IOC is seen again.
+    rev: u32,
+}
+
+fn rev(at: &DateTime<Utc>) -> u32 {
+    at.format("%Y%m%d").to_string().parse().unwrap_or(1)
+}
+
+/// Sid of a rule: a hash of its header and detection keywords mapped into the
+/// range, so a rule keeps its sid when other IOCs come or go. Taken sids probe
+/// forward to the next free one.
+fn stable_sid(key: &str, start: u64, len: u64, used: &mut HashSet<u64>) -> u64 {
+    let digest = Md5::digest(key.as_bytes());
+    let hash = u64::from_be_bytes(digest[..8].try_into().unwrap());
+    let mut offset = hash % len;
+    while !used.insert(start + offset) {
+        offset = (offset + 1) % len;
+    }
+    start + offset
+}
+
+/// Generate rules for the domain, URL and JA3 `entries`, hash lists with one rule per
+/// file-carrying protocol, and an IP reputation list with one `iprep` rule per source
+/// feed. Warninglist-flagged entries are skipped. Sids are stable hashes within
+/// `settings`' range; see [`stable_sid`].
+pub fn generate<'a>(
+    entries: impl IntoIterator<Item = &'a StoreEntry>,
+    settings: &SuricataIocSettings,
+) -> SuricataRuleSet {
+    let entries: Vec<&StoreEntry> = entries.into_iter().filter(|e| !is_flagged(e)).collect();
+    let mut set = SuricataRuleSet::default();
+    // (unit, IOCs it stands for)
+    let mut units: Vec<(Unit, usize)> = Vec::new();
+
+    let mut list_feeds: BTreeMap<&'static str, (&'static str, BTreeSet<String>, u32)> =
+        BTreeMap::new();
+    for entry in &entries {
+        if let Some((list, keyword)) = hash_list(entry.kind) {
+            set.lists.entry(list).or_default().push(entry.value.clone());
+            let (_, feeds, latest) =
+                list_feeds
+                    .entry(list)
+                    .or_insert((keyword, BTreeSet::new(), 1));
+            feeds.extend(entry.sources.iter().cloned());
+            *latest = (*latest).max(rev(&entry.last_seen));
+        }
+    }
+    for (list, (keyword, feeds, latest)) in &list_feeds {
+        let drafts = ["http", "smtp", "smb"]
+            .into_iter()
+            .map(|proto| {
+                draft(
+                    &format!("{} any any -> any any", proto),
+                    format!("file hash on {} ({})", list, proto),
+                    format!("{}:{};", keyword, list),
+                )
+            })
+            .collect();
+        let unit = Unit {
+            drafts,
+            metadata: metadata(feeds, None),
+            rev: *latest,
+        };
+        units.push((unit, set.lists[list].len()));
+    }
+
+    // One reputation category per source feed, scored by the entry's confidence
+    // (iprep takes 0-127).
+    let ip_entries: Vec<&StoreEntry> = entries
+        .iter()
+        .copied()
+        .filter(|e| e.kind.is_network())
+        .collect();
+    let ip_feeds: BTreeSet<&String> = ip_entries.iter().flat_map(|e| &e.sources).collect();
+    let overflow = ip_feeds.len() > MAX_IPREP_CATEGORIES;
+    let category_of: HashMap<&String, usize> = ip_feeds
+        .iter()
+        .enumerate()
+        .map(|(i, feed)| (*feed, (i + 1).min(MAX_IPREP_CATEGORIES)))
+        .collect();
+    // category id -> (short name, feeds, IPs, latest rev)
+    let mut categories: BTreeMap<usize, (String, BTreeSet<String>, usize, u32)> = BTreeMap::new();
+    for entry in &ip_entries {
+        for feed in &entry.sources {
+            let id = category_of[feed];
+            let (_, feeds, _, latest) = categories.entry(id).or_insert_with(|| {
+                let name = if overflow && id == MAX_IPREP_CATEGORIES {
+                    "dw_other_feeds".to_string()
+                } else {
+                    format!("dw_{}", metadata_value(feed))
+                };
+                (name, BTreeSet::new(), 0, 1)
+            });
+            feeds.insert(feed.clone());
+            *latest = (*latest).max(rev(&entry.last_seen));
+        }
+        let ids: BTreeSet<usize> = entry.sources.iter().map(|f| category_of[f]).collect();
+        for id in ids {
+            categories.get_mut(&id).unwrap().2 += 1;
+            set.lists.entry(IPREP_FILE).or_default().push(format!(
+                "{},{},{}",
+                entry.value,
+                id,
+                entry.confidence.min(127)
+            ));
+        }
+    }
+    for (id, (name, feeds, count, latest)) in &categories {
+        set.lists
+            .entry(IPREP_CATEGORIES_FILE)
+            .or_default()
+            .push(format!(
+                "{},{},IOC feeds: {}",
+                id,
+                name,
+                Vec::from_iter(feeds.iter().map(String::as_str)).join(" ")
+            ));
+        let unit = Unit {
+            drafts: vec![draft(
+                "ip $HOME_NET any <> any any",
+                format!("traffic with a host on the {} reputation list", name),
+                format!("iprep:any,{},>,0;", name),
+            )],
+            metadata: metadata(feeds, None),
+            rev: *latest,
+        };
+        units.push((unit, *count));
+    }
+
+    let mut sni_hosts = BTreeSet::new();
+    for entry in entries {
+        let drafts = entry_drafts(entry, &mut sni_hosts);
+        if !drafts.is_empty() {
+            let unit = Unit {
+                drafts,
+                metadata: metadata(&entry.sources, Some(entry)),
+                rev: rev(&entry.last_seen),
+            };
+            units.push((unit, 1));
+        }
+    }
+
+    let start = u64::from(settings.sid_start);
+    let len = (u64::from(settings.sid_end) + 1).saturating_sub(start);
+    let mut free = len;
+    units.retain(|(unit, iocs)| {
+        let needed = unit.drafts.len() as u64;
+        if needed > free {
+            set.skipped += iocs;
+            return false;
+        }
+        free -= needed;
+        true
+    });
+
+    // Hand out sids in key order, so which of two colliding rules probes further
+    // does not depend on the order the IOCs were collected in.
+    let mut keys: Vec<(String, usize, usize)> = Vec::new();
+    for (u, (unit, _)) in units.iter().enumerate() {
+        for (d, draft) in unit.drafts.iter().enumerate() {
+            keys.push((format!("{} {}", draft.header, draft.body), u, d));
+        }
+    }
+    keys.sort();
+    let mut used = HashSet::new();
+    let mut sids: HashMap<(usize, usize), u64> = HashMap::new();
+    for (key, u, d) in keys {
+        sids.insert((u, d), stable_sid(&key, start, len, &mut used));
+    }
+
+    for (u, (unit, _)) in units.iter().enumerate() {
+        for (d, draft) in unit.drafts.iter().enumerate() {
+            set.rules.push(format!(
+                "alert {} (msg:\"Detection Wizard IOC - {}\"; {}{}classtype:bad-unknown; {} sid:{}; rev:{};)",
+                draft.header,
+                msg_text(&draft.msg),
+                draft.body,
+                if draft.body.is_empty() { "" } else { " " },
+                unit.metadata,
+                sids[&(u, d)],
+                unit.rev
+            ));
+        }
+    }
+    set
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ioc::model::Ioc;
+    use crate::ioc::store::IocStore;
+
+    fn sid(rule: &str) -> u32 {
+        let (_, rest) = rule.split_once(" sid:").unwrap();
+        rest[..rest.find(';').unwrap()].parse().unwrap()
+    }
+
+    #[test]
+    fn generates_rules_lists_and_sids() {
+        let store: IocStore = [
+            (IocKind::Ipv4, "45.9.20.13", "firehol-level1;greensnow"),
+            (IocKind::Domain, "evil.example.com", "urlhaus"),
+            (
+                IocKind::Url,
+                "http://evil.example.com/a;b.php?x=1",
+                "urlhaus",
+            ),
+            (IocKind::Url, "https://bad.example.net/x", "openphish"),
+            (IocKind::Url, "https://bad.example.net/y", "openphish"),
+            (
+                IocKind::Md5,
+                "d41d8cd98f00b204e9800998ecf8427e",
+                "botvrij-md5",
+            ),
+            (
+                IocKind::Ja3,
+                "72a589da586844d7f0818ce684948eea",
+                "sslbl-ja3",
+            ),
+            (IocKind::Filename, "dropper.exe", "botvrij-filename"),
+            (IocKind::Ipv4, "8.8.8.8", "firehol-level1"),
+        ]
+        .into_iter()
+        .map(|(kind, value, source)| {
+            let ioc = Ioc::new(kind, value, source).unwrap();
+            match value {
+                "8.8.8.8" => ioc.with_tags(vec!["warninglist:Public DNS resolvers".into()]),
+                _ => ioc,
+            }
+        })
+        .collect();
+        let settings = SuricataIocSettings {
+            enabled: true,
+            sid_start: 5000,
+            sid_end: 5100,
+        };
+        let set = generate(store.entries(), &settings);
+
+        // 3 hash rules, 1 per IP feed, 3 domain, 1 HTTP URL, 1 SNI for both HTTPS URLs,
+        // 1 JA3; nothing for the flagged resolver.
+        assert_eq!(set.rules.len(), 11);
+        assert!(!set.rules.iter().any(|r| r.contains("8.8.8.8")));
+        assert!(set.rules[0].contains("filemd5:ioc-md5.list;"));
+        let sids: BTreeSet<u32> = set.rules.iter().map(|r| sid(r)).collect();
+        assert_eq!(sids.len(), 11);
+        assert!(sids.iter().all(|s| (5000..=5100).contains(s)));
+        let today = Utc::now().format("%Y%m%d").to_string();
+        assert!(set.rules[9].ends_with(&format!("rev:{};)", today)));
+        assert_eq!(
+            set.lists["ioc-md5.list"],
+            vec!["d41d8cd98f00b204e9800998ecf8427e"]
+        );
+        assert_eq!(
+            set.lists["ioc-iprep-categories.txt"],
+            vec![
+                "1,dw_firehol-level1,IOC feeds: firehol-level1",
+                "2,dw_greensnow,IOC feeds: greensnow"
+            ]
+        );
+        let confidence = store.entries().next().unwrap().confidence;
+        assert_eq!(
+            set.lists["ioc-iprep.list"],
+            vec![
+                format!("45.9.20.13,1,{}", confidence),
+                format!("45.9.20.13,2,{}", confidence)
+            ]
+        );
+        let ip = set
+            .rules
+            .iter()
+            .find(|r| r.contains("iprep:any,dw_greensnow,>,0;"))
+            .unwrap();
+        assert!(ip.starts_with("alert ip $HOME_NET any <> any any"));
+        assert!(ip.contains("metadata:source_feed greensnow;"));
+        assert!(set.rules.iter().any(|r| r.contains(
+            "http.host; content:\"evil.example.com\"; startswith; endswith; \
+             http.uri; content:\"/a|3B|b.php?x=1\"; startswith;"
+        )));
+        assert!(
+            set.rules
+                .iter()
+                .any(|r| r.contains("dns.query; dotprefix; content:\".evil.example.com\""))
+        );
+        assert!(
+            set.rules
+                .iter()
+                .any(|r| r.contains("ja3.hash; content:\"72a589da586844d7f0818ce684948eea\";"))
+        );
+
+        // Dropping an IOC leaves every other rule on its sid.
+        let fewer: Vec<&StoreEntry> = store
+            .entries()
+            .filter(|e| e.value != "evil.example.com")
+            .collect();
+        let smaller = generate(fewer, &settings);
+        assert_eq!(smaller.rules.len(), 8);
+        assert!(smaller.rules.iter().all(|r| set.rules.contains(r)));
+
+        let tight = SuricataIocSettings {
+            sid_start: 1,
+            sid_end: 5,
+            ..settings
+        };
+        let set = generate(store.entries(), &tight);
+        assert_eq!(set.rules.len(), 5);
+        assert_eq!(set.skipped, 4);
+    }
+}
@@ -0,0 +1,515 @@
+use super::misp::misp_attribute_types;
+use super::model::IocKind;
+use super::store::{IocStore, StoreEntry};
+use ipnet::IpNet;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::IpAddr;
+use std::path::Path;
+use url::{Host, Url};
+
+/// Lists written to the warninglist folder the first time it is used.
+pub const BUNDLED: [(&str, &str); 7] = [
+    (
+        "top-domains.json",
+        include_str!("warninglists/top-domains.json"),
+    ),
+    (
+        "public-dns-resolvers.json",
+        include_str!("warninglists/public-dns-resolvers.json"),
+    ),
+    (
+        "cloud-cloudflare.json",
+        include_str!("warninglists/cloud-cloudflare.json"),
+    ),
+    (
+        "private-reserved-ranges.json",
+        include_str!("warninglists/private-reserved-ranges.json"),
+    ),
+    (
+        "documentation-ranges.json",
+        include_str!("warninglists/documentation-ranges.json"),
+    ),
+    (
+        "documentation-domains.json",
+        include_str!("warninglists/documentation-domains.json"),
+    ),
+    (
+        "file-names-as-domains.json",
+        include_str!("warninglists/file-names-as-domains.json"),
+    ),
+];
+
+/// Tag prefix of IOCs kept by [`WarninglistAction::Flag`].
+pub const TAG_PREFIX: &str = "warninglist:";
+
+/// Remove every [`TAG_PREFIX`] tag, so flags can be derived again from the current lists.
+pub fn clear_flags(store: &mut IocStore) {
+    for entry in store.entries_mut() {
+        entry.tags.retain(|t| !t.starts_with(TAG_PREFIX));
+    }
+}
+
+/// Whether `entry` was flagged as being on a warninglist. Rule generators skip
+/// these, since a flagged IOC is most likely benign.
+pub fn is_flagged(entry: &StoreEntry) -> bool {
+    entry.tags.iter().any(|t| t.starts_with(TAG_PREFIX))
+}
+
+/// What happens to an IOC that is on a warninglist.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WarninglistAction {
+    Off,
+    /// Keep it, tagged `warninglist:<list name>`.
+    Flag,
+    #[default]
+    Drop,
+}
+
+impl WarninglistAction {
+    pub const ALL: [WarninglistAction; 3] = [Self::Off, Self::Flag, Self::Drop];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::Flag => "Flag",
+            Self::Drop => "Drop",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WarninglistSettings {
+    /// Folder of MISP warninglist JSON files.
+    pub dir: String,
+    pub action: WarninglistAction,
+}
+
+impl Default for WarninglistSettings {
+    fn default() -> Self {
+        Self {
+            dir: "./warninglists".into(),
+            action: WarninglistAction::Drop,
+        }
+    }
+}
+
+/// On-disk MISP warninglist layout; unknown keys such as `version` are ignored.
+#[derive(Deserialize)]
+struct RawWarninglist {
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(rename = "type", default = "string_type")]
+    list_type: String,
+    list: Vec<String>,
+    #[serde(default)]
+    matching_attributes: Vec<String>,
+}
+
+fn string_type() -> String {
+    "string".into()
+}
+
+#[derive(Debug)]
+enum Matcher {
+    /// Exact value; for URLs also the host of a bare URL.
+    Strings(HashSet<String>),
+    /// Exact host or any subdomain of it.
+    Hostnames(HashSet<String>),
+    Cidrs(Vec<IpNet>),
+    Substrings(Vec<String>),
+    Regexes(Vec<Regex>),
+}
+
+/// `/pattern/i` (PHP style, as MISP writes them) or a bare pattern.
+fn misp_regex(entry: &str) -> Result<Regex, regex::Error> {
+    let pattern = match entry.strip_prefix('/').and_then(|r| r.rsplit_once('/')) {
+        Some((body, flags)) if flags.contains('i') => format!("(?i){}", body),
+        Some((body, _)) => body.to_string(),
+        None => entry.to_string(),
+    };
+    Regex::new(&pattern)
+}
+
+fn parse_net(entry: &str) -> Option<IpNet> {
+    entry
+        .parse::<IpNet>()
+        .ok()
+        .or_else(|| entry.parse::<IpAddr>().ok().map(IpNet::from))
+}
+
+/// One MISP-style warninglist.
+#[derive(Debug)]
+pub struct Warninglist {
+    pub name: String,
+    pub description: String,
+    matching_attributes: Vec<String>,
+    matcher: Matcher,
+}
+
+impl Warninglist {
+    /// Parse a MISP warninglist JSON file (`string`, `hostname`, `cidr`,
+    /// `substring` or `regex` type).
+    pub fn from_json(text: &str) -> io::Result<Self> {
+        let raw: RawWarninglist = serde_json::from_str(text)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+        let entries = raw
+            .list
+            .iter()
+            .map(|e| e.trim())
+            .filter(|e| !e.is_empty() && !e.starts_with('#'));
+        let matcher = match raw.list_type.as_str() {
+            "string" => Matcher::Strings(entries.map(str::to_lowercase).collect()),
+            "hostname" => Matcher::Hostnames(
+                entries
+                    .map(|e| e.trim_start_matches("*.").trim_matches('.').to_lowercase())
+                    .collect(),
+            ),
+            "cidr" => Matcher::Cidrs(entries.filter_map(parse_net).collect()),
+            "substring" => Matcher::Substrings(entries.map(str::to_lowercase).collect()),
+            "regex" => Matcher::Regexes(
+                entries
+                    .map(|e| misp_regex(e).map_err(|err| invalid(format!("{}: {}", e, err))))
+                    .collect::<io::Result<_>>()?,
+            ),
+            other => {
+                return Err(invalid(format!(
+                    "{}: unsupported list type {:?}",
+                    raw.name, other
+                )));
+            }
+        };
+        Ok(Self {
+            name: raw.name,
+            description: raw.description,
+            matching_attributes: raw.matching_attributes,
+            matcher,
+        })
+    }
+
+    fn applies_to(&self, kind: IocKind) -> bool {
+        self.matching_attributes.is_empty()
+            || misp_attribute_types(kind)
+                .iter()
+                .any(|t| self.matching_attributes.iter().any(|m| m == t))
+    }
+
+    /// Whether the normalized IOC `value` of `kind` is on this list. A URL with an
+    /// IP host is checked as that IP.
+    pub fn matches(&self, kind: IocKind, value: &str) -> bool {
+        if kind == IocKind::Url
+            && let Some(ip) = url_ip(value)
+        {
+            let ip_kind = if ip.is_ipv4() {
+                IocKind::Ipv4
+            } else {
+                IocKind::Ipv6
+            };
+            return self.matches(ip_kind, &ip.to_string());
+        }
+        if !self.applies_to(kind) {
+            return false;
+        }
+        let lower = value.to_lowercase();
+        match &self.matcher {
+            Matcher::Strings(set) => {
+                set.contains(&lower)
+                    || (kind == IocKind::Url
+                        && bare_url_host(value).is_some_and(|h| set.contains(&h)))
+            }
+            Matcher::Substrings(subs) => subs.iter().any(|s| lower.contains(s.as_str())),
+            Matcher::Regexes(res) => res.iter().any(|re| re.is_match(value)),
+            Matcher::Hostnames(set) => {
+                let host = match kind {
+                    IocKind::Domain => Some(lower),
+                    // A URL with a path is a real indicator even on a popular host.
+                    IocKind::Url => bare_url_host(value),
+                    _ => None,
+                };
+                host.is_some_and(|h| {
+                    let mut suffix = h.as_str();
+                    loop {
+                        if set.contains(suffix) {
+                            return true;
+                        }
+                        match suffix.split_once('.') {
+                            Some((_, rest)) => suffix = rest,
+                            None => return false,
+                        }
+                    }
+                })
+            }
+            Matcher::Cidrs(nets) => {
+                let net = match kind {
+                    IocKind::Ipv4 | IocKind::Ipv6 => value.parse::<IpAddr>().ok().map(IpNet::from),
+                    IocKind::Cidr => value.parse::<IpNet>().ok(),
+                    _ => None,
+                };
+                net.is_some_and(|n| nets.iter().any(|list| list.contains(&n)))
+            }
+        }
+    }
+}
+
+fn url_ip(value: &str) -> Option<IpAddr> {
+    match Url::parse(value).ok()?.host()? {
+        Host::Ipv4(ip) => Some(IpAddr::V4(ip)),
+        Host::Ipv6(ip) => Some(IpAddr::V6(ip)),
+        Host::Domain(_) => None,
+    }
+}
+
+/// Host of a URL that has no path, query or fragment beyond `/`.
+fn bare_url_host(value: &str) -> Option<String> {
+    let url = Url::parse(value).ok()?;
+    if url.path() != "/" || url.query().is_some() || url.fragment().is_some() {
+        return None;
+    }
+    url.host_str().map(str::to_lowercase)
+}
+
+/// What [`Warninglists::apply`] did, per list.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FilterReport {
+    pub action: WarninglistAction,
+    pub checked: usize,
+    pub by_list: BTreeMap<String, usize>,
+}
+
+impl FilterReport {
+    pub fn total(&self) -> usize {
+        self.by_list.values().sum()
+    }
+
+    pub fn merge(&mut self, other: FilterReport) {
+        self.checked += other.checked;
+        for (name, count) in other.by_list {
+            *self.by_list.entry(name).or_insert(0) += count;
+        }
+    }
+}
+
+impl fmt::Display for FilterReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verb = match self.action {
+            WarninglistAction::Off => return write!(f, "warninglists off"),
+            WarninglistAction::Flag => "flagged",
+            WarninglistAction::Drop => "dropped",
+        };
+        write!(
+            f,
+            "warninglists {} {} of {} IOCs",
+            verb,
+            self.total(),
+            self.checked
+        )?;
+        for (name, count) in &self.by_list {
+            write!(f, "\n  {}: {}", name, count)?;
+        }
+        Ok(())
+    }
+}
+
+/// Every warninglist loaded from one folder.
+#[derive(Debug, Default)]
+pub struct Warninglists {
+    pub lists: Vec<Warninglist>,
+}
+
+impl Warninglists {
+    /// The lists shipped with Detection Wizard.
+    pub fn bundled() -> Self {
+        Self {
+            lists: BUNDLED
+                .iter()
+                .map(|(name, text)| {
+                    Warninglist::from_json(text)
+                        .unwrap_or_else(|e| panic!("bundled warninglist {}: {}", name, e))
+                })
+                .collect(),
+        }
+    }
+
+    /// Load every `*.json` in `dir`, skipping files that fail to parse.
+    pub fn load_dir(dir: &Path) -> io::Result<Self> {
+        let mut paths: Vec<_> = fs::read_dir(dir)?
+            .filter_map(Result::ok)
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|e| e == "json"))
+            .collect();
+        paths.sort();
+        let mut lists = Vec::new();
+        for path in paths {
+            match fs::read_to_string(&path).and_then(|t| Warninglist::from_json(&t)) {
+                Ok(list) => lists.push(list),
+                Err(e) => eprintln!("⚠ Skipping warninglist {}: {}", path.display(), e),
+            }
+        }
+        Ok(Self { lists })
+    }
+
+    /// Load `dir`, first creating it with the bundled lists when it does not exist,
+    /// so the lists can be edited or extended with MISP's own files.
+    pub fn load_or_install(dir: &Path) -> io::Result<Self> {
+        if !dir.exists() {
+            fs::create_dir_all(dir)?;
+            for (name, text) in BUNDLED {
+                fs::write(dir.join(name), text)?;
+            }
+        }
+        Self::load_dir(dir)
+    }
+
+    /// Lists for `settings`, or `None` when filtering is off or the folder is unreadable.
+    pub fn for_settings(settings: &WarninglistSettings) -> Option<Self> {
+        if settings.action == WarninglistAction::Off {
+            return None;
+        }
+        Self::load_or_install(Path::new(&settings.dir))
+            .map_err(|e| {
+                eprintln!(
+                    "❌ Failed to load warninglists from {}: {}",
+                    settings.dir, e
+                )
+            })
+            .ok()
+    }
+
+    /// First list `value` is on.
+    pub fn matching(&self, kind: IocKind, value: &str) -> Option<&Warninglist> {
+        self.lists.iter().find(|l| l.matches(kind, value))
+    }
+
+    /// Drop or tag the entries of `store` that are on a list. Flags from earlier runs
+    /// are replaced, so an entry whose list was removed or changed loses its tag.
+    pub fn apply(&self, store: &mut IocStore, action: WarninglistAction) -> FilterReport {
+        let mut report = FilterReport {
+            action,
+            checked: store.len(),
+            by_list: BTreeMap::new(),
+        };
+        if action == WarninglistAction::Off {
+            return report;
+        }
+        clear_flags(store);
+        let mut hit = |kind, value: &str| {
+            let list = self.matching(kind, value)?;
+            *report.by_list.entry(list.name.clone()).or_insert(0) += 1;
+            Some(list.name.clone())
+        };
+        match action {
+            WarninglistAction::Drop => {
+                store.remove_where(|e| hit(e.kind, &e.value).is_some());
+            }
+            _ => {
+                for entry in store.entries_mut() {
+                    if let Some(name) = hit(entry.kind, &entry.value) {
+                        entry.tags.insert(format!("{}{}", TAG_PREFIX, name));
+                    }
+                }
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ioc::model::Ioc;
+
+    fn store(values: &[(IocKind, &str)]) -> IocStore {
+        values
+            .iter()
+            .map(|(kind, value)| Ioc::new(*kind, value, "test").unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn bundled_lists_catch_common_false_positives() {
+        let lists = Warninglists::bundled();
+        let mut iocs = store(&[
+            (IocKind::Domain, "google.com"),
+            (IocKind::Domain, "login.microsoftonline.com"),
+            (IocKind::Domain, "setup.py"),
+            (IocKind::Domain, "evil-c2.ru"),
+            // Attacker-controlled subdomains of shared hosting stay.
+            (IocKind::Domain, "evil.s3.amazonaws.com"),
+            (IocKind::Domain, "d111111abcdef8.cloudfront.net"),
+            (IocKind::Ipv4, "8.8.8.8"),
+            (IocKind::Ipv4, "10.1.2.3"),
+            (IocKind::Ipv4, "5.5.5.5"),
+            (IocKind::Ipv4, "198.51.100.7"),
+            (IocKind::Ipv4, "45.9.20.13"),
+            (IocKind::Cidr, "192.168.0.0/24"),
+            (IocKind::Ipv6, "2001:db8::1"),
+            (IocKind::Url, "https://www.google.com/"),
+            (IocKind::Url, "https://drive.google.com/file/d/abc"),
+            (IocKind::Url, "http://127.0.0.1:8080/x"),
+        ]);
+
+        let flagged = lists.apply(&mut iocs.clone(), WarninglistAction::Flag);
+        assert_eq!(flagged.total(), 10);
+
+        let report = lists.apply(&mut iocs, WarninglistAction::Drop);
+        assert_eq!(report.checked, 16);
+        assert_eq!(report.by_list["Top domains"], 3);
+        assert_eq!(report.by_list["Private and reserved ranges"], 3);
+        let kept: Vec<&str> = iocs.entries().map(|e| e.value.as_str()).collect();
+        assert_eq!(
+            kept,
+            vec![
+                "45.9.20.13",
+                "5.5.5.5",
+                "d111111abcdef8.cloudfront.net",
+                "evil-c2.ru",
+                "evil.s3.amazonaws.com",
+                "https://drive.google.com/file/d/abc"
+            ]
+        );
+        assert!(
+            report
+                .to_string()
+                .starts_with("warninglists dropped 10 of 16 IOCs")
+        );
+    }
+
+    #[test]
+    fn loads_misp_files_from_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let lists_dir = dir.path().join("warninglists");
+        assert_eq!(
+            Warninglists::load_or_install(&lists_dir)
+                .unwrap()
+                .lists
+                .len(),
+            BUNDLED.len()
+        );
+
+        fs::write(
+            lists_dir.join("custom.json"),
+            r#"{"name": "Sinkholes", "version": 1, "type": "string",
+                "list": ["Sinkhole.Example.Net"], "matching_attributes": ["domain"]}"#,
+        )
+        .unwrap();
+        fs::write(lists_dir.join("broken.json"), "{").unwrap();
+        let lists = Warninglists::load_dir(&lists_dir).unwrap();
+        assert_eq!(lists.lists.len(), BUNDLED.len() + 1);
+        assert_eq!(
+            lists
+                .matching(IocKind::Domain, "sinkhole.example.net")
+                .map(|l| l.name.as_str()),
+            Some("Sinkholes")
+        );
+        let sinkhole = &lists.lists[1];
+        assert!(!sinkhole.matches(IocKind::Filename, "sinkhole.example.net"));
+    }
+}
@@ -0,0 +1,289 @@
+use super::model::{IocKind, PRODUCER_NAME};
+use super::store::StoreEntry;
+use super::warninglist::is_flagged;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// File name prefix of generated rule files; older ones are replaced on each run.
+pub const GENERATED_PREFIX: &str = "dw_ioc_";
+
+/// IOC to YARA rule generation into the rule downloader's `yara` folder.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct YaraIocSettings {
+    pub enabled: bool,
+    pub output_dir: String,
+    /// Hashes or strings per rule.
+    pub chunk_size: usize,
+}
+
+impl Default for YaraIocSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            output_dir: "./rule_output/yara".into(),
+            chunk_size: 1000,
+        }
+    }
+}
+
+/// `[A-Za-z0-9_]` only, as rule identifiers require.
+fn identifier(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Escape a text string: backslashes, quotes and non-printable bytes.
+fn yara_string(value: &str) -> String {
+    let mut out = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'\\' => out.push_str("\\\\"),
+            b'"' => out.push_str("\\\""),
+            0x20..=0x7e => out.push(byte as char),
+            _ => out.push_str(&format!("\\x{:02x}", byte)),
+        }
+    }
+    out
+}
+
+/// Registry keys show up in samples without their hive (`Software\...`).
+fn registry_string(key: &str) -> &str {
+    key.split_once('\\').map_or(key, |(_, path)| path)
+}
+
+fn rule(name: &str, description: &str, feed: &str, date: &str, body: &str) -> String {
+    format!(
+        "rule {}\n{{\n    meta:\n        description = \"{}\"\n        author = \"{}\"\n        \
+         source_feed = \"{}\"\n        date = \"{}\"\n{}}}\n",
+        name,
+        yara_string(description),
+        PRODUCER_NAME,
+        yara_string(feed),
+        date,
+        body
+    )
+}
+
+fn hash_rule(name: &str, feed: &str, date: &str, function: &str, hashes: &[&str]) -> String {
+    let checks: Vec<String> = hashes
+        .iter()
+        .map(|h| format!("        hash.{}(0, filesize) == \"{}\"", function, h))
+        .collect();
+    let body = format!("    condition:\n{}\n", checks.join(" or\n"));
+    let description = format!("{} {} hashes reported by {}", hashes.len(), function, feed);
+    rule(name, &description, feed, date, &body)
+}
+
+/// File names are matched against the PE `OriginalFilename`, not file content:
+/// names like `setup.exe` show up as strings in countless benign files.
+fn filename_rule(name: &str, feed: &str, date: &str, values: &[&str]) -> String {
+    let checks: Vec<String> = values
+        .iter()
+        .map(|v| {
+            format!(
+                "        pe.version_info[\"OriginalFilename\"] iequals \"{}\"",
+                yara_string(v)
+            )
+        })
+        .collect();
+    let body = format!("    condition:\n{}\n", checks.join(" or\n"));
+    let description = format!("{} PE file names reported by {}", values.len(), feed);
+    rule(name, &description, feed, date, &body)
+}
+
+fn string_rule(name: &str, feed: &str, date: &str, what: &str, values: &[&str]) -> String {
+    let strings: Vec<String> = values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| format!("        $s{} = \"{}\" ascii wide nocase", i, yara_string(v)))
+        .collect();
+    let body = format!(
+        "    strings:\n{}\n    condition:\n        any of them\n",
+        strings.join("\n")
+    );
+    let description = format!("{} {} reported by {}", values.len(), what, feed);
+    rule(name, &description, feed, date, &body)
+}
+
+/// One `.yar` file per source feed, keyed by file name. Hashes become `hash` module
+/// rules, file names `pe` module rules and registry keys string rules,
+/// `chunk_size` per rule.
+/// Warninglist-flagged entries are left out.
+pub fn generate<'a>(
+    entries: impl IntoIterator<Item = &'a StoreEntry>,
+    settings: &YaraIocSettings,
+    date: NaiveDate,
+) -> BTreeMap<String, String> {
+    // feed -> rule group -> values, in store order.
+    let mut groups: BTreeMap<&str, BTreeMap<&'static str, Vec<&str>>> = BTreeMap::new();
+    for entry in entries.into_iter().filter(|e| !is_flagged(e)) {
+        let (group, value) = match entry.kind {
+            // "md5", "sha1" and "sha256" double as the hash module functions.
+            kind if kind.is_hash() => (kind.as_str(), entry.value.as_str()),
+            IocKind::Filename => ("filename", entry.value.as_str()),
+            IocKind::Registry => ("registry", registry_string(&entry.value)),
+            _ => continue,
+        };
+        for feed in &entry.sources {
+            groups
+                .entry(feed)
+                .or_default()
+                .entry(group)
+                .or_default()
+                .push(value);
+        }
+    }
+
+    let day = date.format("%Y-%m-%d").to_string();
+    let chunk_size = settings.chunk_size.max(1);
+    let mut files = BTreeMap::new();
+    let mut used = HashSet::new();
+    for (feed, by_group) in groups {
+        // Feeds like `a-b` and `a_b` share an identifier; later ones get a suffix.
+        let mut id = identifier(feed);
+        let mut n = 1;
+        while !used.insert(id.clone()) {
+            n += 1;
+            id = format!("{}_{}", identifier(feed), n);
+        }
+        let mut text = format!(
+            "// Generated by {} from the {} IOC feed on {}.\n\nimport \"hash\"\n",
+            PRODUCER_NAME, feed, day
+        );
+        if by_group.contains_key("filename") {
+            text.push_str("import \"pe\"\n");
+        }
+        for (group, values) in by_group {
+            for (n, chunk) in values.chunks(chunk_size).enumerate() {
+                let name = format!("DW_IOC_{}_{}_{}_{}", id, group, identifier(&day), n + 1);
+                text.push('\n');
+                text.push_str(&match group {
+                    "filename" => filename_rule(&name, feed, &day, chunk),
+                    "registry" => string_rule(&name, feed, &day, "registry keys", chunk),
+                    _ => hash_rule(&name, feed, &day, group, chunk),
+                });
+            }
+        }
+        let file_name = format!("{}{}_{}.yar", GENERATED_PREFIX, id, day);
+        files.insert(file_name, text);
+    }
+    files
+}
+
+/// Replace the generated files in `dir` with `files`. Returns how many were written.
+pub fn write_rules(dir: &Path, files: &BTreeMap<String, String>) -> io::Result<usize> {
+    fs::create_dir_all(dir)?;
+    for entry in fs::read_dir(dir)?.filter_map(Result::ok) {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with(GENERATED_PREFIX) && name.ends_with(".yar") {
+            fs::remove_file(entry.path())?;
+        }
+    }
+    for (name, text) in files {
+        fs::write(dir.join(name), text)?;
+    }
+    Ok(files.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ioc::model::Ioc;
+    use crate::ioc::store::IocStore;
+
+    #[test]
+    fn generates_chunked_rules_per_feed() {
+        let store: IocStore = [
+            (
+                IocKind::Md5,
+                "d41d8cd98f00b204e9800998ecf8427e",
+                "botvrij-md5",
+            ),
+            (
+                IocKind::Md5,
+                "0cc175b9c0f1b6a831c399e269772661",
+                "botvrij-md5",
+            ),
+            (
+                IocKind::Md5,
+                "92eb5ffee6ae2fec3ad71c777531578f",
+                "botvrij-md5;eset_malware-ioc",
+            ),
+            (IocKind::Filename, "déploy.exe", "eset_malware-ioc"),
+            (
+                IocKind::Registry,
+                "HKLM\\Software\\Evil",
+                "eset_malware-ioc",
+            ),
+            (IocKind::Ipv4, "45.9.20.13", "firehol-level1"),
+            (IocKind::Filename, "explorer.exe", "botvrij-filename"),
+        ]
+        .into_iter()
+        .map(|(kind, value, source)| {
+            let ioc = Ioc::new(kind, value, source).unwrap();
+            match value {
+                "explorer.exe" => ioc.with_tags(vec!["warninglist:Common file names".into()]),
+                _ => ioc,
+            }
+        })
+        .collect();
+        let settings = YaraIocSettings {
+            chunk_size: 2,
+            ..Default::default()
+        };
+        let date = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let files = generate(store.entries(), &settings, date);
+
+        let names: Vec<&str> = files.keys().map(String::as_str).collect();
+        assert_eq!(
+            names,
+            vec![
+                "dw_ioc_botvrij_md5_2024-06-01.yar",
+                "dw_ioc_eset_malware_ioc_2024-06-01.yar"
+            ]
+        );
+        let botvrij = &files["dw_ioc_botvrij_md5_2024-06-01.yar"];
+        assert!(botvrij.contains("rule DW_IOC_botvrij_md5_md5_2024_06_01_1\n"));
+        assert!(botvrij.contains("rule DW_IOC_botvrij_md5_md5_2024_06_01_2\n"));
+        assert!(
+            botvrij.contains("hash.md5(0, filesize) == \"0cc175b9c0f1b6a831c399e269772661\" or\n")
+        );
+        let eset = &files["dw_ioc_eset_malware_ioc_2024-06-01.yar"];
+        assert!(eset.contains("import \"pe\"\n"));
+        assert!(
+            eset.contains(
+                "pe.version_info[\"OriginalFilename\"] iequals \"d\\xc3\\xa9ploy.exe\"\n"
+            )
+        );
+        assert!(!botvrij.contains("import \"pe\""));
+        assert!(eset.contains("$s0 = \"Software\\\\Evil\" ascii wide nocase"));
+        assert!(eset.contains("source_feed = \"eset_malware-ioc\""));
+
+        // Two feeds that map to the same identifier both keep their file.
+        let clash: IocStore = ["a-b", "a_b"]
+            .into_iter()
+            .map(|feed| Ioc::new(IocKind::Md5, "d41d8cd98f00b204e9800998ecf8427e", feed).unwrap())
+            .collect();
+        let clash = generate(clash.entries(), &settings, date);
+        let names: Vec<&str> = clash.keys().map(String::as_str).collect();
+        assert_eq!(
+            names,
+            vec!["dw_ioc_a_b_2024-06-01.yar", "dw_ioc_a_b_2_2024-06-01.yar"]
+        );
+        assert!(clash["dw_ioc_a_b_2_2024-06-01.yar"].contains("source_feed = \"a_b\""));
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("dw_ioc_old_2024-05-01.yar"), "").unwrap();
+        fs::write(dir.path().join("vendor.yar"), "").unwrap();
+        assert_eq!(write_rules(dir.path(), &files).unwrap(), 2);
+        assert!(!dir.path().join("dw_ioc_old_2024-05-01.yar").exists());
+        assert!(dir.path().join("vendor.yar").exists());
+    }
+}
@@ -1,4 +1,7 @@
 pub mod main_menu;
 pub mod download;
 pub mod rules;
-pub mod ioc;
\ No newline at end of file
+pub mod ioc;
+pub mod settings;
+pub mod cli;
+pub mod xml;
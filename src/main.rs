@@ -24,15 +24,19 @@ fn load_icon(path: &str) -> Option<IconData> {
 fn wgpu_available() -> bool {
     std::panic::catch_unwind(|| {
         let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
-        let adapter = pollster::block_on(
-            instance.request_adapter(&wgpu::RequestAdapterOptions::default()),
-        );
+        let adapter =
+            pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()));
         adapter.is_ok()
     })
     .unwrap_or(false)
 }
 
 fn main() -> eframe::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = detection_wizard::cli::run(&args) {
+        std::process::exit(code);
+    }
+
     let icon_data = load_icon("assets/icon.jpg");
 
     let mut viewport = ViewportBuilder::default().with_inner_size(vec2(1100.0, 720.0));
@@ -1,7 +1,11 @@
 use crate::ioc::ioc_menu::IOCSelectorApp;
 use crate::ioc::ui_ioc;
+use crate::rules::coverage_menu::CoverageApp;
+use crate::rules::hunt_menu::HuntApp;
+use crate::rules::mitre_menu::MitreApp;
 use crate::rules::rule_menu::ToolSelectorApp;
-use crate::rules::ui_rule;
+use crate::rules::sysmon_menu::SysmonToolsApp;
+use crate::rules::{ui_coverage, ui_hunt, ui_mitre, ui_rule, ui_sysmon};
 use eframe::{App, Frame, egui};
 use egui::Color32;
 use egui::Margin;
@@ -10,6 +14,10 @@ pub enum Screen {
     Menu,
     ToolSelector(ToolSelectorApp),
     IOCDownloader(IOCSelectorApp),
+    SysmonTools(SysmonToolsApp),
+    Mitre(MitreApp),
+    Coverage(CoverageApp),
+    HuntPack(HuntApp),
 }
 
 pub struct MainApp {
@@ -58,6 +66,30 @@ impl App for MainApp {
                 });
             }
 
+            Screen::SysmonTools(sysmon_app) => {
+                ui_sysmon::render_ui_sysmon(sysmon_app, ctx, || {
+                    new_screen = Some(Screen::Menu);
+                });
+            }
+
+            Screen::Mitre(mitre_app) => {
+                ui_mitre::render_ui_mitre(mitre_app, ctx, || {
+                    new_screen = Some(Screen::Menu);
+                });
+            }
+
+            Screen::Coverage(coverage_app) => {
+                ui_coverage::render_ui_coverage(coverage_app, ctx, || {
+                    new_screen = Some(Screen::Menu);
+                });
+            }
+
+            Screen::HuntPack(hunt_app) => {
+                ui_hunt::render_ui_hunt(hunt_app, ctx, || {
+                    new_screen = Some(Screen::Menu);
+                });
+            }
+
             Screen::Menu => {
                 egui::CentralPanel::default()
                     .frame(
@@ -95,6 +127,58 @@ impl App for MainApp {
                         {
                             new_screen = Some(Screen::IOCDownloader(Default::default()));
                         }
+                        ui.add_space(5.0);
+
+                        if ui
+                            .add(
+                                egui::Button::new(
+                                    egui::RichText::new("🧩 Sysmon Configs").color(Color32::WHITE),
+                                )
+                                .fill(Color32::from_rgb(106, 90, 205)), // SlateBlue
+                            )
+                            .clicked()
+                        {
+                            new_screen = Some(Screen::SysmonTools(Default::default()));
+                        }
+                        ui.add_space(5.0);
+
+                        if ui
+                            .add(
+                                egui::Button::new(
+                                    egui::RichText::new("🎯 MITRE Analytics").color(Color32::WHITE),
+                                )
+                                .fill(Color32::from_rgb(0, 139, 139)), // DarkCyan
+                            )
+                            .clicked()
+                        {
+                            new_screen = Some(Screen::Mitre(Default::default()));
+                        }
+                        ui.add_space(5.0);
+
+                        if ui
+                            .add(
+                                egui::Button::new(
+                                    egui::RichText::new("🗺 ATT&CK Coverage").color(Color32::WHITE),
+                                )
+                                .fill(Color32::from_rgb(205, 92, 92)), // IndianRed
+                            )
+                            .clicked()
+                        {
+                            new_screen = Some(Screen::Coverage(Default::default()));
+                        }
+                        ui.add_space(5.0);
+
+                        if ui
+                            .add(
+                                egui::Button::new(
+                                    egui::RichText::new("🕵 Hunt Packs").color(Color32::WHITE),
+                                )
+                                .fill(Color32::from_rgb(85, 107, 47)), // DarkOliveGreen
+                            )
+                            .clicked()
+                        {
+                            new_screen = Some(Screen::HuntPack(Default::default()));
+                        }
                         ui.add_space(40.0);
                         if ui
                             .add(
@@ -0,0 +1,597 @@
+use super::grab_mitre::{AnalyticQuery, MitreTechniqueDetails, macroize_query};
+use anyhow::{Context as _, Result, anyhow, bail};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Which ATT&CK matrix a bundle describes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttackDomain {
+    #[default]
+    Enterprise,
+    Ics,
+    Mobile,
+}
+
+impl AttackDomain {
+    pub const ALL: [AttackDomain; 3] = [Self::Enterprise, Self::Ics, Self::Mobile];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Enterprise => "Enterprise",
+            Self::Ics => "ICS",
+            Self::Mobile => "Mobile",
+        }
+    }
+
+    /// Name used by MITRE for the bundle and the Navigator, e.g. `enterprise-attack`.
+    pub fn slug(&self) -> &'static str {
+        match self {
+            Self::Enterprise => "enterprise-attack",
+            Self::Ics => "ics-attack",
+            Self::Mobile => "mobile-attack",
+        }
+    }
+
+    pub fn url(&self) -> String {
+        format!(
+            "https://raw.githubusercontent.com/mitre-attack/attack-stix-data/master/{0}/{0}.json",
+            self.slug()
+        )
+    }
+
+    pub fn cache_path(&self, cache_dir: &Path) -> PathBuf {
+        cache_dir.join(format!("{}.json", self.slug()))
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|d| d.label().eq_ignore_ascii_case(name) || d.slug().eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tactic {
+    pub id: String,
+    pub name: String,
+    /// Kill chain phase name used by techniques, e.g. `defense-evasion`.
+    pub shortname: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Technique {
+    /// `T1055` or `T1055.001`.
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    /// Tactic display names, in matrix order.
+    pub tactics: Vec<String>,
+    pub platforms: Vec<String>,
+    pub parent: Option<String>,
+    pub url: String,
+    /// `Data Source: Data Component` pairs that detect the technique.
+    pub data_sources: Vec<String>,
+    /// Free-text detection guidance (`x_mitre_detection`).
+    pub detection: String,
+    pub analytics: Vec<AnalyticQuery>,
+    /// Group IDs that use the technique directly.
+    pub groups: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Group {
+    pub id: String,
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub techniques: BTreeSet<String>,
+}
+
+/// Everything the app needs from one ATT&CK STIX 2.1 bundle.
+#[derive(Debug, Clone, Default)]
+pub struct AttackData {
+    pub domain: AttackDomain,
+    /// Collection version, e.g. `16.1`.
+    pub version: String,
+    pub tactics: Vec<Tactic>,
+    pub techniques: BTreeMap<String, Technique>,
+    pub groups: BTreeMap<String, Group>,
+}
+
+fn str_field<'a>(obj: &'a Value, key: &str) -> &'a str {
+    obj.get(key).and_then(Value::as_str).unwrap_or_default()
+}
+
+fn str_list(obj: &Value, key: &str) -> Vec<String> {
+    obj.get(key)
+        .and_then(Value::as_array)
+        .map(|a| {
+            a.iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// `(external_id, url)` from the `mitre-attack` external reference.
+fn attack_ref(obj: &Value) -> Option<(String, String)> {
+    obj.get("external_references")?
+        .as_array()?
+        .iter()
+        .find(|r| {
+            matches!(
+                str_field(r, "source_name"),
+                "mitre-attack" | "mitre-ics-attack" | "mitre-mobile-attack"
+            )
+        })
+        .map(|r| {
+            (
+                str_field(r, "external_id").to_string(),
+                str_field(r, "url").to_string(),
+            )
+        })
+        .filter(|(id, _)| !id.is_empty())
+}
+
+fn is_retired(obj: &Value) -> bool {
+    obj.get("revoked").and_then(Value::as_bool).unwrap_or(false)
+        || obj
+            .get("x_mitre_deprecated")
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+}
+
+fn analytic_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"(?s)Analytic\s*\d*\s*-\s*([^\n<]*?)\s*<code>(.*?)</code>").unwrap()
+    })
+}
+
+/// Pull "Analytic N - description <code>query</code>" blocks out of relationship text.
+pub fn extract_analytics(text: &str) -> Vec<AnalyticQuery> {
+    analytic_re()
+        .captures_iter(text)
+        .map(|c| AnalyticQuery {
+            description: c[1].trim().to_string(),
+            query: macroize_query(c[2].trim()),
+        })
+        .collect()
+}
+
+/// First sentence with `(Citation: ...)` markers removed.
+fn first_sentence(text: &str) -> String {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"\s*\(Citation:[^)]*\)").unwrap());
+    let clean = re.replace_all(text, "");
+    match clean.find(". ") {
+        Some(end) => clean[..=end].trim().to_string(),
+        None => clean.trim().to_string(),
+    }
+}
+
+impl AttackData {
+    pub fn load(path: &Path, domain: AttackDomain) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("reading ATT&CK bundle {}", path.display()))?;
+        Self::parse(&text, domain).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    pub fn parse(text: &str, domain: AttackDomain) -> Result<Self> {
+        let bundle: Value = serde_json::from_str(text)?;
+        if str_field(&bundle, "type") != "bundle" {
+            bail!("not a STIX bundle");
+        }
+        let objects = bundle
+            .get("objects")
+            .and_then(Value::as_array)
+            .ok_or_else(|| anyhow!("bundle has no objects"))?;
+
+        let mut data = AttackData {
+            domain,
+            ..Default::default()
+        };
+        // STIX id -> ATT&CK id for techniques and groups, so relationships can be resolved.
+        let mut stix_to_attack: HashMap<&str, String> = HashMap::new();
+        let mut components: HashMap<&str, (&str, &str)> = HashMap::new();
+        let mut data_sources: HashMap<&str, &str> = HashMap::new();
+        let mut phases: HashMap<String, Vec<String>> = HashMap::new();
+        let mut tactic_order: Vec<String> = Vec::new();
+
+        for obj in objects {
+            if is_retired(obj) {
+                continue;
+            }
+            let stix_id = str_field(obj, "id");
+            match str_field(obj, "type") {
+                "x-mitre-collection" => {
+                    data.version = str_field(obj, "x_mitre_version").to_string();
+                    tactic_order = str_list(obj, "x_mitre_tactic_refs");
+                }
+                "x-mitre-matrix" if tactic_order.is_empty() => {
+                    tactic_order = str_list(obj, "tactic_refs");
+                }
+                "x-mitre-tactic" => {
+                    if let Some((id, _)) = attack_ref(obj) {
+                        stix_to_attack.insert(stix_id, id.clone());
+                        data.tactics.push(Tactic {
+                            id,
+                            name: str_field(obj, "name").to_string(),
+                            shortname: str_field(obj, "x_mitre_shortname").to_string(),
+                        });
+                    }
+                }
+                "attack-pattern" => {
+                    let Some((id, url)) = attack_ref(obj) else {
+                        continue;
+                    };
+                    stix_to_attack.insert(stix_id, id.clone());
+                    let kill_chain: Vec<String> = obj
+                        .get("kill_chain_phases")
+                        .and_then(Value::as_array)
+                        .map(|a| {
+                            a.iter()
+                                .map(|p| str_field(p, "phase_name").to_string())
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    phases.insert(id.clone(), kill_chain);
+                    data.techniques.insert(
+                        id.clone(),
+                        Technique {
+                            parent: id.split_once('.').map(|(p, _)| p.to_string()),
+                            id,
+                            name: str_field(obj, "name").to_string(),
+                            description: str_field(obj, "description").to_string(),
+                            platforms: str_list(obj, "x_mitre_platforms"),
+                            url,
+                            data_sources: str_list(obj, "x_mitre_data_sources"),
+                            detection: str_field(obj, "x_mitre_detection").to_string(),
+                            ..Default::default()
+                        },
+                    );
+                }
+                "intrusion-set" => {
+                    if let Some((id, _)) = attack_ref(obj) {
+                        stix_to_attack.insert(stix_id, id.clone());
+                        data.groups.insert(
+                            id.clone(),
+                            Group {
+                                id,
+                                name: str_field(obj, "name").to_string(),
+                                aliases: str_list(obj, "aliases"),
+                                techniques: BTreeSet::new(),
+                            },
+                        );
+                    }
+                }
+                "x-mitre-data-component" => {
+                    components.insert(
+                        stix_id,
+                        (
+                            str_field(obj, "name"),
+                            str_field(obj, "x_mitre_data_source_ref"),
+                        ),
+                    );
+                }
+                "x-mitre-data-source" => {
+                    data_sources.insert(stix_id, str_field(obj, "name"));
+                }
+                _ => {}
+            }
+        }
+
+        if data.techniques.is_empty() {
+            bail!("bundle contains no ATT&CK techniques");
+        }
+
+        // Tactics in matrix order; fall back to bundle order when no collection lists them.
+        let rank = |t: &Tactic| {
+            tactic_order
+                .iter()
+                .position(|r| stix_to_attack.get(r.as_str()) == Some(&t.id))
+                .unwrap_or(usize::MAX)
+        };
+        data.tactics.sort_by_key(rank);
+        for (id, technique) in data.techniques.iter_mut() {
+            let wanted = &phases[id];
+            technique.tactics = data
+                .tactics
+                .iter()
+                .filter(|t| wanted.contains(&t.shortname))
+                .map(|t| t.name.clone())
+                .collect();
+        }
+
+        for obj in objects {
+            if str_field(obj, "type") != "relationship" || is_retired(obj) {
+                continue;
+            }
+            let source = str_field(obj, "source_ref");
+            let Some(target) = stix_to_attack.get(str_field(obj, "target_ref")) else {
+                continue;
+            };
+            match str_field(obj, "relationship_type") {
+                "uses" => {
+                    let Some(group_id) = stix_to_attack.get(source) else {
+                        continue;
+                    };
+                    if let (Some(group), Some(technique)) = (
+                        data.groups.get_mut(group_id),
+                        data.techniques.get_mut(target),
+                    ) {
+                        group.techniques.insert(target.clone());
+                        technique.groups.insert(group_id.clone());
+                    }
+                }
+                "detects" => {
+                    let Some(technique) = data.techniques.get_mut(target) else {
+                        continue;
+                    };
+                    if let Some((component, source_ref)) = components.get(source) {
+                        let label = match data_sources.get(source_ref) {
+                            Some(ds) => format!("{}: {}", ds, component),
+                            None => component.to_string(),
+                        };
+                        if !technique.data_sources.contains(&label) {
+                            technique.data_sources.push(label);
+                        }
+                    }
+                    technique
+                        .analytics
+                        .extend(extract_analytics(str_field(obj, "description")));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(data)
+    }
+
+    /// `v16.1`, the form stamped into analytics as `mitre_version`.
+    pub fn version_label(&self) -> String {
+        if self.version.is_empty() {
+            "unknown".to_string()
+        } else {
+            format!("v{}", self.version)
+        }
+    }
+
+    /// Accepts `T1055`, `t1055.001` or `1055`.
+    pub fn technique(&self, id: &str) -> Option<&Technique> {
+        let id = id.trim();
+        let id = id.strip_prefix(['T', 't']).unwrap_or(id);
+        self.techniques.get(&format!("T{}", id))
+    }
+
+    /// Tactic by ID (`TA0002`), name or shortname (`command-and-control`).
+    pub fn tactic(&self, input: &str) -> Option<&Tactic> {
+        let input = input.trim();
+        let spaced = input.replace(['-', '_'], " ");
+        self.tactics.iter().find(|t| {
+            t.id.eq_ignore_ascii_case(input)
+                || t.name.eq_ignore_ascii_case(&spaced)
+                || t.shortname.eq_ignore_ascii_case(input)
+        })
+    }
+
+    /// Technique and sub-technique IDs under a tactic.
+    pub fn techniques_for_tactic(&self, tactic: &Tactic) -> Vec<String> {
+        self.techniques
+            .values()
+            .filter(|t| t.tactics.contains(&tactic.name))
+            .map(|t| t.id.clone())
+            .collect()
+    }
+
+    /// Group by ID (`G0016`), name or alias.
+    pub fn group(&self, input: &str) -> Option<&Group> {
+        let input = input.trim();
+        self.groups.values().find(|g| {
+            g.id.eq_ignore_ascii_case(input)
+                || g.name.eq_ignore_ascii_case(input)
+                || g.aliases.iter().any(|a| a.eq_ignore_ascii_case(input))
+        })
+    }
+
+    /// The details the analytic generator needs, in the same shape the HTML scraper produced.
+    pub fn technique_details(&self, id: &str) -> Option<MitreTechniqueDetails> {
+        let technique = self.technique(id)?;
+        let parent = technique
+            .parent
+            .as_deref()
+            .and_then(|p| self.techniques.get(p));
+        let (name, subtechnique, technique_id, subtechnique_id) = match parent {
+            Some(p) => (
+                p.name.clone(),
+                technique.name.clone(),
+                p.id.clone(),
+                technique.id.clone(),
+            ),
+            None => (
+                technique.name.clone(),
+                String::new(),
+                technique.id.clone(),
+                String::new(),
+            ),
+        };
+        let mut apts: Vec<String> = technique
+            .groups
+            .iter()
+            .filter_map(|g| self.groups.get(g))
+            .map(|g| g.name.clone())
+            .collect();
+        apts.sort();
+
+        Some(MitreTechniqueDetails {
+            id: technique.id.clone(),
+            name,
+            subtechnique,
+            tactics: technique.tactics.clone(),
+            hunting_trigger: first_sentence(&technique.description),
+            mitre_category: if technique.tactics.is_empty() {
+                "Unknown".to_string()
+            } else {
+                technique.tactics.join(", ")
+            },
+            analytics: technique.analytics.clone(),
+            apts,
+            url: technique.url.clone(),
+            technique_id,
+            subtechnique_id,
+            data_sources: technique.data_sources.clone(),
+            detection: technique.detection.clone(),
+            mitre_version: self.version_label(),
+        })
+    }
+}
+
+/// Where the bundle for `domain` comes from: an explicit file if given, else the cache.
+pub fn bundle_path(local_file: &str, cache_dir: &str, domain: AttackDomain) -> PathBuf {
+    if local_file.trim().is_empty() {
+        domain.cache_path(Path::new(cache_dir))
+    } else {
+        PathBuf::from(local_file.trim())
+    }
+}
+
+/// Download the bundle for `domain` into `cache_dir`, replacing any cached copy.
+pub async fn download_bundle(domain: AttackDomain, cache_dir: &Path) -> Result<PathBuf> {
+    let url = domain.url();
+    let response = reqwest::get(&url).await?;
+    if !response.status().is_success() {
+        bail!("Failed to download {}: {}", url, response.status());
+    }
+    let bytes = response.bytes().await?;
+    // Parse before replacing the cache so a bad download does not clobber a good copy.
+    AttackData::parse(&String::from_utf8_lossy(&bytes), domain)?;
+
+    fs::create_dir_all(cache_dir)?;
+    let path = domain.cache_path(cache_dir);
+    let tmp = path.with_extension("json.part");
+    fs::write(&tmp, &bytes)?;
+    fs::rename(&tmp, &path)?;
+    Ok(path)
+}
+
+/// Load from `local_file` or the cache, downloading into the cache when it is
+/// missing or `refresh` is set. An explicit local file is never downloaded over.
+pub async fn load_or_download(
+    local_file: &str,
+    cache_dir: &str,
+    domain: AttackDomain,
+    refresh: bool,
+) -> Result<AttackData> {
+    let path = bundle_path(local_file, cache_dir, domain);
+    let local = !local_file.trim().is_empty();
+    if !local && (refresh || !path.exists()) {
+        download_bundle(domain, Path::new(cache_dir)).await?;
+    }
+    AttackData::load(&path, domain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUNDLE: &str = r#"{
+      "type": "bundle",
+      "id": "bundle--1",
+      "objects": [
+        {"type": "x-mitre-collection", "id": "x-mitre-collection--1", "name": "Enterprise ATT&CK",
+         "x_mitre_version": "16.1"},
+        {"type": "x-mitre-tactic", "id": "x-mitre-tactic--de", "name": "Defense Evasion",
+         "x_mitre_shortname": "defense-evasion",
+         "external_references": [{"source_name": "mitre-attack", "external_id": "TA0005"}]},
+        {"type": "x-mitre-tactic", "id": "x-mitre-tactic--pe", "name": "Privilege Escalation",
+         "x_mitre_shortname": "privilege-escalation",
+         "external_references": [{"source_name": "mitre-attack", "external_id": "TA0004"}]},
+        {"type": "attack-pattern", "id": "attack-pattern--pi", "name": "Process Injection",
+         "description": "Adversaries may inject code into processes (Citation: X). More text.",
+         "x_mitre_detection": "Monitor API calls.",
+         "kill_chain_phases": [{"kill_chain_name": "mitre-attack", "phase_name": "defense-evasion"},
+                               {"kill_chain_name": "mitre-attack", "phase_name": "privilege-escalation"}],
+         "external_references": [{"source_name": "mitre-attack", "external_id": "T1055",
+                                   "url": "https://attack.mitre.org/techniques/T1055"}]},
+        {"type": "attack-pattern", "id": "attack-pattern--dll", "name": "Dynamic-link Library Injection",
+         "x_mitre_is_subtechnique": true,
+         "kill_chain_phases": [{"kill_chain_name": "mitre-attack", "phase_name": "defense-evasion"}],
+         "external_references": [{"source_name": "mitre-attack", "external_id": "T1055.001",
+                                   "url": "https://attack.mitre.org/techniques/T1055/001"}]},
+        {"type": "attack-pattern", "id": "attack-pattern--old", "name": "Old", "revoked": true,
+         "external_references": [{"source_name": "mitre-attack", "external_id": "T9999"}]},
+        {"type": "intrusion-set", "id": "intrusion-set--apt28", "name": "APT28",
+         "aliases": ["APT28", "Fancy Bear"],
+         "external_references": [{"source_name": "mitre-attack", "external_id": "G0007"}]},
+        {"type": "x-mitre-data-source", "id": "x-mitre-data-source--proc", "name": "Process"},
+        {"type": "x-mitre-data-component", "id": "x-mitre-data-component--access", "name": "OS API Execution",
+         "x_mitre_data_source_ref": "x-mitre-data-source--proc"},
+        {"type": "relationship", "id": "relationship--1", "relationship_type": "uses",
+         "source_ref": "intrusion-set--apt28", "target_ref": "attack-pattern--pi"},
+        {"type": "relationship", "id": "relationship--2", "relationship_type": "detects",
+         "source_ref": "x-mitre-data-component--access", "target_ref": "attack-pattern--pi",
+         "description": "Monitor for injection.\n\nAnalytic 1 - Remote thread creation\n\n<code>sourcetype=WinEventLog:Microsoft-Windows-Sysmon/Operational EventCode=8</code>"}
+      ]
+    }"#;
+
+    #[test]
+    fn parses_bundle() {
+        let data = AttackData::parse(BUNDLE, AttackDomain::Enterprise).unwrap();
+        assert_eq!(data.version_label(), "v16.1");
+        assert_eq!(data.techniques.len(), 2);
+        assert!(data.technique("T9999").is_none());
+
+        let pi = data.technique("1055").unwrap();
+        assert_eq!(pi.tactics, vec!["Defense Evasion", "Privilege Escalation"]);
+        assert_eq!(pi.data_sources, vec!["Process: OS API Execution"]);
+        assert_eq!(pi.groups.iter().collect::<Vec<_>>(), vec!["G0007"]);
+        assert_eq!(data.group("fancy bear").unwrap().id, "G0007");
+
+        let tactic = data.tactic("defense-evasion").unwrap();
+        assert_eq!(
+            data.techniques_for_tactic(tactic),
+            vec!["T1055", "T1055.001"]
+        );
+    }
+
+    #[test]
+    fn builds_technique_details() {
+        let data = AttackData::parse(BUNDLE, AttackDomain::Enterprise).unwrap();
+        let details = data.technique_details("T1055").unwrap();
+        assert_eq!(details.apts, vec!["APT28"]);
+        assert_eq!(
+            details.mitre_category,
+            "Defense Evasion, Privilege Escalation"
+        );
+        assert_eq!(
+            details.hunting_trigger,
+            "Adversaries may inject code into processes."
+        );
+        assert_eq!(details.analytics.len(), 1);
+        assert_eq!(details.analytics[0].description, "Remote thread creation");
+        assert_eq!(details.analytics[0].query, "`sysmon` EventCode=8");
+        assert_eq!(details.mitre_version, "v16.1");
+
+        let sub = data.technique_details("T1055.001").unwrap();
+        assert_eq!(sub.name, "Process Injection");
+        assert_eq!(sub.subtechnique, "Dynamic-link Library Injection");
+        assert_eq!(sub.technique_id, "T1055");
+        assert_eq!(sub.subtechnique_id, "T1055.001");
+    }
+
+    #[test]
+    fn rejects_non_bundles() {
+        assert!(AttackData::parse("{\"type\": \"x\"}", AttackDomain::Ics).is_err());
+        assert_eq!(AttackDomain::parse("ics"), Some(AttackDomain::Ics));
+        assert_eq!(
+            AttackDomain::Mobile.url(),
+            "https://raw.githubusercontent.com/mitre-attack/attack-stix-data/master/mobile-attack/mobile-attack.json"
+        );
+    }
+}
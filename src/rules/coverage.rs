@@ -0,0 +1,506 @@
+use super::splunk_content::{SplunkContent, parse_content};
+use super::sysmon_analysis::{rule_techniques, technique_re};
+use super::sysmon_config::SysmonConfig;
+use crate::download::source_of;
+use crate::ioc::csv_export::csv_field;
+use regex::Regex;
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use walkdir::WalkDir;
+
+/// Tool folders under the rule output root that carry ATT&CK references.
+pub const COVERAGE_TOOLS: [(&str, &str); 5] = [
+    ("Sigma", "sigma"),
+    ("Splunk", "splunk"),
+    ("Suricata", "suricata"),
+    ("Sysmon", "sysmon"),
+    ("Yara", "yara"),
+];
+
+/// Label for files fetched from direct URLs rather than a cloned repo.
+pub const DIRECT_SOURCE: &str = "direct download";
+
+/// One rule that references at least one technique.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleRef {
+    pub tool: String,
+    pub source: String,
+    pub rule: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TechniqueCoverage {
+    pub by_tool: BTreeMap<String, usize>,
+    pub by_source: BTreeMap<(String, String), usize>,
+    pub rules: Vec<RuleRef>,
+}
+
+impl TechniqueCoverage {
+    pub fn total(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Rule count for one tool, or every tool when `tool` is `None`.
+    pub fn count(&self, tool: Option<&str>) -> usize {
+        match tool {
+            Some(t) => self.by_tool.get(t).copied().unwrap_or(0),
+            None => self.total(),
+        }
+    }
+}
+
+/// Rules named in a heat map cell's hover text before the rest are only counted.
+const LISTED_RULES: usize = 20;
+
+/// Hover text of a technique's heat map cells, sub-techniques included.
+#[derive(Debug, Clone, Default)]
+pub struct RuleList {
+    pub all: String,
+    pub by_tool: BTreeMap<String, String>,
+}
+
+impl RuleList {
+    /// Text for one tool, or every tool when `tool` is `None`.
+    pub fn get(&self, tool: Option<&str>) -> Option<&str> {
+        match tool {
+            Some(t) => self.by_tool.get(t).map(String::as_str),
+            None => Some(&self.all),
+        }
+    }
+}
+
+fn rule_text(rules: &[&RuleRef]) -> String {
+    let mut text = rules
+        .iter()
+        .take(LISTED_RULES)
+        .map(|r| format!("[{}] {} ({})", r.tool, r.rule, r.source))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if rules.len() > LISTED_RULES {
+        text.push_str(&format!("\n… and {} more", rules.len() - LISTED_RULES));
+    }
+    text
+}
+
+/// Technique ID -> which rules cover it.
+#[derive(Debug, Clone, Default)]
+pub struct CoverageMap {
+    pub techniques: BTreeMap<String, TechniqueCoverage>,
+    /// Files that could not be read or parsed, for the status line.
+    pub skipped_files: usize,
+}
+
+impl CoverageMap {
+    pub fn add(&mut self, tool: &str, source: &str, rule: &str, techniques: &BTreeSet<String>) {
+        for technique in techniques {
+            let entry = self.techniques.entry(technique.clone()).or_default();
+            *entry.by_tool.entry(tool.to_string()).or_insert(0) += 1;
+            *entry
+                .by_source
+                .entry((tool.to_string(), source.to_string()))
+                .or_insert(0) += 1;
+            entry.rules.push(RuleRef {
+                tool: tool.to_string(),
+                source: source.to_string(),
+                rule: rule.to_string(),
+            });
+        }
+    }
+
+    pub fn tools(&self) -> BTreeSet<&str> {
+        self.techniques
+            .values()
+            .flat_map(|c| c.by_tool.keys().map(String::as_str))
+            .collect()
+    }
+
+    pub fn count(&self, technique: &str, tool: Option<&str>) -> usize {
+        self.techniques.get(technique).map_or(0, |c| c.count(tool))
+    }
+
+    /// Rule count for a technique plus all of its sub-techniques.
+    pub fn count_with_subtechniques(&self, technique: &str, tool: Option<&str>) -> usize {
+        let prefix = format!("{}.", technique);
+        self.techniques
+            .range(technique.to_string()..)
+            .take_while(|(id, _)| *id == technique || id.starts_with(&prefix))
+            .map(|(_, c)| c.count(tool))
+            .sum()
+    }
+
+    /// Hover texts per technique, built once per scan rather than every frame.
+    /// A technique's list also holds the rules of its sub-techniques.
+    pub fn rule_lists(&self) -> BTreeMap<String, RuleList> {
+        let mut grouped: BTreeMap<&str, Vec<&RuleRef>> = BTreeMap::new();
+        for (id, c) in &self.techniques {
+            let parent = id.split_once('.').map(|(p, _)| p);
+            for key in std::iter::once(id.as_str()).chain(parent) {
+                grouped.entry(key).or_default().extend(&c.rules);
+            }
+        }
+        grouped
+            .into_iter()
+            .map(|(id, rules)| {
+                let mut by_tool: BTreeMap<&str, Vec<&RuleRef>> = BTreeMap::new();
+                for rule in &rules {
+                    by_tool.entry(&rule.tool).or_default().push(rule);
+                }
+                let list = RuleList {
+                    all: rule_text(&rules),
+                    by_tool: by_tool
+                        .into_iter()
+                        .map(|(tool, rules)| (tool.to_string(), rule_text(&rules)))
+                        .collect(),
+                };
+                (id.to_string(), list)
+            })
+            .collect()
+    }
+
+    /// Techniques with at least one rule from `tool` (or any tool).
+    pub fn covered(&self, tool: Option<&str>) -> usize {
+        self.techniques
+            .values()
+            .filter(|c| c.count(tool) > 0)
+            .count()
+    }
+
+    /// `technique,total,<tool>...` with one column per tool.
+    pub fn by_tool_csv(&self) -> String {
+        let tools: Vec<&str> = self.tools().into_iter().collect();
+        let mut out = format!("technique,total,{}\n", tools.join(","));
+        for (technique, c) in &self.techniques {
+            let counts: Vec<String> = tools.iter().map(|t| c.count(Some(t)).to_string()).collect();
+            out.push_str(&format!(
+                "{},{},{}\n",
+                technique,
+                c.total(),
+                counts.join(",")
+            ));
+        }
+        out
+    }
+
+    /// Long format `technique,tool,source,rules`.
+    pub fn by_source_csv(&self) -> String {
+        let mut out = String::from("technique,tool,source,rules\n");
+        for (technique, c) in &self.techniques {
+            for ((tool, source), n) in &c.by_source {
+                out.push_str(&format!(
+                    "{},{},{},{}\n",
+                    technique,
+                    tool,
+                    csv_field(source),
+                    n
+                ));
+            }
+        }
+        out
+    }
+
+    /// Write `coverage_by_tool.csv` and `coverage_by_source.csv` into `dir`.
+    pub fn write_csv(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        fs::create_dir_all(dir)?;
+        let by_tool = dir.join("coverage_by_tool.csv");
+        let by_source = dir.join("coverage_by_source.csv");
+        fs::write(&by_tool, self.by_tool_csv())?;
+        fs::write(&by_source, self.by_source_csv())?;
+        Ok(vec![by_tool, by_source])
+    }
+}
+
+pub fn technique_ids(text: &str) -> BTreeSet<String> {
+    technique_re()
+        .find_iter(text)
+        .map(|m| m.as_str().to_string())
+        .collect()
+}
+
+/// `attack.t1059.001` tags -> `T1059.001`.
+pub fn sigma_techniques(text: &str) -> Option<(String, BTreeSet<String>)> {
+    let doc: Value = serde_yaml::Deserializer::from_str(text)
+        .next()
+        .and_then(|d| Value::deserialize(d).ok())?;
+    let title = doc.get("title").and_then(Value::as_str)?.to_string();
+    let techniques = doc
+        .get("tags")
+        .and_then(Value::as_sequence)
+        .map(|tags| {
+            tags.iter()
+                .filter_map(Value::as_str)
+                .filter_map(|t| t.strip_prefix("attack."))
+                .filter(|t| t.starts_with(['t', 'T']))
+                .flat_map(|t| technique_ids(&t.to_ascii_uppercase()))
+                .collect()
+        })
+        .unwrap_or_default();
+    Some((title, techniques))
+}
+
+fn yara_rule_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"(?m)^\s*(?:(?:private|global)\s+)*rule\s+([A-Za-z_][A-Za-z0-9_]*)").unwrap()
+    })
+}
+
+/// Technique IDs in each rule's `meta:` section.
+pub fn yara_techniques(text: &str) -> Vec<(String, BTreeSet<String>)> {
+    let starts: Vec<(usize, String)> = yara_rule_re()
+        .captures_iter(text)
+        .map(|c| (c.get(0).unwrap().start(), c[1].to_string()))
+        .collect();
+    let mut rules = Vec::new();
+    for (i, (start, name)) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).map_or(text.len(), |(s, _)| *s);
+        let body = &text[*start..end];
+        let Some(meta_at) = body.find("meta:") else {
+            continue;
+        };
+        let meta = &body[meta_at..];
+        let meta_end = ["strings:", "condition:"]
+            .iter()
+            .filter_map(|k| meta.find(k))
+            .min()
+            .unwrap_or(meta.len());
+        let techniques = technique_ids(&meta[..meta_end]);
+        if !techniques.is_empty() {
+            rules.push((name.clone(), techniques));
+        }
+    }
+    rules
+}
+
+fn suricata_re() -> &'static (Regex, Regex) {
+    static RE: OnceLock<(Regex, Regex)> = OnceLock::new();
+    RE.get_or_init(|| {
+        (
+            Regex::new(r#"msg\s*:\s*"((?:[^"\\]|\\.)*)""#).unwrap(),
+            Regex::new(r"metadata\s*:\s*([^;]*)").unwrap(),
+        )
+    })
+}
+
+/// `metadata: mitre_technique_id T1071, ...` on active (uncommented) rules.
+pub fn suricata_techniques(text: &str) -> Vec<(String, BTreeSet<String>)> {
+    let (msg_re, meta_re) = suricata_re();
+    let mut rules = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let techniques: BTreeSet<String> = meta_re
+            .captures_iter(line)
+            .flat_map(|c| {
+                c[1].split(',')
+                    .filter_map(|kv| kv.trim().strip_prefix("mitre_technique_id"))
+                    .flat_map(|v| technique_ids(&v.to_ascii_uppercase()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        if techniques.is_empty() {
+            continue;
+        }
+        let msg = msg_re
+            .captures(line)
+            .map_or_else(|| line.chars().take(80).collect(), |c| c[1].to_string());
+        rules.push((msg, techniques));
+    }
+    rules
+}
+
+fn splunk_techniques(text: &str) -> Option<(String, BTreeSet<String>)> {
+    match parse_content(text)? {
+        SplunkContent::Detection(d) if !d.is_retired() => Some((
+            d.name,
+            d.mitre_attack_id
+                .iter()
+                .flat_map(|t| technique_ids(&t.to_ascii_uppercase()))
+                .collect(),
+        )),
+        _ => None,
+    }
+}
+
+fn sysmon_techniques(path: &Path) -> Option<Vec<(String, BTreeSet<String>)>> {
+    let config = SysmonConfig::load(path).ok()?;
+    let mut rules = Vec::new();
+    for filter in &config.filters {
+        for rule in &filter.rules {
+            let techniques = rule_techniques(rule);
+            if techniques.is_empty() {
+                continue;
+            }
+            let label = rule.attr("name").unwrap_or(&filter.event);
+            rules.push((format!("{}: {}", config.name, label), techniques));
+        }
+    }
+    Some(rules)
+}
+
+/// Whether `path` has one of the rule extensions for `tool`.
+pub fn is_rule_file(tool: &str, path: &Path) -> bool {
+    let exts: &[&str] = match tool {
+        "Sigma" | "Splunk" => &["yml", "yaml"],
+        "Suricata" => &["rules", "rule"],
+        "Yara" => &["yar", "yara"],
+        "Sysmon" => &["xml"],
+        _ => return false,
+    };
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| exts.iter().any(|x| x.eq_ignore_ascii_case(e)))
+}
+
+/// Rules in one file with their techniques, or `None` when the file is unreadable.
+pub fn file_techniques(tool: &str, path: &Path) -> Option<Vec<(String, BTreeSet<String>)>> {
+    if !is_rule_file(tool, path) {
+        return Some(Vec::new());
+    }
+    if tool == "Sysmon" {
+        return sysmon_techniques(path);
+    }
+    let bytes = fs::read(path).ok()?;
+    let text = String::from_utf8_lossy(&bytes);
+    Some(match tool {
+        "Sigma" => sigma_techniques(&text).into_iter().collect(),
+        "Splunk" => splunk_techniques(&text).into_iter().collect(),
+        "Suricata" => suricata_techniques(&text),
+        _ => yara_techniques(&text),
+    })
+}
+
+/// Walk every tool folder under `root` and count rules per technique.
+pub fn scan_dir(root: &Path) -> CoverageMap {
+    let mut map = CoverageMap::default();
+    for (tool, folder) in COVERAGE_TOOLS {
+        let dir = root.join(folder);
+        for entry in WalkDir::new(&dir).into_iter().filter_map(Result::ok) {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let path = entry.path();
+            let file_name = entry.file_name().to_string_lossy();
+            let source = source_of(&file_name).unwrap_or(DIRECT_SOURCE).to_string();
+            match file_techniques(tool, path) {
+                Some(rules) => {
+                    for (rule, techniques) in rules {
+                        map.add(tool, &source, &rule, &techniques);
+                    }
+                }
+                None => map.skipped_files += 1,
+            }
+        }
+    }
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_from_each_format() {
+        let (title, t) = sigma_techniques(
+            "title: Enc PS\ntags:\n  - attack.execution\n  - attack.t1059.001\n  - attack.T1027\n",
+        )
+        .unwrap();
+        assert_eq!(title, "Enc PS");
+        assert_eq!(
+            t.into_iter().collect::<Vec<_>>(),
+            vec!["T1027", "T1059.001"]
+        );
+
+        let yara = r#"
+rule Cobalt_Beacon : apt {
+    meta:
+        mitre_att = "T1071.001"
+        description = "not T1000 in strings"
+    strings:
+        $a = "T1105"
+    condition:
+        $a
+}
+private rule NoMeta { condition: true }
+"#;
+        let rules = yara_techniques(yara);
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].0, "Cobalt_Beacon");
+        assert!(rules[0].1.contains("T1071.001") && !rules[0].1.contains("T1105"));
+
+        let suricata = concat!(
+            "alert dns any any -> any any (msg:\"ET DNS Query to C2\"; ",
+            "metadata: created_at 2024_01_01, mitre_technique_id T1071, mitre_technique_id T1071.004; sid:1;)\n",
+            "# alert tcp any any -> any any (msg:\"off\"; metadata: mitre_technique_id T1046; sid:2;)\n",
+        );
+        let rules = suricata_techniques(suricata);
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].0, "ET DNS Query to C2");
+        assert_eq!(rules[0].1.len(), 2);
+    }
+
+    #[test]
+    fn scans_folders_per_tool_and_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let sigma = dir.path().join("sigma");
+        fs::create_dir_all(&sigma).unwrap();
+        fs::write(
+            sigma.join("SigmaHQ_sigma__a.yml"),
+            "title: A\ntags: [attack.t1059]\ndetection: {}\n",
+        )
+        .unwrap();
+        fs::write(
+            sigma.join("b.yml"),
+            "title: B\ntags: [attack.t1059, attack.t1105]\n",
+        )
+        .unwrap();
+        let splunk = dir.path().join("splunk");
+        fs::create_dir_all(&splunk).unwrap();
+        fs::write(
+            splunk.join("splunk_security_content__c.yml"),
+            "name: C\nsearch: '| tstats count'\ntags:\n  mitre_attack_id: [T1059]\n",
+        )
+        .unwrap();
+
+        let map = scan_dir(dir.path());
+        assert_eq!(map.count("T1059", None), 3);
+        assert_eq!(map.count("T1059", Some("Sigma")), 2);
+        assert_eq!(map.count("T1105", Some("Splunk")), 0);
+        assert_eq!(map.covered(Some("Splunk")), 1);
+        assert_eq!(map.count_with_subtechniques("T1059", None), 3);
+
+        let mut with_sub = map.clone();
+        with_sub.add(
+            "Sigma",
+            "x",
+            "D",
+            &BTreeSet::from(["T1059.001".to_string()]),
+        );
+        let lists = with_sub.rule_lists();
+        assert_eq!(lists["T1059"].get(None).unwrap().lines().count(), 4);
+        assert_eq!(
+            lists["T1059"].get(Some("Splunk")),
+            Some("[Splunk] C (splunk_security_content)")
+        );
+        assert_eq!(lists["T1059.001"].get(None), Some("[Sigma] D (x)"));
+        assert_eq!(lists["T1105"].get(Some("Splunk")), None);
+
+        let c = &map.techniques["T1059"];
+        assert_eq!(c.by_source[&("Sigma".into(), "SigmaHQ_sigma".into())], 1);
+        assert_eq!(c.by_source[&("Sigma".into(), DIRECT_SOURCE.into())], 1);
+
+        let csv = map.by_tool_csv();
+        assert!(csv.starts_with("technique,total,Sigma,Splunk\n"));
+        assert!(csv.contains("T1059,3,2,1\n"));
+        assert!(
+            map.by_source_csv()
+                .contains("T1105,Sigma,direct download,1\n")
+        );
+    }
+}
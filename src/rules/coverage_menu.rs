@@ -0,0 +1,90 @@
+use super::attack_data::{AttackData, AttackDomain, bundle_path};
+use super::coverage::{CoverageMap, RuleList, scan_dir};
+use super::navigator::{layer_file_name, navigator_layer, write_layer};
+use crate::settings::Settings;
+use eframe::egui::Context;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// A finished scan, with the ATT&CK matrix when a bundle is cached locally.
+pub struct CoverageScan {
+    pub map: CoverageMap,
+    pub domain: AttackDomain,
+    pub attack: Option<AttackData>,
+    /// Hover text per technique, from [`CoverageMap::rule_lists`].
+    pub rule_lists: BTreeMap<String, RuleList>,
+}
+
+pub struct CoverageApp {
+    /// Rule output root holding the per-tool folders.
+    pub input_dir: String,
+    pub export_dir: String,
+    /// Only count rules from this tool; `None` = all tools.
+    pub tool_filter: Option<String>,
+    pub scanning: Arc<AtomicBool>,
+    pub scan: Arc<Mutex<Option<CoverageScan>>>,
+    /// Message from the last CSV or Navigator export.
+    pub status: Option<String>,
+}
+
+impl Default for CoverageApp {
+    fn default() -> Self {
+        Self {
+            input_dir: "./rule_output".to_string(),
+            export_dir: "./coverage_output".to_string(),
+            tool_filter: None,
+            scanning: Arc::new(AtomicBool::new(false)),
+            scan: Arc::new(Mutex::new(None)),
+            status: None,
+        }
+    }
+}
+
+impl CoverageApp {
+    /// Scan the rule folders on a background thread.
+    pub fn start(&mut self, ctx: &Context) {
+        let root = PathBuf::from(&self.input_dir);
+        let mitre = Settings::load().mitre;
+        let scanning = Arc::clone(&self.scanning);
+        let scan = Arc::clone(&self.scan);
+        let ctx = ctx.clone();
+
+        scanning.store(true, Ordering::Relaxed);
+        self.status = None;
+        thread::spawn(move || {
+            let map = scan_dir(&root);
+            let path = bundle_path(&mitre.stix_file, &mitre.cache_dir, mitre.domain);
+            let attack = AttackData::load(&path, mitre.domain).ok();
+            *scan.lock().unwrap() = Some(CoverageScan {
+                rule_lists: map.rule_lists(),
+                map,
+                domain: mitre.domain,
+                attack,
+            });
+            scanning.store(false, Ordering::Relaxed);
+            ctx.request_repaint();
+        });
+    }
+
+    pub fn export_csv(&mut self, map: &CoverageMap) {
+        self.status = Some(match map.write_csv(&PathBuf::from(&self.export_dir)) {
+            Ok(files) => format!("✅ Wrote {} CSV files to {}", files.len(), self.export_dir),
+            Err(e) => format!("❌ {}", e),
+        });
+    }
+
+    /// Write a Navigator layer for the current tool filter into the export folder.
+    pub fn export_layer(&mut self, scan: &CoverageScan) {
+        let tool = self.tool_filter.as_deref();
+        let version = scan.attack.as_ref().map(|a| a.version.as_str());
+        let layer = navigator_layer(&scan.map, tool, scan.domain, version);
+        let path = PathBuf::from(&self.export_dir).join(layer_file_name(tool));
+        self.status = Some(match write_layer(&layer, &path) {
+            Ok(()) => format!("✅ Wrote Navigator layer {}", path.display()),
+            Err(e) => format!("❌ {}", e),
+        });
+    }
+}
@@ -1,13 +1,142 @@
-use reqwest;
+use super::attack_data::{AttackData, AttackDomain, load_or_download};
+use crate::ioc::model::PRODUCER_NAME;
+use anyhow::{Result, anyhow, bail};
+use chrono::Utc;
+use regex::Regex;
 use scraper::{Html, Selector};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 use std::fs;
-use std::path::Path;
-use chrono::{DateTime, Utc};
-use anyhow::{Result, anyhow};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Values stamped into every generated analytic, saved with the other settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MitreSettings {
+    pub creator: String,
+    /// Macro the results are `collect`ed into.
+    pub index_macro: String,
+    /// Macro applied before the table to drop known-good activity.
+    pub whitelist_macro: String,
+    pub priority: String,
+    /// Fallback when the technique data carries no ATT&CK version.
+    pub mitre_version: String,
+    pub output_dir: String,
+    /// Read techniques from the ATT&CK STIX bundle instead of scraping attack.mitre.org.
+    pub offline: bool,
+    pub domain: AttackDomain,
+    /// Local bundle to use; empty means the cached download in `cache_dir`.
+    pub stix_file: String,
+    pub cache_dir: String,
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Default for MitreSettings {
+    fn default() -> Self {
+        Self {
+            creator: PRODUCER_NAME.into(),
+            index_macro: "mitre_index".into(),
+            whitelist_macro: "process_create_whitelist".into(),
+            priority: "medium".into(),
+            mitre_version: "v16".into(),
+            output_dir: "./mitre_analytics".into(),
+            offline: true,
+            domain: AttackDomain::Enterprise,
+            stix_file: String::new(),
+            cache_dir: "./attack_data".into(),
+        }
+    }
+}
+
+pub const PRIORITIES: [&str; 4] = ["low", "medium", "high", "critical"];
+
+/// Enterprise ATT&CK tactics, so users can type a name instead of a TA number.
+pub const TACTICS: [(&str, &str); 14] = [
+    ("TA0043", "Reconnaissance"),
+    ("TA0042", "Resource Development"),
+    ("TA0001", "Initial Access"),
+    ("TA0002", "Execution"),
+    ("TA0003", "Persistence"),
+    ("TA0004", "Privilege Escalation"),
+    ("TA0005", "Defense Evasion"),
+    ("TA0006", "Credential Access"),
+    ("TA0007", "Discovery"),
+    ("TA0008", "Lateral Movement"),
+    ("TA0009", "Collection"),
+    ("TA0011", "Command and Control"),
+    ("TA0010", "Exfiltration"),
+    ("TA0040", "Impact"),
+];
+
+/// `T1055`, `t1055.001` or `1055` -> `1055` / `1055.001`, the form the client takes.
+pub fn normalize_technique_id(input: &str) -> Option<String> {
+    let id = input.trim();
+    let id = id.strip_prefix(['T', 't']).unwrap_or(id);
+    let (base, sub) = match id.split_once('.') {
+        Some((b, s)) => (b, Some(s)),
+        None => (id, None),
+    };
+    let digits = |s: &str, n: usize| s.len() == n && s.chars().all(|c| c.is_ascii_digit());
+    if !digits(base, 4) {
+        return None;
+    }
+    match sub {
+        None => Some(base.to_string()),
+        Some(s) if digits(s, 3) => Some(format!("{}.{}", base, s)),
+        Some(_) => None,
+    }
+}
+
+/// `TA0002`, `execution` or `Command and Control` / `command-and-control` -> `TA00xx`.
+pub fn tactic_id(input: &str) -> Option<&'static str> {
+    let wanted = input.trim().replace(['-', '_'], " ");
+    TACTICS
+        .iter()
+        .find(|(id, name)| id.eq_ignore_ascii_case(&wanted) || name.eq_ignore_ascii_case(&wanted))
+        .map(|(id, _)| *id)
+}
+
+/// Split free text into technique IDs and tactics. Commas and newlines always
+/// separate; spaces only separate when the chunk is not a tactic name.
+pub fn split_inputs(text: &str) -> Vec<String> {
+    text.split([',', '\n', ';'])
+        .map(str::trim)
+        .filter(|chunk| !chunk.is_empty())
+        .flat_map(|chunk| {
+            if tactic_id(chunk).is_some() {
+                vec![chunk.to_string()]
+            } else {
+                chunk.split_whitespace().map(str::to_string).collect()
+            }
+        })
+        .collect()
+}
+
+/// Escape `value` for a double-quoted SPL string.
+fn spl_string(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Macro names cannot hold backticks, which would end the macro call early.
+fn spl_macro(name: &str) -> String {
+    name.replace('`', "")
+}
+
+/// Technique and sub-technique IDs linked from a tactic page, in the client's form.
+fn techniques_in_tactic_page(html: &str) -> Vec<String> {
+    let re = Regex::new(r#"href="/techniques/T(\d{4})(?:/(\d{3}))?/?""#).unwrap();
+    let found: BTreeSet<String> = re
+        .captures_iter(html)
+        .map(|c| match c.get(2) {
+            Some(sub) => format!("{}.{}", &c[1], sub.as_str()),
+            None => c[1].to_string(),
+        })
+        .collect();
+    found.into_iter().collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AnalyticQuery {
     pub description: String,
     pub query: String,
@@ -26,26 +155,76 @@ pub struct MitreTechniqueDetails {
     pub url: String,
     pub technique_id: String,
     pub subtechnique_id: String,
+    #[serde(default)]
+    pub data_sources: Vec<String>,
+    #[serde(default)]
+    pub detection: String,
+    /// ATT&CK release the details came from, e.g. `v16.1`; empty when scraped.
+    #[serde(default)]
+    pub mitre_version: String,
+}
+
+/// Swap raw sourcetypes in ATT&CK analytics for the macros our searches use.
+pub fn macroize_query(query: &str) -> String {
+    query
+        .replace(
+            "sourcetype=WinEventLog:Microsoft-Windows-Sysmon/Operational",
+            "`sysmon`",
+        )
+        .replace("sourcetype=WinEventLog:Security", "`windows-security`")
 }
 
 pub struct MitreClient {
     client: reqwest::Client,
+    /// When set, technique and tactic lookups use this bundle and never hit the website.
+    attack: Option<Arc<AttackData>>,
+}
+
+impl Default for MitreClient {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MitreClient {
     pub fn new() -> Self {
         Self {
             client: reqwest::Client::new(),
+            attack: None,
         }
     }
 
-    pub async fn get_mitre_technique_details(&self, technique_id: &str) -> Result<MitreTechniqueDetails> {
+    pub fn with_attack_data(attack: Arc<AttackData>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            attack: Some(attack),
+        }
+    }
+
+    pub async fn get_mitre_technique_details(
+        &self,
+        technique_id: &str,
+    ) -> Result<MitreTechniqueDetails> {
+        if let Some(attack) = &self.attack {
+            return attack.technique_details(technique_id).ok_or_else(|| {
+                anyhow!(
+                    "Technique T{} is not in ATT&CK {} {}",
+                    technique_id,
+                    attack.domain.label(),
+                    attack.version_label()
+                )
+            });
+        }
+
         let url = self.build_technique_url(technique_id);
-        
+
         let response = self.client.get(&url).send().await?;
-        
+
         if !response.status().is_success() {
-            return Err(anyhow!("Failed to fetch details for Technique ID {}", technique_id));
+            return Err(anyhow!(
+                "Failed to fetch details for Technique ID {}",
+                technique_id
+            ));
         }
 
         let html_content = response.text().await?;
@@ -77,15 +256,69 @@ impl MitreClient {
             url,
             technique_id: technique_id_clean,
             subtechnique_id,
+            data_sources: Vec::new(),
+            detection: String::new(),
+            mitre_version: String::new(),
         })
     }
 
+    /// Every technique and sub-technique listed under an Enterprise tactic.
+    pub async fn get_tactic_techniques(&self, tactic: &str) -> Result<Vec<String>> {
+        if let Some(attack) = &self.attack {
+            let found = attack
+                .tactic(tactic)
+                .ok_or_else(|| anyhow!("Unknown tactic {}", tactic))?;
+            return Ok(attack
+                .techniques_for_tactic(found)
+                .into_iter()
+                .map(|id| id.trim_start_matches('T').to_string())
+                .collect());
+        }
+
+        let id = tactic_id(tactic).ok_or_else(|| anyhow!("Unknown tactic {}", tactic))?;
+        let url = format!("https://attack.mitre.org/tactics/{}/", id);
+
+        let response = self.client.get(&url).send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Failed to fetch techniques for tactic {}", id));
+        }
+        Ok(techniques_in_tactic_page(&response.text().await?))
+    }
+
+    /// Turn user input (technique IDs and/or tactics, comma or space separated)
+    /// into a deduplicated list of technique IDs.
+    pub async fn resolve_targets(&self, inputs: &[String]) -> Result<Vec<String>> {
+        let mut ids = Vec::new();
+        for input in inputs {
+            if let Some(id) = normalize_technique_id(input) {
+                ids.push(id);
+            } else if self.is_tactic(input) {
+                ids.extend(self.get_tactic_techniques(input).await?);
+            } else {
+                bail!("{:?} is neither a technique ID nor a tactic", input);
+            }
+        }
+        let mut seen = HashSet::new();
+        ids.retain(|id| seen.insert(id.clone()));
+        Ok(ids)
+    }
+
+    fn is_tactic(&self, input: &str) -> bool {
+        match &self.attack {
+            Some(attack) => attack.tactic(input).is_some(),
+            None => tactic_id(input).is_some(),
+        }
+    }
+
     fn build_technique_url(&self, technique_id: &str) -> String {
         if technique_id.contains('.') {
             let parts: Vec<&str> = technique_id.split('.').collect();
             let base_id = parts[0];
             let sub_id = parts[1].parse::<u32>().unwrap_or(0);
-            format!("https://attack.mitre.org/techniques/T{}/{:03}/", base_id, sub_id)
+            format!(
+                "https://attack.mitre.org/techniques/T{}/{:03}/",
+                base_id, sub_id
+            )
         } else {
             format!("https://attack.mitre.org/techniques/T{}/", technique_id)
         }
@@ -93,10 +326,15 @@ impl MitreClient {
 
     fn extract_name_and_subtechnique(&self, document: &Html) -> Result<(String, String)> {
         let h1_selector = Selector::parse("h1").unwrap();
-        
+
         if let Some(h1_element) = document.select(&h1_selector).next() {
-            let raw_name = h1_element.text().collect::<Vec<_>>().join("").trim().to_string();
-            
+            let raw_name = h1_element
+                .text()
+                .collect::<Vec<_>>()
+                .join("")
+                .trim()
+                .to_string();
+
             if raw_name.contains(':') {
                 let parts: Vec<&str> = raw_name.splitn(2, ':').collect();
                 Ok((parts[0].trim().to_string(), parts[1].trim().to_string()))
@@ -112,16 +350,28 @@ impl MitreClient {
         let tactics_selector = Selector::parse("div.card-tactics a").unwrap();
         document
             .select(&tactics_selector)
-            .map(|element| element.text().collect::<Vec<_>>().join("").trim().to_string())
+            .map(|element| {
+                element
+                    .text()
+                    .collect::<Vec<_>>()
+                    .join("")
+                    .trim()
+                    .to_string()
+            })
             .collect()
     }
 
     fn extract_hunting_trigger(&self, document: &Html) -> String {
         let description_selector = Selector::parse("div.description-body").unwrap();
-        
+
         if let Some(description_element) = document.select(&description_selector).next() {
-            let full_text = description_element.text().collect::<Vec<_>>().join(" ").trim().to_string();
-            
+            let full_text = description_element
+                .text()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .trim()
+                .to_string();
+
             if let Some(first_sentence_end) = full_text.find('.') {
                 format!("{}.", &full_text[..first_sentence_end])
             } else {
@@ -134,9 +384,14 @@ impl MitreClient {
 
     fn extract_mitre_category(&self, document: &Html) -> String {
         let tactics_selector = Selector::parse("div#card-tactics a").unwrap();
-        
+
         if let Some(tactic_element) = document.select(&tactics_selector).next() {
-            tactic_element.text().collect::<Vec<_>>().join("").trim().to_string()
+            tactic_element
+                .text()
+                .collect::<Vec<_>>()
+                .join("")
+                .trim()
+                .to_string()
         } else {
             "Unknown".to_string()
         }
@@ -149,34 +404,32 @@ impl MitreClient {
 
         for p_element in document.select(&p_selector) {
             let p_text = p_element.text().collect::<Vec<_>>().join("");
-            
-            if p_text.contains("Analytic") {
-                if let Some(code_element) = p_element.select(&code_selector).next() {
-                    let mut query_text = code_element.text().collect::<Vec<_>>().join("").trim().to_string();
-                    
-                    // Replace sourcetypes with more readable macros
-                    query_text = query_text.replace(
-                        "sourcetype=WinEventLog:Microsoft-Windows-Sysmon/Operational",
-                        "`sysmon`"
-                    );
-                    query_text = query_text.replace(
-                        "sourcetype=WinEventLog:Security",
-                        "`windows-security`"
-                    );
-
-                    // Remove "Analytic # -" prefix from description
-                    let mut description = p_text.trim().to_string();
-                    if description.starts_with("Analytic") {
-                        if let Some(dash_pos) = description.find('-') {
-                            description = description[dash_pos + 1..].trim().to_string();
-                        }
-                    }
-
-                    analytics.push(AnalyticQuery {
-                        description,
-                        query: query_text,
-                    });
+
+            if p_text.contains("Analytic")
+                && let Some(code_element) = p_element.select(&code_selector).next()
+            {
+                let mut query_text: String = code_element
+                    .text()
+                    .collect::<Vec<_>>()
+                    .join("")
+                    .trim()
+                    .to_string();
+
+                // Replace sourcetypes with more readable macros
+                query_text = macroize_query(&query_text);
+
+                // Remove "Analytic # -" prefix from description
+                let mut description = p_text.trim().to_string();
+                if description.starts_with("Analytic")
+                    && let Some(dash_pos) = description.find('-')
+                {
+                    description = description[dash_pos + 1..].trim().to_string();
                 }
+
+                analytics.push(AnalyticQuery {
+                    description,
+                    query: query_text,
+                });
             }
         }
 
@@ -192,12 +445,25 @@ impl MitreClient {
 
         for row in document.select(&table_selector) {
             let columns: Vec<_> = row.select(&td_selector).collect();
-            
+
             if columns.len() > 2 {
-                let apt_id = columns[0].text().collect::<Vec<_>>().join("").trim().to_string();
-                let apt_name = columns[1].text().collect::<Vec<_>>().join("").trim().to_string();
-                
-                if apt_id.starts_with('G') && apt_id.len() == 5 && apt_id[1..].chars().all(|c| c.is_ascii_digit()) {
+                let apt_id = columns[0]
+                    .text()
+                    .collect::<Vec<_>>()
+                    .join("")
+                    .trim()
+                    .to_string();
+                let apt_name = columns[1]
+                    .text()
+                    .collect::<Vec<_>>()
+                    .join("")
+                    .trim()
+                    .to_string();
+
+                if apt_id.starts_with('G')
+                    && apt_id.len() == 5
+                    && apt_id[1..].chars().all(|c| c.is_ascii_digit())
+                {
                     apts.insert(apt_name);
                 }
             }
@@ -213,31 +479,48 @@ impl MitreClient {
         technique_id: &str,
         details: &MitreTechniqueDetails,
         today_date: &str,
-    ) -> Result<()> {
-        let base_folder = "MITRE_Alerts";
-        fs::create_dir_all(base_folder)?;
+        settings: &MitreSettings,
+    ) -> Result<Vec<PathBuf>> {
+        let base_folder = PathBuf::from(&settings.output_dir);
+        fs::create_dir_all(&base_folder)?;
 
         let mitre_category = &details.mitre_category;
         if mitre_category == "Unknown" {
-            println!("Skipping analytics save for Technique ID {} due to unknown category.", technique_id);
-            return Ok(());
+            println!(
+                "Skipping analytics save for Technique ID {} due to unknown category.",
+                technique_id
+            );
+            return Ok(Vec::new());
         }
 
-        let category_folder = format!("{}/{}", base_folder, mitre_category.replace(", ", "_"));
+        let category_folder = base_folder.join(mitre_category.replace(", ", "_"));
         fs::create_dir_all(&category_folder)?;
 
-        let apts_joined = details.apts.join("\",\"");
+        let apts_joined = details
+            .apts
+            .iter()
+            .map(|apt| spl_string(apt))
+            .collect::<Vec<_>>()
+            .join("\",\"");
 
+        let mut written = Vec::new();
         for (index, analytic) in details.analytics.iter().enumerate() {
-            let file_title = format!("[T{}] {}_Analytic_{}.txt", technique_id, details.name, index + 1);
-            let file_path = format!("{}/{}", category_folder, file_title);
-
-            let content = self.build_splunk_query(analytic, details, &apts_joined, today_date);
+            let file_title = format!(
+                "[T{}] {}_Analytic_{}.txt",
+                technique_id,
+                details.name,
+                index + 1
+            );
+            let file_path = category_folder.join(file_title.replace(['/', '\\'], "_"));
+
+            let content =
+                self.build_splunk_query(analytic, details, &apts_joined, today_date, settings);
             fs::write(&file_path, content)?;
+            written.push(file_path);
         }
 
-        println!("Analytics saved in folder: {}", category_folder);
-        Ok(())
+        println!("Analytics saved in folder: {}", category_folder.display());
+        Ok(written)
     }
 
     fn build_splunk_query(
@@ -246,6 +529,7 @@ impl MitreClient {
         details: &MitreTechniqueDetails,
         apts_joined: &str,
         today_date: &str,
+        settings: &MitreSettings,
     ) -> String {
         format!(
             r#"`indextime` {}
@@ -258,46 +542,101 @@ mitre_subtechnique="{}",
 mitre_subtechnique_id="{}",
 apt=mvappend("{}"),
 mitre_link="{}",
-creator="Cpl Iverson",
+creator="{}",
 upload_date="{}",
 last_modify_date="{}",
-mitre_version="v16",
-priority="medium"
-| `process_create_whitelist`
+mitre_version="{}",
+priority="{}"
+| `{}`
 | eval indextime = _indextime
 | convert ctime(indextime)
 | table _time indextime event_description hash_sha256 host_fqdn user_name original_file_name process_path process_guid process_parent_path process_id process_parent_id process_command_line process_parent_command_line process_parent_guid mitre_category mitre_technique mitre_technique_id hunting_trigger mitre_subtechnique mitre_subtechnique_id apt mitre_link creator upload_date last_modify_date mitre_version priority
-| collect `jarvis_index`
+| collect `{}`
 "#,
             analytic.query,
-            analytic.description,
-            details.mitre_category,
-            details.name,
-            details.technique_id,
-            details.subtechnique,
-            details.subtechnique_id,
+            spl_string(&analytic.description),
+            spl_string(&details.mitre_category),
+            spl_string(&details.name),
+            spl_string(&details.technique_id),
+            spl_string(&details.subtechnique),
+            spl_string(&details.subtechnique_id),
             apts_joined,
-            details.url,
+            spl_string(&details.url),
+            spl_string(&settings.creator),
             today_date,
-            today_date
+            today_date,
+            spl_string(if details.mitre_version.is_empty() {
+                &settings.mitre_version
+            } else {
+                &details.mitre_version
+            }),
+            spl_string(&settings.priority),
+            spl_macro(&settings.whitelist_macro),
+            spl_macro(&settings.index_macro)
         )
     }
 }
 
-// Convenience function for single technique processing
-pub async fn process_mitre_technique(technique_id: &str) -> Result<()> {
-    let client = MitreClient::new();
+#[derive(Debug, Clone, Default)]
+pub struct GenerateSummary {
+    pub techniques: usize,
+    /// ATT&CK release used, when the offline bundle was loaded.
+    pub attack_version: Option<String>,
+    pub files: Vec<PathBuf>,
+    /// Technique ID and error for every technique that could not be processed.
+    pub failures: Vec<(String, String)>,
+}
+
+/// Resolve `inputs` and write analytics for each technique, calling
+/// `report(done, total, current)` as it goes. One failing technique does not stop the rest.
+pub async fn generate_analytics(
+    inputs: &[String],
+    settings: &MitreSettings,
+    cancel_flag: &AtomicBool,
+    mut report: impl FnMut(usize, usize, &str),
+) -> Result<GenerateSummary> {
+    let client = if settings.offline {
+        report(0, 1, "Loading ATT&CK data");
+        let attack = load_or_download(
+            &settings.stix_file,
+            &settings.cache_dir,
+            settings.domain,
+            false,
+        )
+        .await?;
+        MitreClient::with_attack_data(Arc::new(attack))
+    } else {
+        MitreClient::new()
+    };
+    let ids = client.resolve_targets(inputs).await?;
     let today_date = Utc::now().format("%Y-%m-%d").to_string();
-    
-    let details = client.get_mitre_technique_details(technique_id).await?;
-    
-    if details.analytics.is_empty() {
-        println!("No Analytics Queries Found for {}", technique_id);
-        return Ok(());
+
+    let mut summary = GenerateSummary {
+        techniques: ids.len(),
+        attack_version: client.attack.as_ref().map(|a| a.version_label()),
+        ..Default::default()
+    };
+    for (done, id) in ids.iter().enumerate() {
+        if cancel_flag.load(Ordering::Relaxed) {
+            break;
+        }
+        report(done, ids.len(), &format!("T{}", id));
+
+        let result = match client.get_mitre_technique_details(id).await {
+            Ok(details) => {
+                client
+                    .save_analytics_to_files(id, &details, &today_date, settings)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(files) => summary.files.extend(files),
+            Err(e) => summary.failures.push((format!("T{}", id), e.to_string())),
+        }
     }
-    
-    client.save_analytics_to_files(technique_id, &details, &today_date).await?;
-    Ok(())
+    report(ids.len(), ids.len(), "");
+    Ok(summary)
 }
 
 #[cfg(test)]
@@ -306,21 +645,97 @@ mod tests {
 
     #[tokio::test]
     async fn test_mitre_client_creation() {
-        let client = MitreClient::new();
+        let _client = MitreClient::new();
         // Just test that we can create a client
-        assert!(true);
     }
 
     #[tokio::test]
     async fn test_url_building() {
         let client = MitreClient::new();
-        
+
         // Test main technique
         let url1 = client.build_technique_url("1055");
         assert_eq!(url1, "https://attack.mitre.org/techniques/T1055/");
-        
+
         // Test subtechnique
         let url2 = client.build_technique_url("1055.001");
         assert_eq!(url2, "https://attack.mitre.org/techniques/T1055/001/");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_input_parsing() {
+        assert_eq!(normalize_technique_id("T1055"), Some("1055".into()));
+        assert_eq!(
+            normalize_technique_id(" t1055.001 "),
+            Some("1055.001".into())
+        );
+        assert_eq!(normalize_technique_id("1055.1"), None);
+        assert_eq!(tactic_id("command-and-control"), Some("TA0011"));
+        assert_eq!(tactic_id("ta0002"), Some("TA0002"));
+        assert_eq!(tactic_id("T1055"), None);
+
+        assert_eq!(
+            split_inputs("T1055 T1003,\nCommand and Control"),
+            vec!["T1055", "T1003", "Command and Control"]
+        );
+
+        let html = r#"<a href="/techniques/T1059">T1059</a><a href="/techniques/T1059/001">.001</a><a href="/techniques/T1059">again</a>"#;
+        assert_eq!(techniques_in_tactic_page(html), vec!["1059", "1059.001"]);
+    }
+
+    #[test]
+    fn test_query_uses_settings() {
+        let client = MitreClient::new();
+        let details = MitreTechniqueDetails {
+            id: "T1055".into(),
+            name: "Process Injection".into(),
+            subtechnique: String::new(),
+            tactics: vec!["Defense Evasion".into()],
+            hunting_trigger: String::new(),
+            mitre_category: "Defense Evasion".into(),
+            analytics: Vec::new(),
+            apts: Vec::new(),
+            url: String::new(),
+            technique_id: "T1055".into(),
+            subtechnique_id: String::new(),
+            data_sources: Vec::new(),
+            detection: String::new(),
+            mitre_version: String::new(),
+        };
+        let analytic = AnalyticQuery {
+            description: "Remote thread".into(),
+            query: "`sysmon` EventCode=8".into(),
+        };
+        let settings = MitreSettings {
+            creator: "SOC Team".into(),
+            index_macro: "soc_index".into(),
+            whitelist_macro: "soc_whitelist".into(),
+            priority: "high".into(),
+            ..Default::default()
+        };
+        let query = client.build_splunk_query(&analytic, &details, "", "2025-01-01", &settings);
+        assert!(query.contains("creator=\"SOC Team\""));
+        assert!(query.contains("priority=\"high\""));
+        assert!(query.contains("| `soc_whitelist`"));
+        assert!(query.contains("| collect `soc_index`"));
+        assert!(!query.contains(PRODUCER_NAME));
+        assert!(query.contains("mitre_version=\"v16\""));
+
+        let versioned = MitreTechniqueDetails {
+            mitre_version: "v17.0".into(),
+            ..details
+        };
+        let query = client.build_splunk_query(&analytic, &versioned, "", "2025-01-01", &settings);
+        assert!(query.contains("mitre_version=\"v17.0\""));
+
+        // Quotes and backticks in settings cannot break out of the SPL strings or macros.
+        let settings = MitreSettings {
+            creator: "SOC \"Blue\" \\ Team".into(),
+            index_macro: "soc`_index".into(),
+            ..settings
+        };
+        let query = client.build_splunk_query(&analytic, &versioned, "", "2025-01-01", &settings);
+        assert!(query.contains(r#"creator="SOC \"Blue\" \\ Team","#));
+        assert!(query.contains("| collect `soc_index`"));
+    }
+}
@@ -0,0 +1,61 @@
+use super::hunt_pack::{HuntPack, build_hunt_pack};
+use crate::settings::Settings;
+use eframe::egui::Context;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+pub struct HuntApp {
+    /// Group ID, name or alias, e.g. `G0016` or `APT29`.
+    pub group: String,
+    pub rule_dir: String,
+    pub ioc_dir: String,
+    pub out_dir: String,
+    pub running: Arc<AtomicBool>,
+    /// Outcome of the last run, filled in by the worker thread.
+    pub result: Arc<Mutex<Option<Result<HuntPack, String>>>>,
+}
+
+impl Default for HuntApp {
+    fn default() -> Self {
+        Self {
+            group: String::new(),
+            rule_dir: "./rule_output".to_string(),
+            ioc_dir: "./ioc_output".to_string(),
+            out_dir: "./hunt_packs".to_string(),
+            running: Arc::new(AtomicBool::new(false)),
+            result: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl HuntApp {
+    /// Resolve the group and write its hunt pack on a background thread.
+    pub fn start(&mut self, ctx: &Context) {
+        let group = self.group.trim().to_string();
+        let rule_dir = PathBuf::from(&self.rule_dir);
+        let ioc_dir = PathBuf::from(&self.ioc_dir);
+        let out_dir = PathBuf::from(&self.out_dir);
+        let mitre = Settings::load().mitre;
+        let running = Arc::clone(&self.running);
+        let result = Arc::clone(&self.result);
+        let ctx = ctx.clone();
+
+        running.store(true, Ordering::Relaxed);
+        *result.lock().unwrap() = None;
+        thread::spawn(move || {
+            let outcome = match tokio::runtime::Runtime::new() {
+                Ok(rt) => rt
+                    .block_on(build_hunt_pack(
+                        &group, &rule_dir, &ioc_dir, &out_dir, &mitre, false,
+                    ))
+                    .map_err(|e| format!("{:#}", e)),
+                Err(e) => Err(e.to_string()),
+            };
+            *result.lock().unwrap() = Some(outcome);
+            running.store(false, Ordering::Relaxed);
+            ctx.request_repaint();
+        });
+    }
+}
@@ -0,0 +1,436 @@
+use super::attack_data::{AttackData, Group, load_or_download};
+use super::coverage::{CoverageMap, DIRECT_SOURCE, file_techniques, is_rule_file};
+use super::grab_mitre::MitreSettings;
+use super::navigator::{navigator_layer, write_layer};
+use crate::download::source_of;
+use crate::ioc::csv_export::csv_field;
+use crate::ioc::database::DATABASE_FILE;
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Rule folders a hunt pack draws from, as (tool, subfolder).
+pub const HUNT_TOOLS: [(&str, &str); 3] =
+    [("Sigma", "sigma"), ("Splunk", "splunk"), ("Yara", "yara")];
+
+/// Aliases shorter than this are too ambiguous to match in free text.
+const MIN_ALIAS_LEN: usize = 4;
+
+/// Matches a group's ID, name or aliases in rule text, tolerating `APT29`,
+/// `apt_29`, `APT-29` and `attack.g0016`.
+pub struct GroupMatcher {
+    re: Regex,
+}
+
+impl GroupMatcher {
+    pub fn new(group: &Group) -> Self {
+        let mut names: BTreeSet<String> = BTreeSet::new();
+        for name in std::iter::once(&group.id)
+            .chain(std::iter::once(&group.name))
+            .chain(&group.aliases)
+        {
+            let tokens = name_tokens(name);
+            if tokens.concat().len() >= MIN_ALIAS_LEN {
+                let escaped: Vec<String> = tokens.iter().map(|t| regex::escape(t)).collect();
+                names.insert(escaped.join(r"[\s_-]*"));
+            }
+        }
+        let alternatives: Vec<String> = names.into_iter().collect();
+        let pattern = format!(
+            r"(?i)(?:^|[^a-z0-9])(?:{})(?:[^a-z0-9]|$)",
+            alternatives.join("|")
+        );
+        Self {
+            re: Regex::new(&pattern).expect("escaped group names form a valid regex"),
+        }
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        self.re.is_match(text)
+    }
+}
+
+/// Split a name into runs of letters and runs of digits: `APT29` -> `APT`, `29`.
+fn name_tokens(name: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = Vec::new();
+    let mut last_digit = None;
+    for c in name.chars() {
+        if !c.is_ascii_alphanumeric() {
+            last_digit = None;
+            continue;
+        }
+        let digit = c.is_ascii_digit();
+        match tokens.last_mut() {
+            Some(token) if last_digit == Some(digit) => token.push(c),
+            _ => tokens.push(c.to_string()),
+        }
+        last_digit = Some(digit);
+    }
+    tokens
+}
+
+/// A rule covers a group technique when the IDs match, or when the rule targets a
+/// sub-technique of a technique the group uses.
+pub fn technique_matches(rule: &str, group_techniques: &BTreeSet<String>) -> bool {
+    group_techniques.contains(rule)
+        || rule
+            .split_once('.')
+            .is_some_and(|(parent, _)| group_techniques.contains(parent))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchReason {
+    Technique,
+    GroupName,
+    Both,
+}
+
+impl MatchReason {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Technique => "technique",
+            Self::GroupName => "group name",
+            Self::Both => "technique + group name",
+        }
+    }
+}
+
+/// A rule file selected for the pack.
+#[derive(Debug, Clone)]
+pub struct PackRule {
+    pub tool: String,
+    pub path: PathBuf,
+    pub reason: MatchReason,
+    /// Rule names in the file with the group techniques they cover.
+    pub rules: Vec<(String, BTreeSet<String>)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct HuntPlan {
+    pub group: Group,
+    pub rules: Vec<PackRule>,
+    pub iocs: Vec<PathBuf>,
+    /// Group techniques with no selected rule.
+    pub gaps: Vec<String>,
+}
+
+impl HuntPlan {
+    pub fn rule_counts(&self) -> BTreeMap<&str, usize> {
+        let mut counts = BTreeMap::new();
+        for rule in &self.rules {
+            *counts.entry(rule.tool.as_str()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Coverage of the group's techniques by the selected rules.
+    pub fn coverage(&self) -> CoverageMap {
+        let mut map = CoverageMap::default();
+        for file in &self.rules {
+            let file_name = file.path.file_name().unwrap_or_default().to_string_lossy();
+            let source = source_of(&file_name).unwrap_or(DIRECT_SOURCE);
+            for (rule, techniques) in &file.rules {
+                map.add(&file.tool, source, rule, techniques);
+            }
+        }
+        map
+    }
+}
+
+/// Resolve `input` (ID, name or alias) to a group in `attack`.
+pub fn find_group<'a>(attack: &'a AttackData, input: &str) -> Result<&'a Group> {
+    attack.group(input).ok_or_else(|| {
+        anyhow!(
+            "No ATT&CK group matches {:?} in the {} matrix",
+            input,
+            attack.domain.label()
+        )
+    })
+}
+
+fn files_under(dir: &Path) -> impl Iterator<Item = PathBuf> {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+}
+
+/// Select the rules under `rule_dir` and IOC files under `ioc_dir` relevant to `group`.
+pub fn plan_hunt_pack(group: &Group, rule_dir: &Path, ioc_dir: &Path) -> HuntPlan {
+    let matcher = GroupMatcher::new(group);
+    let mut rules = Vec::new();
+    let mut covered = BTreeSet::new();
+
+    for (tool, folder) in HUNT_TOOLS {
+        for path in files_under(&rule_dir.join(folder)) {
+            if !is_rule_file(tool, &path) {
+                continue;
+            }
+            let relevant: Vec<(String, BTreeSet<String>)> = file_techniques(tool, &path)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(name, techniques)| {
+                    let hits = techniques
+                        .into_iter()
+                        .filter(|t| technique_matches(t, &group.techniques))
+                        .collect::<BTreeSet<_>>();
+                    (name, hits)
+                })
+                .filter(|(_, hits)| !hits.is_empty())
+                .collect();
+            let by_name = fs::read(&path)
+                .map(|bytes| matcher.matches(&String::from_utf8_lossy(&bytes)))
+                .unwrap_or(false);
+            let reason = match (!relevant.is_empty(), by_name) {
+                (true, true) => MatchReason::Both,
+                (true, false) => MatchReason::Technique,
+                (false, true) => MatchReason::GroupName,
+                (false, false) => continue,
+            };
+            covered.extend(relevant.iter().flat_map(|(_, hits)| hits.iter().cloned()));
+            rules.push(PackRule {
+                tool: tool.to_string(),
+                path,
+                reason,
+                rules: relevant,
+            });
+        }
+    }
+
+    let iocs = files_under(ioc_dir)
+        .filter(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            // The IOC database holds every type ever collected; only exports go in packs.
+            name != DATABASE_FILE
+                && (matcher.matches(&name)
+                    || fs::read(path)
+                        .map(|bytes| matcher.matches(&String::from_utf8_lossy(&bytes)))
+                        .unwrap_or(false))
+        })
+        .collect();
+
+    let gaps = group
+        .techniques
+        .iter()
+        .filter(|t| {
+            !covered.contains(*t) && !covered.iter().any(|c| c.starts_with(&format!("{}.", t)))
+        })
+        .cloned()
+        .collect();
+
+    HuntPlan {
+        group: group.clone(),
+        rules,
+        iocs,
+        gaps,
+    }
+}
+
+/// Folder name for a pack, e.g. `G0016_APT29_2025-01-31`.
+pub fn pack_dir_name(group: &Group, today: &str) -> String {
+    let name: String = group
+        .name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{}_{}_{}", group.id, name, today)
+}
+
+/// Copy the selected files into `<out_root>/<pack_dir_name>` alongside a
+/// `techniques.csv`, a Navigator layer and a README summarising matches and gaps.
+pub fn write_hunt_pack(
+    attack: &AttackData,
+    plan: &HuntPlan,
+    out_root: &Path,
+    today: &str,
+) -> Result<PathBuf> {
+    let pack = out_root.join(pack_dir_name(&plan.group, today));
+    fs::create_dir_all(&pack)?;
+
+    for rule in &plan.rules {
+        let dest = pack.join(rule.tool.to_ascii_lowercase());
+        fs::create_dir_all(&dest)?;
+        fs::copy(
+            &rule.path,
+            dest.join(rule.path.file_name().unwrap_or_default()),
+        )?;
+    }
+    if !plan.iocs.is_empty() {
+        let dest = pack.join("iocs");
+        fs::create_dir_all(&dest)?;
+        for ioc in &plan.iocs {
+            fs::copy(ioc, dest.join(ioc.file_name().unwrap_or_default()))?;
+        }
+    }
+
+    let coverage = plan.coverage();
+    let mut csv = String::from("technique,name,tactics,rules\n");
+    for id in &plan.group.techniques {
+        let (name, tactics) = attack
+            .technique(id)
+            .map(|t| (t.name.as_str(), t.tactics.join("; ")))
+            .unwrap_or_default();
+        csv.push_str(&format!(
+            "{},{},{},{}\n",
+            id,
+            csv_field(name),
+            csv_field(&tactics),
+            coverage.count_with_subtechniques(id, None)
+        ));
+    }
+    fs::write(pack.join("techniques.csv"), csv)?;
+
+    let mut layer = navigator_layer(&coverage, None, attack.domain, Some(&attack.version));
+    layer["name"] = format!("{} hunt pack", plan.group.name).into();
+    write_layer(&layer, &pack.join("navigator_layer.json"))?;
+
+    fs::write(pack.join("README.md"), readme(attack, plan, today))?;
+    Ok(pack)
+}
+
+/// A written pack and what went into it.
+#[derive(Debug, Clone)]
+pub struct HuntPack {
+    pub plan: HuntPlan,
+    pub dir: PathBuf,
+}
+
+/// Load ATT&CK per `mitre`, resolve `group_input` and write the pack into `out_root`.
+pub async fn build_hunt_pack(
+    group_input: &str,
+    rule_dir: &Path,
+    ioc_dir: &Path,
+    out_root: &Path,
+    mitre: &MitreSettings,
+    refresh: bool,
+) -> Result<HuntPack> {
+    let attack =
+        load_or_download(&mitre.stix_file, &mitre.cache_dir, mitre.domain, refresh).await?;
+    let group = find_group(&attack, group_input)?;
+    let plan = plan_hunt_pack(group, rule_dir, ioc_dir);
+    let today = Utc::now().format("%Y-%m-%d").to_string();
+    let dir = write_hunt_pack(&attack, &plan, out_root, &today)?;
+    Ok(HuntPack { plan, dir })
+}
+
+fn readme(attack: &AttackData, plan: &HuntPlan, today: &str) -> String {
+    let group = &plan.group;
+    let mut out = format!("# Hunt pack: {} ({})\n\n", group.name, group.id);
+    if !group.aliases.is_empty() {
+        out.push_str(&format!("Aliases: {}\n\n", group.aliases.join(", ")));
+    }
+    out.push_str(&format!(
+        "Generated {} from ATT&CK {} {}. {} techniques, {} rule files, {} IOC files.\n\n",
+        today,
+        attack.domain.label(),
+        attack.version_label(),
+        group.techniques.len(),
+        plan.rules.len(),
+        plan.iocs.len()
+    ));
+    out.push_str("## Rules\n\n");
+    for rule in &plan.rules {
+        let techniques: BTreeSet<&String> = rule.rules.iter().flat_map(|(_, t)| t).collect();
+        out.push_str(&format!(
+            "- [{}] {} — {}{}\n",
+            rule.tool,
+            rule.path.file_name().unwrap_or_default().to_string_lossy(),
+            rule.reason.label(),
+            if techniques.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    " ({})",
+                    techniques
+                        .into_iter()
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }
+        ));
+    }
+    if !plan.gaps.is_empty() {
+        out.push_str("\n## Techniques without rules\n\n");
+        for id in &plan.gaps {
+            let name = attack.technique(id).map_or("", |t| t.name.as_str());
+            out.push_str(&format!("- {} {}\n", id, name));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group() -> Group {
+        Group {
+            id: "G0016".into(),
+            name: "APT29".into(),
+            aliases: vec!["APT29".into(), "Cozy Bear".into(), "YT".into()],
+            techniques: ["T1059".to_string(), "T1566.001".to_string()].into(),
+        }
+    }
+
+    #[test]
+    fn matches_group_names_loosely() {
+        let m = GroupMatcher::new(&group());
+        assert!(m.matches("tags:\n  - attack.g0016\n"));
+        assert!(m.matches("rule APT_29_Loader {"));
+        assert!(m.matches("description = \"Cozy-Bear backdoor\""));
+        assert!(m.matches("apt29"));
+        assert!(!m.matches("APT290 and notAPT29x"));
+        assert!(!m.matches("YT video"));
+
+        let techniques = group().techniques;
+        assert!(technique_matches("T1059.001", &techniques));
+        assert!(technique_matches("T1566.001", &techniques));
+        assert!(!technique_matches("T1566", &techniques));
+    }
+
+    #[test]
+    fn selects_rules_iocs_and_gaps() {
+        let dir = tempfile::tempdir().unwrap();
+        let rules = dir.path().join("rules");
+        let iocs = dir.path().join("iocs");
+        fs::create_dir_all(rules.join("sigma")).unwrap();
+        fs::create_dir_all(rules.join("yara")).unwrap();
+        fs::create_dir_all(&iocs).unwrap();
+        fs::write(
+            rules.join("sigma/ps.yml"),
+            "title: PS\ntags: [attack.t1059.001]\n",
+        )
+        .unwrap();
+        fs::write(
+            rules.join("sigma/other.yml"),
+            "title: Other\ntags: [attack.t1003]\n",
+        )
+        .unwrap();
+        fs::write(
+            rules.join("yara/wellmess.yar"),
+            "rule WellMess { meta: actor = \"APT29\" condition: true }",
+        )
+        .unwrap();
+        fs::write(iocs.join("apt29_c2.txt"), "1.2.3.4\n").unwrap();
+        fs::write(iocs.join("generic.txt"), "5.6.7.8\n").unwrap();
+
+        let plan = plan_hunt_pack(&group(), &rules, &iocs);
+        assert_eq!(plan.rules.len(), 2);
+        assert_eq!(plan.rule_counts()["Sigma"], 1);
+        let yara = plan.rules.iter().find(|r| r.tool == "Yara").unwrap();
+        assert_eq!(yara.reason, MatchReason::GroupName);
+        assert_eq!(plan.iocs.len(), 1);
+        assert_eq!(plan.gaps, vec!["T1566.001"]);
+        assert_eq!(plan.coverage().count_with_subtechniques("T1059", None), 1);
+        assert_eq!(
+            pack_dir_name(&plan.group, "2025-01-31"),
+            "G0016_APT29_2025-01-31"
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use super::attack_data::{AttackData, download_bundle};
+use super::grab_mitre::{GenerateSummary, MitreSettings, generate_analytics, split_inputs};
+use crate::download::ProgressTriplet;
+use crate::settings::Settings;
+use eframe::egui::Context;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+pub struct MitreApp {
+    /// Technique IDs and/or tactics as typed by the user.
+    pub input: String,
+    pub settings: MitreSettings,
+    pub progress: ProgressTriplet,
+    pub cancel_flag: Arc<AtomicBool>,
+    /// Outcome of the last run, filled in by the worker thread.
+    pub result: Arc<Mutex<Option<Result<GenerateSummary, String>>>>,
+    /// Message from the last ATT&CK bundle download.
+    pub attack_status: Arc<Mutex<Option<String>>>,
+}
+
+impl Default for MitreApp {
+    fn default() -> Self {
+        Self {
+            input: String::new(),
+            settings: Settings::load().mitre,
+            progress: Arc::new(Mutex::new(None)),
+            cancel_flag: Arc::new(AtomicBool::new(false)),
+            result: Arc::new(Mutex::new(None)),
+            attack_status: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl MitreApp {
+    /// Generate analytics on a background thread with its own tokio runtime.
+    pub fn start(&mut self, ctx: &Context) {
+        let inputs = split_inputs(&self.input);
+        let settings = self.settings.clone();
+        let progress = Arc::clone(&self.progress);
+        let cancel_flag = Arc::clone(&self.cancel_flag);
+        let result = Arc::clone(&self.result);
+        let ctx = ctx.clone();
+
+        cancel_flag.store(false, Ordering::Relaxed);
+        *result.lock().unwrap() = None;
+        *progress.lock().unwrap() = Some((0, 1, "Resolving techniques".to_string()));
+
+        thread::spawn(move || {
+            let outcome = match tokio::runtime::Runtime::new() {
+                Ok(rt) => rt
+                    .block_on(generate_analytics(
+                        &inputs,
+                        &settings,
+                        &cancel_flag,
+                        |done, total, current| {
+                            *progress.lock().unwrap() = Some((done, total, current.to_string()));
+                            ctx.request_repaint();
+                        },
+                    ))
+                    .map_err(|e| format!("{:#}", e)),
+                Err(e) => Err(e.to_string()),
+            };
+            *result.lock().unwrap() = Some(outcome);
+            *progress.lock().unwrap() = None;
+            ctx.request_repaint();
+        });
+    }
+
+    /// Download the bundle for the selected domain into the cache, in the background.
+    pub fn refresh_attack_data(&mut self, ctx: &Context) {
+        let domain = self.settings.domain;
+        let cache_dir = self.settings.cache_dir.clone();
+        let status = Arc::clone(&self.attack_status);
+        let ctx = ctx.clone();
+
+        *status.lock().unwrap() = Some(format!("Downloading ATT&CK {}…", domain.label()));
+        thread::spawn(move || {
+            let outcome = tokio::runtime::Runtime::new()
+                .map_err(anyhow::Error::from)
+                .and_then(|rt| rt.block_on(download_bundle(domain, Path::new(&cache_dir))))
+                .and_then(|path| AttackData::load(&path, domain));
+            *status.lock().unwrap() = Some(match outcome {
+                Ok(data) => format!(
+                    "✅ ATT&CK {} {} cached ({} techniques)",
+                    domain.label(),
+                    data.version_label(),
+                    data.techniques.len()
+                ),
+                Err(e) => format!("❌ {:#}", e),
+            });
+            ctx.request_repaint();
+        });
+    }
+}
@@ -1,8 +1,28 @@
+pub mod attack_data;
+pub mod coverage;
+pub mod coverage_menu;
+pub mod grab_mitre;
+pub mod hunt_menu;
+pub mod hunt_pack;
+pub mod mitre_menu;
+pub mod navigator;
+pub mod qradar;
 pub mod rule_menu;
-pub mod ui_rule;
-pub mod yara;
 pub mod sigma;
 pub mod splunk;
+pub mod splunk_app;
+pub mod splunk_content;
 pub mod suricata;
-pub mod qradar;
-pub mod sysmon;
\ No newline at end of file
+pub mod sysmon;
+pub mod sysmon_analysis;
+pub mod sysmon_config;
+pub mod sysmon_menu;
+pub mod tool;
+pub mod ui_coverage;
+pub mod ui_hunt;
+pub mod ui_mitre;
+pub mod ui_rule;
+pub mod ui_sysmon;
+pub mod velociraptor;
+pub mod yara;
+pub mod zeek;
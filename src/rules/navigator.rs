@@ -0,0 +1,173 @@
+use super::attack_data::AttackDomain;
+use super::coverage::CoverageMap;
+use serde_json::{Value, json};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Navigator layer format written by [`navigator_layer`].
+pub const LAYER_VERSION: &str = "4.5";
+pub const NAVIGATOR_VERSION: &str = "5.1.0";
+
+/// Longest comment per technique; Navigator gets sluggish with huge tooltips.
+const MAX_COMMENT_RULES: usize = 50;
+
+/// Build an ATT&CK Navigator layer where each technique's score is its rule
+/// count and the comment lists the rules. `tool` limits both to one tool.
+/// `attack_version` is the bundle version (`16.1`); only the major part is used.
+pub fn navigator_layer(
+    map: &CoverageMap,
+    tool: Option<&str>,
+    domain: AttackDomain,
+    attack_version: Option<&str>,
+) -> Value {
+    let mut techniques = Vec::new();
+    let mut max_score = 0;
+    for (id, coverage) in &map.techniques {
+        let score = coverage.count(tool);
+        if score == 0 {
+            continue;
+        }
+        max_score = max_score.max(score);
+        let mut rules: Vec<String> = coverage
+            .rules
+            .iter()
+            .filter(|r| tool.is_none_or(|t| r.tool == t))
+            .map(|r| format!("[{}] {} ({})", r.tool, r.rule, r.source))
+            .collect();
+        rules.dedup();
+        if rules.len() > MAX_COMMENT_RULES {
+            let more = rules.len() - MAX_COMMENT_RULES;
+            rules.truncate(MAX_COMMENT_RULES);
+            rules.push(format!("… and {} more", more));
+        }
+        let has_subtechniques = !id.contains('.')
+            && map
+                .techniques
+                .range(format!("{}.", id)..)
+                .take_while(|(sub, _)| sub.starts_with(&format!("{}.", id)))
+                .any(|(_, c)| c.count(tool) > 0);
+        techniques.push(json!({
+            "techniqueID": id,
+            "score": score,
+            "color": "",
+            "comment": rules.join("\n"),
+            "enabled": true,
+            "metadata": [],
+            "links": [],
+            "showSubtechniques": has_subtechniques,
+        }));
+    }
+
+    let scope = tool.unwrap_or("all tools");
+    let mut versions = json!({ "navigator": NAVIGATOR_VERSION, "layer": LAYER_VERSION });
+    if let Some(major) = attack_version
+        .and_then(|v| v.trim_start_matches('v').split('.').next())
+        .filter(|v| !v.is_empty())
+    {
+        versions["attack"] = json!(major);
+    }
+
+    json!({
+        "name": format!("Detection Wizard coverage ({})", scope),
+        "versions": versions,
+        "domain": domain.slug(),
+        "description": format!(
+            "Rule counts per technique from {}, generated by Detection Wizard",
+            scope
+        ),
+        "sorting": 3,
+        "layout": {
+            "layout": "side",
+            "aggregateFunction": "sum",
+            "showID": true,
+            "showName": true,
+            "showAggregateScores": true,
+            "countUnscored": false,
+            "expandedSubtechniques": "annotated",
+        },
+        "hideDisabled": false,
+        "techniques": techniques,
+        "gradient": {
+            "colors": ["#fff7b3ff", "#ff6666ff"],
+            "minValue": 0,
+            "maxValue": max_score.max(1),
+        },
+        "legendItems": [],
+        "metadata": [],
+        "links": [],
+        "showTacticRowBackground": false,
+        "tacticRowBackground": "#dddddd",
+        "selectTechniquesAcrossTactics": true,
+        "selectSubtechniquesWithParent": false,
+        "selectVisibleTechniques": false,
+    })
+}
+
+/// File name for a layer, e.g. `navigator_layer_sigma.json`.
+pub fn layer_file_name(tool: Option<&str>) -> String {
+    format!(
+        "navigator_layer_{}.json",
+        tool.unwrap_or("all").to_ascii_lowercase()
+    )
+}
+
+pub fn write_layer(layer: &Value, path: &Path) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(layer)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn ids(list: &[&str]) -> BTreeSet<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn builds_layer_with_scores_and_comments() {
+        let mut map = CoverageMap::default();
+        map.add(
+            "Sigma",
+            "SigmaHQ_sigma",
+            "Encoded PowerShell",
+            &ids(&["T1059.001"]),
+        );
+        map.add("Sigma", "SigmaHQ_sigma", "Shell spawn", &ids(&["T1059"]));
+        map.add(
+            "Splunk",
+            "splunk_security_content",
+            "PS Exec",
+            &ids(&["T1059"]),
+        );
+
+        let layer = navigator_layer(&map, None, AttackDomain::Enterprise, Some("16.1"));
+        assert_eq!(layer["versions"]["layer"], "4.5");
+        assert_eq!(layer["versions"]["attack"], "16");
+        assert_eq!(layer["domain"], "enterprise-attack");
+        let techniques = layer["techniques"].as_array().unwrap();
+        assert_eq!(techniques.len(), 2);
+        assert_eq!(techniques[0]["techniqueID"], "T1059");
+        assert_eq!(techniques[0]["score"], 2);
+        assert_eq!(techniques[0]["showSubtechniques"], true);
+        let comment = techniques[0]["comment"].as_str().unwrap();
+        assert!(comment.contains("[Sigma] Shell spawn (SigmaHQ_sigma)"));
+        assert!(comment.contains("[Splunk] PS Exec"));
+        assert_eq!(layer["gradient"]["maxValue"], 2);
+
+        let layer = navigator_layer(&map, Some("Splunk"), AttackDomain::Enterprise, None);
+        let techniques = layer["techniques"].as_array().unwrap();
+        assert_eq!(techniques.len(), 1);
+        assert_eq!(techniques[0]["score"], 1);
+        assert_eq!(techniques[0]["showSubtechniques"], false);
+        assert!(layer["versions"].get("attack").is_none());
+        assert_eq!(
+            layer_file_name(Some("Splunk")),
+            "navigator_layer_splunk.json"
+        );
+    }
+}
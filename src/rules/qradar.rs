@@ -1,40 +1,28 @@
-use crate::download::{process_tool, ToolSpec};
-use eframe::egui::Context;
-use std::path::Path;
-use std::sync::{Arc, Mutex};
-use std::sync::atomic::AtomicBool;
+use super::tool::{DetectionTool, ToolRegistration};
+use crate::download::ToolSpec;
 
-pub fn qradar_total_sources() -> usize {
-    QRADAR_REPOS.len() + QRADAR_PAGES.len()
-}
+#[derive(Clone, Default)]
+pub struct QRadarRules;
 
-pub fn qradar_spec() -> ToolSpec {
-    ToolSpec {
-        name: "QRadar",
-        dest_subfolder: "qradar",
-        repo_urls: &QRADAR_REPOS,
-        page_urls: &QRADAR_PAGES,
-        allowed_exts: &["xml", "json", "aql", "txt"],
+impl DetectionTool for QRadarRules {
+    fn spec(&self) -> ToolSpec {
+        ToolSpec {
+            name: "QRadar",
+            dest_subfolder: "qradar",
+            repo_urls: &QRADAR_REPOS,
+            page_urls: &QRADAR_PAGES,
+            allowed_exts: &["xml", "json", "aql", "txt"],
+            file_filter: None,
+            keep_structure: false,
+            repo_paths: &[],
+        }
     }
 }
 
-pub fn process_qradar(
-    output_root: &str,
-    progress_triplet: Arc<Mutex<Option<(usize, usize, String)>>>,
-    ctx: Context,
-    cancel_flag: Arc<AtomicBool>,
-) {
-    let _ = process_tool(
-        &qradar_spec(),
-        Path::new(output_root),
-        progress_triplet,
-        ctx,
-        cancel_flag,
-    );
+inventory::submit! {
+    ToolRegistration::new(50, || Box::new(QRadarRules))
 }
 
-static QRADAR_REPOS: [&str; 1] = [
-    "https://github.com/Xboarder56/QRCE-Rules.git",
-];
+static QRADAR_REPOS: [&str; 1] = ["https://github.com/Xboarder56/QRCE-Rules.git"];
 
 static QRADAR_PAGES: [&str; 0] = [];
@@ -1,20 +1,21 @@
+use super::tool::{DetectionTool, registered_tools};
+use crate::download::ProgressTriplet;
 use eframe::{App, Frame, egui};
 use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
 
 pub struct ToolSelectorApp {
-    pub tool_names: Vec<&'static str>,
-    pub selected: Vec<bool>,
+    /// Every registered tool with its checkbox state.
+    pub tools: Vec<(Box<dyn DetectionTool>, bool)>,
     pub custom_path: Option<String>,
-    pub progress: Arc<Mutex<Option<(usize, usize, String)>>>, // <-- triplet now
-    pub cancel_flag: Arc<AtomicBool>,                         // <-- AtomicBool now
+    pub progress: ProgressTriplet,
+    pub cancel_flag: Arc<AtomicBool>,
 }
 
 impl Default for ToolSelectorApp {
     fn default() -> Self {
         Self {
-            selected: vec![false; 6],
-            tool_names: vec!["Yara", "Suricata", "Sigma", "Splunk", "QRadar", "All"],
+            tools: registered_tools().into_iter().map(|t| (t, false)).collect(),
             progress: Arc::new(Mutex::new(None)),
             custom_path: None,
             cancel_flag: Arc::new(AtomicBool::new(false)),
@@ -22,6 +23,21 @@ impl Default for ToolSelectorApp {
     }
 }
 
+impl ToolSelectorApp {
+    pub fn is_selected(&self, name: &str) -> bool {
+        self.tools.iter().any(|(t, on)| *on && t.name() == name)
+    }
+
+    /// Snapshots of the checked tools, options included, ready to hand to a run.
+    pub fn selected_tools(&self) -> Vec<Box<dyn DetectionTool>> {
+        self.tools
+            .iter()
+            .filter(|(_, on)| *on)
+            .map(|(t, _)| t.clone())
+            .collect()
+    }
+}
+
 impl App for ToolSelectorApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
         super::ui_rule::render_ui(self, ctx, || {});
@@ -1,36 +1,260 @@
-use crate::download::{process_tool, ToolSpec};
-use eframe::egui::Context;
-use std::path::Path;
-use std::sync::{Arc, Mutex};
-use std::sync::atomic::AtomicBool;
+use super::tool::{DetectionTool, ToolRegistration, remove_duplicate_files};
+use super::ui_rule::render_sigma_filter;
+use crate::download::ToolSpec;
+use crate::settings::Settings;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-pub fn sigma_total_sources() -> usize {
-    SIGMA_REPOS.len() + SIGMA_PAGES.len()
+pub const SIGMA_STATUSES: [&str; 5] = [
+    "stable",
+    "test",
+    "experimental",
+    "deprecated",
+    "unsupported",
+];
+pub const SIGMA_LEVELS: [&str; 5] = ["informational", "low", "medium", "high", "critical"];
+
+/// Which Sigma rules to keep. Status and level are allow-lists (a rule without the field
+/// passes); the remaining lists are empty for "any" and otherwise need one match.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SigmaFilter {
+    pub statuses: Vec<String>,
+    pub levels: Vec<String>,
+    pub products: Vec<String>,
+    pub categories: Vec<String>,
+    pub services: Vec<String>,
+    /// Tag prefixes, e.g. `attack.t1059` also keeps `attack.t1059.001`.
+    pub tags: Vec<String>,
+    /// Case-insensitive substrings of the `author` field.
+    pub authors: Vec<String>,
+}
+
+impl Default for SigmaFilter {
+    fn default() -> Self {
+        Self {
+            statuses: SIGMA_STATUSES.iter().map(|s| s.to_string()).collect(),
+            levels: SIGMA_LEVELS.iter().map(|s| s.to_string()).collect(),
+            products: Vec::new(),
+            categories: Vec::new(),
+            services: Vec::new(),
+            tags: Vec::new(),
+            authors: Vec::new(),
+        }
+    }
+}
+
+impl SigmaFilter {
+    /// True when the filter would keep every rule, so files need not be parsed at all.
+    pub fn is_permissive(&self) -> bool {
+        let all_of = |list: &[String], known: &[&str]| {
+            known
+                .iter()
+                .all(|k| list.iter().any(|v| v.eq_ignore_ascii_case(k)))
+        };
+        all_of(&self.statuses, &SIGMA_STATUSES)
+            && all_of(&self.levels, &SIGMA_LEVELS)
+            && self.products.is_empty()
+            && self.categories.is_empty()
+            && self.services.is_empty()
+            && self.tags.is_empty()
+            && self.authors.is_empty()
+    }
+
+    /// Check a parsed Sigma document. YAML that is not a rule (no `detection`) is kept
+    /// untouched, since the filter only has an opinion about rules.
+    pub fn matches(&self, doc: &Value) -> bool {
+        if doc.get("detection").is_none() {
+            return true;
+        }
+
+        let field = |key: &str| doc.get(key).and_then(Value::as_str);
+        let logsource = |key: &str| {
+            doc.get("logsource")
+                .and_then(|l| l.get(key))
+                .and_then(Value::as_str)
+        };
+
+        allowed(&self.statuses, field("status"))
+            && allowed(&self.levels, field("level"))
+            && any_equal(&self.products, logsource("product"))
+            && any_equal(&self.categories, logsource("category"))
+            && any_equal(&self.services, logsource("service"))
+            && self.tags_match(doc)
+            && self.author_matches(field("author"))
+    }
+
+    /// Read and check a rule file. Unreadable or unparsable files are dropped when the
+    /// filter is active, because nothing about them can be verified. Rule collections
+    /// are kept when at least one of their rules matches.
+    pub fn matches_file(&self, path: &Path) -> bool {
+        if self.is_permissive() {
+            return true;
+        }
+        let Ok(text) = fs::read_to_string(path) else {
+            return false;
+        };
+        let docs: Result<Vec<Value>, _> = serde_yaml::Deserializer::from_str(&text)
+            .map(Value::deserialize)
+            .collect();
+        match docs {
+            Ok(docs) if !docs.is_empty() => collection_rules(docs).iter().any(|d| self.matches(d)),
+            _ => false,
+        }
+    }
+
+    fn tags_match(&self, doc: &Value) -> bool {
+        if self.tags.is_empty() {
+            return true;
+        }
+        let Some(tags) = doc.get("tags").and_then(Value::as_sequence) else {
+            return false;
+        };
+        tags.iter().filter_map(Value::as_str).any(|tag| {
+            let tag = tag.to_ascii_lowercase();
+            self.tags.iter().any(|want| {
+                let want = want.to_ascii_lowercase();
+                tag == want || tag.starts_with(&format!("{}.", want))
+            })
+        })
+    }
+
+    fn author_matches(&self, author: Option<&str>) -> bool {
+        if self.authors.is_empty() {
+            return true;
+        }
+        let Some(author) = author.map(str::to_ascii_lowercase) else {
+            return false;
+        };
+        self.authors
+            .iter()
+            .any(|a| author.contains(&a.to_ascii_lowercase()))
+    }
+}
+
+/// The rules of a multi-document file. An `action: global` document is merged into
+/// each document after it and `action: reset` clears it again; other files are
+/// returned as they are.
+fn collection_rules(docs: Vec<Value>) -> Vec<Value> {
+    let action = |doc: &Value| {
+        doc.get("action")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+    if docs.len() < 2 || action(&docs[0]).as_deref() != Some("global") {
+        return docs;
+    }
+    let mut global = Value::Null;
+    let mut rules = Vec::new();
+    for doc in docs {
+        match action(&doc).as_deref() {
+            Some("global") => global = doc,
+            Some("reset") => global = Value::Null,
+            _ => {
+                let mut rule = global.clone();
+                merge_yaml(&mut rule, doc);
+                rules.push(rule);
+            }
+        }
+    }
+    rules
+}
+
+/// Overlay `doc` on `base`, merging nested mappings key by key.
+fn merge_yaml(base: &mut Value, doc: Value) {
+    match (base, doc) {
+        (Value::Mapping(base), Value::Mapping(doc)) => {
+            for (key, value) in doc {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_yaml(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, doc) => *base = doc,
+    }
+}
+
+fn allowed(list: &[String], value: Option<&str>) -> bool {
+    match value {
+        Some(v) => list.iter().any(|a| a.eq_ignore_ascii_case(v.trim())),
+        None => true,
+    }
+}
+
+fn any_equal(list: &[String], value: Option<&str>) -> bool {
+    if list.is_empty() {
+        return true;
+    }
+    value.is_some_and(|v| list.iter().any(|a| a.eq_ignore_ascii_case(v.trim())))
 }
 
-pub fn sigma_spec() -> ToolSpec {
-    ToolSpec {
-        name: "Sigma",
-        dest_subfolder: "sigma",
-        repo_urls: &SIGMA_REPOS,
-        page_urls: &SIGMA_PAGES,
-        allowed_exts: &["yml", "yaml"],
+/// Sigma rules narrowed by a [`SigmaFilter`], which starts from the saved settings.
+#[derive(Clone)]
+pub struct SigmaRules {
+    pub filter: SigmaFilter,
+}
+
+impl Default for SigmaRules {
+    fn default() -> Self {
+        Self {
+            filter: Settings::load().sigma_filter,
+        }
+    }
+}
+
+impl DetectionTool for SigmaRules {
+    fn spec(&self) -> ToolSpec {
+        let file_filter = if self.filter.is_permissive() {
+            None
+        } else {
+            let filter = self.filter.clone();
+            Some(Arc::new(move |path: &Path| filter.matches_file(path)) as _)
+        };
+
+        ToolSpec {
+            name: "Sigma",
+            dest_subfolder: "sigma",
+            repo_urls: SIGMA_REPOS,
+            page_urls: SIGMA_PAGES,
+            allowed_exts: &["yml", "yaml"],
+            file_filter,
+            keep_structure: false,
+            repo_paths: &[],
+        }
+    }
+
+    /// Several repos vendor SigmaHQ rules unchanged.
+    fn dedup(&self, written: &[PathBuf]) -> io::Result<usize> {
+        remove_duplicate_files(written)
+    }
+
+    fn has_options(&self) -> bool {
+        true
+    }
+
+    fn ui_options(&mut self, ui: &mut egui::Ui) {
+        render_sigma_filter(ui, &mut self.filter);
+        ui.add_space(5.0);
+        if ui.button("Save as default").clicked() {
+            let mut settings = Settings::load();
+            settings.sigma_filter = self.filter.clone();
+            if let Err(e) = settings.save() {
+                eprintln!("❌ Failed to save settings: {}", e);
+            }
+        }
     }
 }
 
-pub fn process_sigma(
-    output_root: &str,
-    progress_triplet: Arc<Mutex<Option<(usize, usize, String)>>>,
-    ctx: Context,
-    cancel_flag: Arc<AtomicBool>,
-) {
-    let _ = process_tool(
-        &sigma_spec(),
-        Path::new(output_root),
-        progress_triplet,
-        ctx,
-        cancel_flag,
-    );
+inventory::submit! {
+    ToolRegistration::new(30, || Box::new(SigmaRules::default()))
 }
 
 static SIGMA_REPOS: &[&str] = &[
@@ -48,3 +272,111 @@ static SIGMA_REPOS: &[&str] = &[
 static SIGMA_PAGES: &[&str] = &[
     "https://raw.githubusercontent.com/delivr-to/detections/refs/heads/main/sigma-rules/file_event_win_pdf_html_smuggle.yml",
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(yaml: &str) -> Value {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    const RULE: &str = r#"
+title: Suspicious Encoded PowerShell
+status: experimental
+level: high
+author: Florian Roth (Nextron Systems)
+logsource:
+    product: windows
+    category: process_creation
+tags:
+    - attack.execution
+    - attack.t1059.001
+detection:
+    selection:
+        CommandLine|contains: ' -enc '
+    condition: selection
+"#;
+
+    #[test]
+    fn default_filter_keeps_everything() {
+        let filter = SigmaFilter::default();
+        assert!(filter.is_permissive());
+        assert!(filter.matches(&rule(RULE)));
+    }
+
+    #[test]
+    fn status_and_level_allow_lists() {
+        let mut filter = SigmaFilter {
+            statuses: vec!["stable".into(), "test".into()],
+            ..Default::default()
+        };
+        assert!(!filter.matches(&rule(RULE)));
+
+        filter.statuses.push("experimental".into());
+        filter.levels = vec!["critical".into()];
+        assert!(!filter.matches(&rule(RULE)));
+    }
+
+    #[test]
+    fn logsource_tags_and_author() {
+        let filter = SigmaFilter {
+            products: vec!["Windows".into()],
+            categories: vec!["process_creation".into()],
+            tags: vec!["attack.t1059".into()],
+            authors: vec!["roth".into()],
+            ..Default::default()
+        };
+        assert!(filter.matches(&rule(RULE)));
+
+        let other = SigmaFilter {
+            services: vec!["sysmon".into()],
+            ..Default::default()
+        };
+        assert!(!other.matches(&rule(RULE)));
+
+        let wrong_tag = SigmaFilter {
+            tags: vec!["attack.t105".into()],
+            ..Default::default()
+        };
+        assert!(!wrong_tag.matches(&rule(RULE)));
+    }
+
+    #[test]
+    fn non_rule_yaml_passes() {
+        let filter = SigmaFilter {
+            products: vec!["linux".into()],
+            ..Default::default()
+        };
+        assert!(filter.matches(&rule("name: some backend config\nfields: []\n")));
+    }
+
+    #[test]
+    fn collections_merge_the_global_document() {
+        let collection = "action: global\ntitle: Shared\nstatus: test\nlevel: high\n\
+                          detection:\n    condition: selection\n---\n\
+                          logsource:\n    product: windows\n\
+                          detection:\n    selection:\n        Image: x.exe\n---\n\
+                          logsource:\n    product: linux\n\
+                          detection:\n    selection:\n        exe: x\n";
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("collection.yml");
+        fs::write(&path, collection).unwrap();
+
+        let linux = SigmaFilter {
+            products: vec!["linux".into()],
+            ..Default::default()
+        };
+        assert!(linux.matches_file(&path));
+        let macos = SigmaFilter {
+            products: vec!["macos".into()],
+            ..Default::default()
+        };
+        assert!(!macos.matches_file(&path));
+        let stable = SigmaFilter {
+            statuses: vec!["stable".into()],
+            ..Default::default()
+        };
+        assert!(!stable.matches_file(&path));
+    }
+}
@@ -1,36 +1,95 @@
-use crate::download::{process_tool, ToolSpec};
-use eframe::egui::Context;
+use super::splunk_app::{SplunkAppSettings, find_lookups, package_app};
+use super::splunk_content::{export_conf, is_splunk_content};
+use super::tool::{DetectionTool, ToolRegistration};
+use crate::download::ToolSpec;
+use crate::settings::Settings;
+use eframe::egui;
+use std::io;
 use std::path::Path;
-use std::sync::{Arc, Mutex};
-use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 
-pub fn splunk_total_sources() -> usize {
-    SPLUNK_REPOS.len() + SPLUNK_PAGES.len()
+/// Splunk detections, exported as `.conf` files and packaged as an installable app.
+#[derive(Clone)]
+pub struct SplunkRules {
+    pub app: SplunkAppSettings,
 }
 
-pub fn splunk_spec() -> ToolSpec {
-    ToolSpec {
-        name: "Splunk",
-        dest_subfolder: "splunk",
-        repo_urls: &SPLUNK_REPOS,
-        page_urls: &SPLUNK_PAGES,
-        allowed_exts: &["conf", "xml", "txt", "md"],
+impl Default for SplunkRules {
+    fn default() -> Self {
+        Self {
+            app: Settings::load().splunk_app,
+        }
     }
 }
 
-pub fn process_splunk(
-    output_root: &str,
-    progress_triplet: Arc<Mutex<Option<(usize, usize, String)>>>,
-    ctx: Context,
-    cancel_flag: Arc<AtomicBool>,
-) {
-    let _ = process_tool(
-        &splunk_spec(),
-        Path::new(output_root),
-        progress_triplet,
-        ctx,
-        cancel_flag,
-    );
+impl DetectionTool for SplunkRules {
+    fn spec(&self) -> ToolSpec {
+        ToolSpec {
+            name: "Splunk",
+            dest_subfolder: "splunk",
+            repo_urls: SPLUNK_REPOS,
+            page_urls: &SPLUNK_PAGES,
+            allowed_exts: &["conf", "xml", "txt", "md", "yml", "yaml"],
+            file_filter: Some(Arc::new(is_splunk_content)),
+            keep_structure: false,
+            repo_paths: &[],
+        }
+    }
+
+    /// Turn the security_content YAMLs into `default/savedsearches.conf` and
+    /// `default/macros.conf` inside the Splunk folder, then wrap them in a `.spl`.
+    fn export(&self, dir: &Path) -> io::Result<()> {
+        let summary = export_conf(dir, dir)?;
+        println!(
+            "[Splunk] exported {} saved searches and {} macros ({} retired skipped)",
+            summary.detections, summary.macros, summary.retired
+        );
+
+        let lookups = find_lookups(Path::new(&self.app.lookups_dir));
+        let spl = package_app(&self.app, dir, &lookups, dir)?;
+        println!(
+            "[Splunk] packaged {} with {} lookups",
+            spl.display(),
+            lookups.len()
+        );
+        Ok(())
+    }
+
+    fn has_options(&self) -> bool {
+        true
+    }
+
+    fn ui_options(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("splunk_app_options").show(ui, |ui| {
+            for (label, value) in [
+                ("App id", &mut self.app.app_id),
+                ("Label", &mut self.app.label),
+                ("Version", &mut self.app.version),
+                ("Author", &mut self.app.author),
+                ("Description", &mut self.app.description),
+                ("Lookup CSV folder", &mut self.app.lookups_dir),
+            ] {
+                ui.label(label);
+                ui.text_edit_singleline(value);
+                ui.end_row();
+            }
+        });
+        if let Err(e) = self.app.validate() {
+            ui.colored_label(egui::Color32::RED, e.to_string());
+        }
+        ui.add_space(5.0);
+        if ui.button("Save as default").clicked() {
+            let mut settings = Settings::load();
+            settings.splunk_app = self.app.clone();
+            if let Err(e) = settings.save() {
+                eprintln!("❌ Failed to save settings: {}", e);
+            }
+        }
+    }
+}
+
+inventory::submit! {
+    ToolRegistration::new(40, || Box::new(SplunkRules::default()))
 }
 
 static SPLUNK_REPOS: &[&str] = &[
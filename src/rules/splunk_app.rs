@@ -0,0 +1,352 @@
+use crate::ioc::csv_export::{CSV_COLUMNS, parse_csv};
+use chrono::{NaiveDate, Utc};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+/// How the generated Splunk app identifies itself, saved with the other settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SplunkAppSettings {
+    /// Folder name on the search head; letters, digits, `_` and `-` only.
+    pub app_id: String,
+    pub label: String,
+    /// `major.minor.patch`; bump it so Splunk accepts the package as an upgrade.
+    pub version: String,
+    pub author: String,
+    pub description: String,
+    /// The newest IOC CSV export of each type in this folder is packaged as a lookup.
+    pub lookups_dir: String,
+}
+
+impl Default for SplunkAppSettings {
+    fn default() -> Self {
+        Self {
+            app_id: "detection_wizard".into(),
+            label: "Detection Wizard".into(),
+            version: "1.0.0".into(),
+            author: "Detection Wizard".into(),
+            description: "Detections and IOC lookups collected by Detection Wizard".into(),
+            lookups_dir: "./ioc_output".into(),
+        }
+    }
+}
+
+impl SplunkAppSettings {
+    pub fn validate(&self) -> io::Result<()> {
+        let id_ok = !self.app_id.is_empty()
+            && self
+                .app_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !id_ok {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid Splunk app id: {:?}", self.app_id),
+            ));
+        }
+        let parts: Vec<&str> = self.version.split('.').collect();
+        if parts.len() != 3 || parts.iter().any(|p| p.parse::<u32>().is_err()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("app version must look like 1.2.3, got {:?}", self.version),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn package_name(&self) -> String {
+        format!("{}-{}.spl", self.app_id, self.version)
+    }
+}
+
+pub fn app_conf(settings: &SplunkAppSettings, build: &str) -> String {
+    format!(
+        "# Generated by Detection Wizard\n\
+         [install]\n\
+         is_configured = 0\n\
+         build = {build}\n\
+         \n\
+         [package]\n\
+         id = {id}\n\
+         check_for_updates = 0\n\
+         \n\
+         [launcher]\n\
+         author = {author}\n\
+         version = {version}\n\
+         description = {description}\n\
+         \n\
+         [ui]\n\
+         is_visible = 0\n\
+         label = {label}\n",
+        build = build,
+        id = settings.app_id,
+        author = settings.author,
+        version = settings.version,
+        description = settings.description,
+        label = settings.label,
+    )
+}
+
+/// Share everything app-wide so the searches and lookups work from any app.
+const DEFAULT_META: &str = "[]\naccess = read : [ * ], write : [ admin ]\nexport = system\n";
+
+/// Prefix of packaged IOC lookups, followed by the IOC type.
+pub const LOOKUP_PREFIX: &str = "dw_ioc_";
+
+/// A CSV packaged as `lookups/<name>.csv`. The name stays the same across exports so
+/// searches using it keep working after an upgrade; the date lives in the app build.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lookup {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Lookup definitions so each CSV can be used as `| inputlookup <name>`.
+pub fn transforms_conf(lookups: &[Lookup]) -> String {
+    let mut out = String::from("# Generated by Detection Wizard\n");
+    for lookup in lookups {
+        out.push_str(&format!(
+            "\n[{name}]\nfilename = {name}.csv\n",
+            name = lookup.name
+        ));
+    }
+    out
+}
+
+/// `<type>-<YYYY-MM-DD>.csv` -> (`type`, date).
+fn export_name(file_name: &str) -> Option<(&str, NaiveDate)> {
+    let stem = file_name.strip_suffix(".csv")?;
+    let split = stem.len().checked_sub(11)?;
+    let (ioc_type, date) = stem.split_at_checked(split)?;
+    let date = NaiveDate::parse_from_str(date.strip_prefix('-')?, "%Y-%m-%d").ok()?;
+    (!ioc_type.is_empty()).then_some((ioc_type, date))
+}
+
+/// Whether the first line of `path` is the IOC CSV header.
+fn has_ioc_header(path: &Path) -> bool {
+    let Ok(file) = File::open(path) else {
+        return false;
+    };
+    let mut line = String::new();
+    if BufReader::new(file).read_line(&mut line).is_err() {
+        return false;
+    }
+    parse_csv(&line).first().is_some_and(|h| h == &CSV_COLUMNS)
+}
+
+/// The newest IOC CSV export of each type directly in `dir` as `dw_ioc_<type>`, sorted.
+/// Delta files, older snapshots and unrelated CSVs are left out; a missing folder
+/// means no lookups.
+pub fn find_lookups(dir: &Path) -> Vec<Lookup> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut newest: BTreeMap<String, (NaiveDate, PathBuf)> = BTreeMap::new();
+    for path in entries.filter_map(Result::ok).map(|e| e.path()) {
+        let Some((ioc_type, date)) = path
+            .file_name()
+            .and_then(|f| f.to_str())
+            .and_then(export_name)
+        else {
+            continue;
+        };
+        if newest.get(ioc_type).is_some_and(|(d, _)| *d >= date)
+            || !path.is_file()
+            || !has_ioc_header(&path)
+        {
+            continue;
+        }
+        newest.insert(ioc_type.to_string(), (date, path));
+    }
+    newest
+        .into_iter()
+        .map(|(ioc_type, (_, path))| Lookup {
+            name: format!("{}{}", LOOKUP_PREFIX, ioc_type),
+            path,
+        })
+        .collect()
+}
+
+fn append_bytes<W: io::Write>(
+    builder: &mut tar::Builder<W>,
+    path: &str,
+    bytes: &[u8],
+    mtime: u64,
+) -> io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(bytes.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(mtime);
+    header.set_cksum();
+    builder.append_data(&mut header, path, bytes)
+}
+
+/// Build `<out_dir>/<app_id>-<version>.spl`: a gzipped tar whose single top-level
+/// folder is the app id, as Splunk expects. `conf_dir/default/*.conf` is copied in
+/// alongside the generated `app.conf`, lookups and `metadata/default.meta`.
+pub fn package_app(
+    settings: &SplunkAppSettings,
+    conf_dir: &Path,
+    lookups: &[Lookup],
+    out_dir: &Path,
+) -> io::Result<PathBuf> {
+    settings.validate()?;
+    fs::create_dir_all(out_dir)?;
+    let out_path = out_dir.join(settings.package_name());
+
+    let now = Utc::now();
+    let mtime = now.timestamp().max(0) as u64;
+    let root = &settings.app_id;
+
+    let encoder = GzEncoder::new(File::create(&out_path)?, Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+
+    append_bytes(
+        &mut builder,
+        &format!("{}/default/app.conf", root),
+        app_conf(settings, &now.format("%Y%m%d%H%M").to_string()).as_bytes(),
+        mtime,
+    )?;
+
+    for conf in ["savedsearches.conf", "macros.conf"] {
+        let path = conf_dir.join("default").join(conf);
+        if path.is_file() {
+            append_bytes(
+                &mut builder,
+                &format!("{}/default/{}", root, conf),
+                &fs::read(&path)?,
+                mtime,
+            )?;
+        }
+    }
+
+    if !lookups.is_empty() {
+        append_bytes(
+            &mut builder,
+            &format!("{}/default/transforms.conf", root),
+            transforms_conf(lookups).as_bytes(),
+            mtime,
+        )?;
+        for lookup in lookups {
+            append_bytes(
+                &mut builder,
+                &format!("{}/lookups/{}.csv", root, lookup.name),
+                &fs::read(&lookup.path)?,
+                mtime,
+            )?;
+        }
+    }
+
+    append_bytes(
+        &mut builder,
+        &format!("{}/metadata/default.meta", root),
+        DEFAULT_META.as_bytes(),
+        mtime,
+    )?;
+
+    builder.into_inner()?.finish()?;
+    Ok(out_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+
+    #[test]
+    fn package_has_app_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        let conf_dir = dir.path().join("splunk");
+        fs::create_dir_all(conf_dir.join("default")).unwrap();
+        fs::write(
+            conf_dir.join("default/savedsearches.conf"),
+            "[x]\nsearch = *\n",
+        )
+        .unwrap();
+        let lookup = dir.path().join("bad_ips.csv");
+        fs::write(&lookup, "value\n1.2.3.4\n").unwrap();
+
+        let settings = SplunkAppSettings::default();
+        let lookup = Lookup {
+            name: "dw_ioc_ip".into(),
+            path: lookup,
+        };
+        let spl = package_app(&settings, &conf_dir, &[lookup], dir.path()).unwrap();
+        assert!(spl.ends_with("detection_wizard-1.0.0.spl"));
+
+        let mut archive = tar::Archive::new(GzDecoder::new(File::open(&spl).unwrap()));
+        let mut names: Vec<String> = archive
+            .entries()
+            .unwrap()
+            .map(|e| e.unwrap().path().unwrap().display().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "detection_wizard/default/app.conf",
+                "detection_wizard/default/savedsearches.conf",
+                "detection_wizard/default/transforms.conf",
+                "detection_wizard/lookups/dw_ioc_ip.csv",
+                "detection_wizard/metadata/default.meta",
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_newest_ioc_exports() {
+        let dir = tempfile::tempdir().unwrap();
+        let header = "value,type,source_feed,first_seen,last_seen,tags,confidence\r\n";
+        for name in [
+            "ip-2024-06-01.csv",
+            "ip-2024-06-02.csv",
+            "hash-2024-05-30.csv",
+            "ioc-delta-2024-06-02-101500.csv",
+        ] {
+            fs::write(dir.path().join(name), header).unwrap();
+        }
+        fs::write(
+            dir.path().join("domain-2024-06-02.csv"),
+            "value\nevil.example\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("notes.csv"), header).unwrap();
+
+        let lookups = find_lookups(dir.path());
+        let found: Vec<(&str, String)> = lookups
+            .iter()
+            .map(|l| {
+                let file = l.path.file_name().unwrap().to_string_lossy().into_owned();
+                (l.name.as_str(), file)
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("dw_ioc_hash", "hash-2024-05-30.csv".to_string()),
+                ("dw_ioc_ip", "ip-2024-06-02.csv".to_string()),
+            ]
+        );
+        assert!(transforms_conf(&lookups).contains("\n[dw_ioc_ip]\nfilename = dw_ioc_ip.csv\n"));
+    }
+
+    #[test]
+    fn rejects_bad_id_and_version() {
+        let mut settings = SplunkAppSettings {
+            app_id: "my app".into(),
+            ..Default::default()
+        };
+        assert!(settings.validate().is_err());
+        settings.app_id = "my_app".into();
+        settings.version = "1.0".into();
+        assert!(settings.validate().is_err());
+        settings.version = "1.0.3".into();
+        assert!(settings.validate().is_ok());
+        assert!(app_conf(&settings, "1").contains("version = 1.0.3\n"));
+    }
+}
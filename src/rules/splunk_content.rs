@@ -0,0 +1,404 @@
+use regex::Regex;
+use serde_yaml::Value;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Detection types that run on a schedule; `Hunting` and friends are exported
+/// but left unscheduled.
+const SCHEDULED_TYPES: [&str; 3] = ["TTP", "Anomaly", "Correlation"];
+
+/// One detection YAML from `splunk/security_content` (or anything shaped like it).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SplunkDetection {
+    pub name: String,
+    pub id: String,
+    pub status: String,
+    /// `type:` in the YAML, e.g. TTP, Anomaly, Hunting.
+    pub kind: String,
+    pub description: String,
+    pub search: String,
+    pub how_to_implement: String,
+    pub known_false_positives: String,
+    pub data_source: Vec<String>,
+    pub mitre_attack_id: Vec<String>,
+    pub analytic_story: Vec<String>,
+}
+
+impl SplunkDetection {
+    pub fn is_scheduled(&self) -> bool {
+        SCHEDULED_TYPES
+            .iter()
+            .any(|t| t.eq_ignore_ascii_case(&self.kind))
+    }
+
+    /// Deprecated and removed detections stay in the repo but should not ship.
+    pub fn is_retired(&self) -> bool {
+        matches!(
+            self.status.to_ascii_lowercase().as_str(),
+            "deprecated" | "removed"
+        )
+    }
+}
+
+/// One macro YAML from `security_content/macros`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SplunkMacro {
+    pub name: String,
+    pub definition: String,
+    pub description: String,
+    pub arguments: Vec<String>,
+}
+
+impl SplunkMacro {
+    /// Stanza name; macros with arguments are addressed as `name(N)`.
+    pub fn stanza(&self) -> String {
+        if self.arguments.is_empty() || self.name.ends_with(')') {
+            self.name.clone()
+        } else {
+            format!("{}({})", self.name, self.arguments.len())
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SplunkContent {
+    Detection(SplunkDetection),
+    Macro(SplunkMacro),
+}
+
+fn text(doc: &Value, key: &str) -> String {
+    match doc.get(key) {
+        Some(Value::String(s)) => s.trim().to_string(),
+        Some(Value::Number(n)) => n.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// A string or a list of strings, as security_content is not consistent about it.
+fn list(doc: Option<&Value>) -> Vec<String> {
+    match doc {
+        Some(Value::String(s)) => vec![s.trim().to_string()],
+        Some(Value::Sequence(items)) => items
+            .iter()
+            .filter_map(Value::as_str)
+            .map(|s| s.trim().to_string())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Classify a YAML document: detections have `name` + `search`, macros have
+/// `name` + `definition`. Anything else (stories, baselines, configs) is `None`.
+pub fn parse_content(text_in: &str) -> Option<SplunkContent> {
+    let doc: Value = serde_yaml::from_str(text_in).ok()?;
+    let name = text(&doc, "name");
+    if name.is_empty() {
+        return None;
+    }
+
+    let search = text(&doc, "search");
+    if !search.is_empty() {
+        let tags = doc.get("tags");
+        return Some(SplunkContent::Detection(SplunkDetection {
+            name,
+            id: text(&doc, "id"),
+            status: text(&doc, "status"),
+            kind: text(&doc, "type"),
+            description: text(&doc, "description"),
+            search,
+            how_to_implement: text(&doc, "how_to_implement"),
+            known_false_positives: text(&doc, "known_false_positives"),
+            data_source: list(doc.get("data_source")),
+            mitre_attack_id: list(tags.and_then(|t| t.get("mitre_attack_id"))),
+            analytic_story: list(tags.and_then(|t| t.get("analytic_story"))),
+        }));
+    }
+
+    let definition = text(&doc, "definition");
+    if !definition.is_empty() {
+        return Some(SplunkContent::Macro(SplunkMacro {
+            name,
+            definition,
+            description: text(&doc, "description"),
+            arguments: list(doc.get("arguments")),
+        }));
+    }
+
+    None
+}
+
+/// Download filter for the Splunk tool: no READMEs/licences, and YAML only when
+/// it is a detection or macro.
+pub fn is_splunk_content(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    const NOISE: [&str; 5] = [
+        "readme",
+        "license",
+        "changelog",
+        "contributing",
+        "code_of_conduct",
+    ];
+    if NOISE.iter().any(|n| name.starts_with(n)) {
+        return false;
+    }
+
+    if name.ends_with(".yml") || name.ends_with(".yaml") {
+        return fs::read_to_string(path)
+            .ok()
+            .and_then(|t| parse_content(&t))
+            .is_some();
+    }
+    true
+}
+
+/// Format a `.conf` value; embedded newlines become backslash continuations.
+fn conf_value(value: &str) -> String {
+    value
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join(" \\\n")
+}
+
+fn push_kv(out: &mut String, key: &str, value: &str) {
+    if !value.is_empty() {
+        out.push_str(&format!("{} = {}\n", key, conf_value(value)));
+    }
+}
+
+fn filter_macro_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"`([A-Za-z0-9_]+_filter)`").unwrap())
+}
+
+/// `savedsearches.conf` for the detections, shipped disabled so nothing fires
+/// until someone turns it on.
+pub fn savedsearches_conf(detections: &[SplunkDetection]) -> String {
+    let mut out = String::from("# Generated by Detection Wizard\n");
+    for d in detections {
+        out.push_str(&format!("\n[{}]\n", d.name));
+        push_kv(&mut out, "description", &d.description);
+        push_kv(&mut out, "search", &d.search);
+        out.push_str("disabled = 1\n");
+        if d.is_scheduled() {
+            out.push_str("enableSched = 1\n");
+            out.push_str("cron_schedule = 0 * * * *\n");
+            out.push_str("dispatch.earliest_time = -70m@m\n");
+            out.push_str("dispatch.latest_time = -10m@m\n");
+            out.push_str("alert.track = 1\n");
+            out.push_str("counttype = number of events\n");
+            out.push_str("relation = greater than\n");
+            out.push_str("quantity = 0\n");
+        } else {
+            out.push_str("enableSched = 0\n");
+        }
+        push_kv(
+            &mut out,
+            "action.escu.how_to_implement",
+            &d.how_to_implement,
+        );
+        push_kv(
+            &mut out,
+            "action.escu.known_false_positives",
+            &d.known_false_positives,
+        );
+        push_kv(
+            &mut out,
+            "action.escu.data_source",
+            &d.data_source.join(", "),
+        );
+        push_kv(
+            &mut out,
+            "action.escu.analytic_story",
+            &d.analytic_story.join(", "),
+        );
+        if !d.mitre_attack_id.is_empty() {
+            let ids: Vec<String> = d
+                .mitre_attack_id
+                .iter()
+                .map(|t| format!("\"{}\"", t))
+                .collect();
+            out.push_str(&format!(
+                "action.correlationsearch.annotations = {{\"mitre_attack\": [{}]}}\n",
+                ids.join(", ")
+            ));
+        }
+    }
+    out
+}
+
+/// `macros.conf` for the given macros plus a pass-through `*_filter` macro for
+/// every filter a detection calls that nothing defines.
+pub fn macros_conf(macros: &[SplunkMacro], detections: &[SplunkDetection]) -> String {
+    let mut out = String::from("# Generated by Detection Wizard\n");
+    let defined: BTreeSet<String> = macros.iter().map(|m| m.stanza()).collect();
+    for m in macros {
+        out.push_str(&format!("\n[{}]\n", m.stanza()));
+        push_kv(&mut out, "args", &m.arguments.join(", "));
+        push_kv(&mut out, "definition", &m.definition);
+        push_kv(&mut out, "description", &m.description);
+    }
+
+    let missing: BTreeSet<&str> = detections
+        .iter()
+        .flat_map(|d| filter_macro_re().captures_iter(&d.search))
+        .filter_map(|c| c.get(1))
+        .map(|m| m.as_str())
+        .filter(|name| !defined.contains(*name))
+        .collect();
+    for name in missing {
+        out.push_str(&format!("\n[{}]\n", name));
+        out.push_str("definition = search *\n");
+        out.push_str(
+            "description = Update this macro to filter out false positives for the detection.\n",
+        );
+    }
+    out
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExportSummary {
+    pub detections: usize,
+    pub macros: usize,
+    pub retired: usize,
+}
+
+/// Parse every YAML directly under `dir` and write `default/savedsearches.conf`
+/// and `default/macros.conf` beneath `out_dir`.
+pub fn export_conf(dir: &Path, out_dir: &Path) -> io::Result<ExportSummary> {
+    let mut detections = Vec::new();
+    let mut macros = Vec::new();
+    let mut summary = ExportSummary::default();
+
+    let mut paths: Vec<_> = fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| {
+            p.is_file()
+                && p.extension().is_some_and(|e| {
+                    e.eq_ignore_ascii_case("yml") || e.eq_ignore_ascii_case("yaml")
+                })
+        })
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let Ok(text) = fs::read_to_string(&path) else {
+            continue;
+        };
+        match parse_content(&text) {
+            Some(SplunkContent::Detection(d)) if d.is_retired() => summary.retired += 1,
+            Some(SplunkContent::Detection(d))
+                if !detections
+                    .iter()
+                    .any(|e: &SplunkDetection| e.name == d.name) =>
+            {
+                detections.push(d)
+            }
+            Some(SplunkContent::Macro(m))
+                if !macros
+                    .iter()
+                    .any(|e: &SplunkMacro| e.stanza() == m.stanza()) =>
+            {
+                macros.push(m)
+            }
+            _ => {}
+        }
+    }
+
+    let default_dir = out_dir.join("default");
+    fs::create_dir_all(&default_dir)?;
+    fs::write(
+        default_dir.join("savedsearches.conf"),
+        savedsearches_conf(&detections),
+    )?;
+    fs::write(
+        default_dir.join("macros.conf"),
+        macros_conf(&macros, &detections),
+    )?;
+
+    summary.detections = detections.len();
+    summary.macros = macros.len();
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DETECTION: &str = r#"
+name: Windows PowerShell Encoded Command
+id: 1b2c3d4e-0000-4000-8000-000000000000
+version: 3
+status: production
+type: TTP
+description: Detects powershell.exe launched with an encoded command.
+data_source:
+- Sysmon EventID 1
+search: '| tstats `security_content_summariesonly` count from datamodel=Endpoint.Processes
+  where Processes.process_name=powershell.exe Processes.process="*-enc*" by Processes.dest
+  | `windows_powershell_encoded_command_filter`'
+how_to_implement: Ingest process creation events with full command lines.
+known_false_positives: Some admin scripts use encoded commands.
+tags:
+  analytic_story:
+  - Malicious PowerShell
+  mitre_attack_id:
+  - T1059.001
+  - T1027
+"#;
+
+    const MACRO: &str = r#"
+name: security_content_ctime
+arguments:
+- field
+definition: convert timeformat="%Y-%m-%dT%H:%M:%S" ctime($field$)
+description: convert epoch time to string
+"#;
+
+    #[test]
+    fn parses_detections_and_macros() {
+        let Some(SplunkContent::Detection(d)) = parse_content(DETECTION) else {
+            panic!("expected a detection");
+        };
+        assert_eq!(d.kind, "TTP");
+        assert_eq!(d.data_source, vec!["Sysmon EventID 1"]);
+        assert_eq!(d.mitre_attack_id, vec!["T1059.001", "T1027"]);
+        assert!(d.is_scheduled());
+
+        let Some(SplunkContent::Macro(m)) = parse_content(MACRO) else {
+            panic!("expected a macro");
+        };
+        assert_eq!(m.stanza(), "security_content_ctime(1)");
+
+        assert_eq!(parse_content("name: Story\nnarrative: text\n"), None);
+    }
+
+    #[test]
+    fn conf_output_has_annotations_and_filter_stubs() {
+        let Some(SplunkContent::Detection(d)) = parse_content(DETECTION) else {
+            panic!("expected a detection");
+        };
+        let saved = savedsearches_conf(std::slice::from_ref(&d));
+        assert!(saved.contains("[Windows PowerShell Encoded Command]\n"));
+        assert!(saved.contains("disabled = 1\n"));
+        assert!(saved.contains(r#"{"mitre_attack": ["T1059.001", "T1027"]}"#));
+
+        let macros = macros_conf(&[], &[d]);
+        assert!(
+            macros.contains("[windows_powershell_encoded_command_filter]\ndefinition = search *\n")
+        );
+        assert!(!macros.contains("[security_content_summariesonly]"));
+    }
+
+    #[test]
+    fn multiline_values_use_continuations() {
+        assert_eq!(conf_value("a  \nb"), "a \\\nb");
+    }
+}
@@ -1,36 +1,33 @@
-use crate::download::{process_tool, ToolSpec};
-use eframe::egui::Context;
-use std::path::Path;
-use std::sync::{Arc, Mutex};
-use std::sync::atomic::AtomicBool;
+use super::tool::{DetectionTool, ToolRegistration, remove_duplicate_files};
+use crate::download::ToolSpec;
+use std::io;
+use std::path::PathBuf;
 
-pub fn suricata_total_sources() -> usize {
-    SURICATA_REPOS.len() + SURICATA_PAGES.len()
-}
+#[derive(Clone, Default)]
+pub struct SuricataRules;
+
+impl DetectionTool for SuricataRules {
+    fn spec(&self) -> ToolSpec {
+        ToolSpec {
+            name: "Suricata",
+            dest_subfolder: "suricata",
+            repo_urls: &SURICATA_REPOS,
+            page_urls: &SURICATA_PAGES, // only direct .rules/.rule if you have them
+            allowed_exts: &["rules", "rule"],
+            file_filter: None,
+            keep_structure: false,
+            repo_paths: &[],
+        }
+    }
 
-pub fn suricata_spec() -> ToolSpec {
-    ToolSpec {
-        name: "Suricata",
-        dest_subfolder: "suricata",
-        repo_urls: &SURICATA_REPOS,
-        page_urls: &SURICATA_PAGES,    // only direct .rules/.rule if you have them
-        allowed_exts: &["rules", "rule"],
+    /// Some repos republish rule files that are also fetched as pages.
+    fn dedup(&self, written: &[PathBuf]) -> io::Result<usize> {
+        remove_duplicate_files(written)
     }
 }
 
-pub fn process_suricata(
-    output_root: &str,
-    progress_triplet: Arc<Mutex<Option<(usize, usize, String)>>>,
-    ctx: Context,
-    cancel_flag: Arc<AtomicBool>,
-) {
-    let _ = process_tool(
-        &suricata_spec(),
-        Path::new(output_root),
-        progress_triplet,
-        ctx,
-        cancel_flag,
-    );
+inventory::submit! {
+    ToolRegistration::new(20, || Box::new(SuricataRules))
 }
 
 static SURICATA_REPOS: [&str; 9] = [
@@ -1,36 +1,48 @@
-use crate::download::{process_tool, ToolSpec};
-use eframe::egui::Context;
+use super::sysmon_config::SysmonConfig;
+use super::tool::{DetectionTool, ToolRegistration};
+use crate::download::ToolSpec;
+use std::fs;
+use std::io;
 use std::path::Path;
-use std::sync::{Arc, Mutex};
-use std::sync::atomic::AtomicBool;
 
-pub fn sysmon_total_sources() -> usize {
-    SYSMON_REPOS.len() + SYSMON_PAGES.len()
-}
+#[derive(Clone, Default)]
+pub struct SysmonConfigs;
+
+impl DetectionTool for SysmonConfigs {
+    fn spec(&self) -> ToolSpec {
+        ToolSpec {
+            name: "Sysmon",
+            dest_subfolder: "sysmon",
+            repo_urls: &SYSMON_REPOS, // keep [] if you don’t have repos
+            page_urls: SYSMON_PAGES,  // direct XMLs if you have them
+            allowed_exts: &["xml"],
+            file_filter: None,
+            keep_structure: false,
+            repo_paths: &[],
+        }
+    }
 
-pub fn sysmon_spec() -> ToolSpec {
-    ToolSpec {
-        name: "Sysmon",
-        dest_subfolder: "sysmon",
-        repo_urls: &SYSMON_REPOS,  // keep [] if you don’t have repos
-        page_urls: &SYSMON_PAGES,  // direct XMLs if you have them
-        allowed_exts: &["xml"],
+    /// Drop XML that is not a Sysmon config the merger could read.
+    fn validate(&self, dir: &Path) -> io::Result<()> {
+        for entry in fs::read_dir(dir)?.filter_map(Result::ok) {
+            let path = entry.path();
+            if !path.is_file() {
+                continue;
+            }
+            if let Err(e) = SysmonConfig::load(&path) {
+                eprintln!("[Sysmon] removing {}: {:#}", path.display(), e);
+                // Keep checking the rest of the folder even if this one stays behind.
+                if let Err(e) = fs::remove_file(&path) {
+                    eprintln!("❌ [Sysmon] could not remove {}: {}", path.display(), e);
+                }
+            }
+        }
+        Ok(())
     }
 }
 
-pub fn process_sysmon(
-    output_root: &str,
-    progress_triplet: Arc<Mutex<Option<(usize, usize, String)>>>,
-    ctx: Context,
-    cancel_flag: Arc<AtomicBool>,
-) {
-    let _ = process_tool(
-        &sysmon_spec(),
-        Path::new(output_root),
-        progress_triplet,
-        ctx,
-        cancel_flag,
-    );
+inventory::submit! {
+    ToolRegistration::new(60, || Box::new(SysmonConfigs))
 }
 
 static SYSMON_REPOS: [&str; 0] = [];
@@ -0,0 +1,251 @@
+use super::sysmon_config::{OnMatch, SchemaVersion, SysmonConfig};
+use crate::xml::XmlElement;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::OnceLock;
+
+/// Sysmon event IDs produced by each filterable event element.
+pub fn event_ids(event: &str) -> &'static [u32] {
+    match event {
+        "ProcessCreate" => &[1],
+        "FileCreateTime" => &[2],
+        "NetworkConnect" => &[3],
+        "ProcessTerminate" => &[5],
+        "DriverLoad" => &[6],
+        "ImageLoad" => &[7],
+        "CreateRemoteThread" => &[8],
+        "RawAccessRead" => &[9],
+        "ProcessAccess" => &[10],
+        "FileCreate" => &[11],
+        "RegistryEvent" => &[12, 13, 14],
+        "FileCreateStreamHash" => &[15],
+        "PipeEvent" => &[17, 18],
+        "WmiEvent" => &[19, 20, 21],
+        "DnsQuery" => &[22],
+        "FileDelete" => &[23],
+        "ClipboardChange" => &[24],
+        "ProcessTampering" => &[25],
+        "FileDeleteDetected" => &[26],
+        "FileBlockExecutable" => &[27],
+        "FileBlockShredding" => &[28],
+        "FileExecutableDetected" => &[29],
+        _ => &[],
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventCoverage {
+    pub event: String,
+    pub includes: usize,
+    pub excludes: usize,
+    /// An empty include block switches the event off; any exclude block or a
+    /// non-empty include turns it on.
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigReport {
+    pub name: String,
+    pub schema_version: SchemaVersion,
+    pub events: Vec<EventCoverage>,
+    /// ATT&CK technique ID -> number of rules naming it.
+    pub techniques: BTreeMap<String, usize>,
+}
+
+impl ConfigReport {
+    pub fn event(&self, event: &str) -> Option<&EventCoverage> {
+        self.events.iter().find(|e| e.event == event)
+    }
+
+    pub fn enabled_event_ids(&self) -> Vec<u32> {
+        let mut ids: Vec<u32> = self
+            .events
+            .iter()
+            .filter(|e| e.enabled)
+            .flat_map(|e| event_ids(&e.event).iter().copied())
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+}
+
+pub(crate) fn technique_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\bT\d{4}(?:\.\d{3})?\b").unwrap())
+}
+
+/// ATT&CK technique IDs mentioned in a rule's `name` attributes, nested rules included.
+pub fn rule_techniques(rule: &XmlElement) -> BTreeSet<String> {
+    let mut found = BTreeSet::new();
+    collect_techniques(rule, &mut found);
+    found
+}
+
+fn collect_techniques(element: &XmlElement, found: &mut BTreeSet<String>) {
+    if let Some(name) = element.attr("name") {
+        for m in technique_re().find_iter(name) {
+            found.insert(m.as_str().to_string());
+        }
+    }
+    for child in &element.children {
+        collect_techniques(child, found);
+    }
+}
+
+pub fn analyze(config: &SysmonConfig) -> ConfigReport {
+    let mut events: Vec<EventCoverage> = Vec::new();
+    let mut techniques = BTreeMap::new();
+
+    for filter in &config.filters {
+        let idx = match events.iter().position(|e| e.event == filter.event) {
+            Some(i) => i,
+            None => {
+                events.push(EventCoverage {
+                    event: filter.event.clone(),
+                    includes: 0,
+                    excludes: 0,
+                    enabled: false,
+                });
+                events.len() - 1
+            }
+        };
+        let coverage = &mut events[idx];
+        match filter.onmatch {
+            OnMatch::Include => {
+                coverage.includes += filter.rules.len();
+                coverage.enabled |= !filter.rules.is_empty();
+            }
+            OnMatch::Exclude => {
+                coverage.excludes += filter.rules.len();
+                coverage.enabled = true;
+            }
+        }
+
+        for rule in &filter.rules {
+            for technique in rule_techniques(rule) {
+                *techniques.entry(technique).or_insert(0) += 1;
+            }
+        }
+    }
+
+    events.sort_by_key(|e| event_ids(&e.event).first().copied().unwrap_or(u32::MAX));
+
+    ConfigReport {
+        name: config.name.clone(),
+        schema_version: config.schema_version,
+        events,
+        techniques,
+    }
+}
+
+/// What changes when moving from `baseline` to `candidate`.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigDiff {
+    pub events_lost: Vec<String>,
+    pub events_gained: Vec<String>,
+    /// Event, onmatch and the canonical rule text, for rules only the baseline has.
+    pub rules_lost: Vec<(String, OnMatch, String)>,
+    pub rules_gained: Vec<(String, OnMatch, String)>,
+    pub techniques_lost: Vec<String>,
+    pub techniques_gained: Vec<String>,
+}
+
+pub fn diff(baseline: &SysmonConfig, candidate: &SysmonConfig) -> ConfigDiff {
+    let base_report = analyze(baseline);
+    let cand_report = analyze(candidate);
+
+    let enabled = |report: &ConfigReport| -> BTreeSet<String> {
+        report
+            .events
+            .iter()
+            .filter(|e| e.enabled)
+            .map(|e| e.event.clone())
+            .collect()
+    };
+    let base_events = enabled(&base_report);
+    let cand_events = enabled(&cand_report);
+
+    let rules = |config: &SysmonConfig| -> BTreeSet<(String, OnMatch, String)> {
+        config
+            .filters
+            .iter()
+            .flat_map(|f| {
+                f.rules
+                    .iter()
+                    .map(move |r| (f.event.clone(), f.onmatch, r.canonical()))
+            })
+            .collect()
+    };
+    let base_rules = rules(baseline);
+    let cand_rules = rules(candidate);
+
+    let base_techniques: BTreeSet<&String> = base_report.techniques.keys().collect();
+    let cand_techniques: BTreeSet<&String> = cand_report.techniques.keys().collect();
+
+    ConfigDiff {
+        events_lost: base_events.difference(&cand_events).cloned().collect(),
+        events_gained: cand_events.difference(&base_events).cloned().collect(),
+        rules_lost: base_rules.difference(&cand_rules).cloned().collect(),
+        rules_gained: cand_rules.difference(&base_rules).cloned().collect(),
+        techniques_lost: base_techniques
+            .difference(&cand_techniques)
+            .map(|t| t.to_string())
+            .collect(),
+        techniques_gained: cand_techniques
+            .difference(&base_techniques)
+            .map(|t| t.to_string())
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = r#"<Sysmon schemaversion="4.90"><EventFiltering>
+      <RuleGroup name="" groupRelation="or">
+        <ProcessCreate onmatch="exclude">
+          <Image condition="is">C:\Windows\explorer.exe</Image>
+        </ProcessCreate>
+      </RuleGroup>
+      <RuleGroup name="" groupRelation="or">
+        <ProcessAccess onmatch="include">
+          <TargetImage name="technique_id=T1003,technique_name=Credential Dumping" condition="is">C:\Windows\system32\lsass.exe</TargetImage>
+        </ProcessAccess>
+      </RuleGroup>
+      <RuleGroup name="" groupRelation="or">
+        <WmiEvent onmatch="include"/>
+      </RuleGroup>
+    </EventFiltering></Sysmon>"#;
+
+    const CANDIDATE: &str = r#"<Sysmon schemaversion="4.90"><EventFiltering>
+      <ProcessCreate onmatch="exclude"/>
+      <DnsQuery onmatch="exclude">
+        <Rule name="MitreRef=T1071.004" groupRelation="or">
+          <QueryName condition="end with">.microsoft.com</QueryName>
+        </Rule>
+      </DnsQuery>
+    </EventFiltering></Sysmon>"#;
+
+    #[test]
+    fn reports_enabled_events_and_techniques() {
+        let report = analyze(&SysmonConfig::parse("base", BASE).unwrap());
+        assert_eq!(report.enabled_event_ids(), vec![1, 10]);
+        assert_eq!(report.event("ProcessAccess").unwrap().includes, 1);
+        assert!(!report.event("WmiEvent").unwrap().enabled);
+        assert_eq!(report.techniques.get("T1003"), Some(&1));
+    }
+
+    #[test]
+    fn diff_lists_losses_and_gains() {
+        let base = SysmonConfig::parse("base", BASE).unwrap();
+        let candidate = SysmonConfig::parse("candidate", CANDIDATE).unwrap();
+        let d = diff(&base, &candidate);
+        assert_eq!(d.events_lost, vec!["ProcessAccess"]);
+        assert_eq!(d.events_gained, vec!["DnsQuery"]);
+        assert_eq!(d.rules_lost.len(), 2);
+        assert_eq!(d.rules_gained.len(), 1);
+        assert_eq!(d.techniques_lost, vec!["T1003"]);
+        assert_eq!(d.techniques_gained, vec!["T1071.004"]);
+    }
+}
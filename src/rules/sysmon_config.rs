@@ -0,0 +1,652 @@
+use crate::xml::{XmlElement, parse_xml};
+use anyhow::{Context as _, Result, anyhow, bail};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Oldest schema we accept; everything before 4.x predates `RuleGroup`.
+pub const MIN_SCHEMA_VERSION: SchemaVersion = SchemaVersion { major: 4, minor: 0 };
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SchemaVersion {
+    pub major: u32,
+    /// Two-digit minor so that "4.5" and "4.50" compare equal.
+    pub minor: u32,
+}
+
+impl SchemaVersion {
+    pub fn parse(s: &str) -> Result<Self> {
+        let (major, minor) = s.trim().split_once('.').unwrap_or((s.trim(), "0"));
+        let major = major
+            .parse()
+            .with_context(|| format!("bad schemaversion {:?}", s))?;
+        let mut minor_digits = minor.to_string();
+        if minor_digits.len() == 1 {
+            minor_digits.push('0');
+        }
+        let minor = minor_digits
+            .parse()
+            .with_context(|| format!("bad schemaversion {:?}", s))?;
+        Ok(Self { major, minor })
+    }
+}
+
+impl fmt::Display for SchemaVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:02}", self.major, self.minor)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum OnMatch {
+    Include,
+    Exclude,
+}
+
+impl OnMatch {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OnMatch::Include => "include",
+            OnMatch::Exclude => "exclude",
+        }
+    }
+}
+
+/// `RuleGroup@groupRelation`: whether any or all of a block's rules must match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum GroupRelation {
+    Or,
+    And,
+}
+
+impl GroupRelation {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GroupRelation::Or => "or",
+            GroupRelation::And => "and",
+        }
+    }
+}
+
+/// One `<ProcessCreate onmatch="include">`-style block and its filter rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventFilter {
+    pub event: String,
+    pub onmatch: OnMatch,
+    /// Name of the enclosing `RuleGroup`, empty when there was none.
+    pub group_name: String,
+    /// Relation of the enclosing `RuleGroup`; `Or` when there was none.
+    pub group_relation: GroupRelation,
+    /// Field conditions (`<Image condition="end with">`) and compound `<Rule>` elements.
+    pub rules: Vec<XmlElement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SysmonConfig {
+    /// Where the config came from, usually the file name.
+    pub name: String,
+    pub schema_version: SchemaVersion,
+    /// Top-level settings such as `HashAlgorithms`, `CheckRevocation` or `DnsLookup`.
+    pub settings: Vec<XmlElement>,
+    pub filters: Vec<EventFilter>,
+}
+
+impl SysmonConfig {
+    pub fn parse(name: &str, text: &str) -> Result<Self> {
+        let root = parse_xml(text).with_context(|| format!("{}: invalid XML", name))?;
+        if root.name != "Sysmon" {
+            bail!(
+                "{}: root element is <{}>, expected <Sysmon>",
+                name,
+                root.name
+            );
+        }
+        let version = root
+            .attr("schemaversion")
+            .ok_or_else(|| anyhow!("{}: missing schemaversion", name))?;
+        let schema_version = SchemaVersion::parse(version)?;
+
+        let mut settings = Vec::new();
+        let mut filters = Vec::new();
+        for child in root.children {
+            if child.name != "EventFiltering" {
+                settings.push(child);
+                continue;
+            }
+            for entry in child.children {
+                if entry.name == "RuleGroup" {
+                    let group_name = entry.attr("name").unwrap_or_default().to_string();
+                    let relation = match entry.attr("groupRelation") {
+                        Some(v) if v.eq_ignore_ascii_case("and") => GroupRelation::And,
+                        _ => GroupRelation::Or,
+                    };
+                    for event in entry.children {
+                        filters.push(event_filter(event, &group_name, relation));
+                    }
+                } else {
+                    filters.push(event_filter(entry, "", GroupRelation::Or));
+                }
+            }
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            schema_version,
+            settings,
+            filters,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text =
+            fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        let name = path
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string());
+        Self::parse(&name, &text)
+    }
+
+    /// Serialize with one `RuleGroup` per event block, the layout Sysmon itself expects.
+    pub fn to_xml(&self) -> String {
+        let mut out = format!(
+            "<!-- Generated by Detection Wizard from: {} -->\n<Sysmon schemaversion=\"{}\">\n",
+            self.name, self.schema_version
+        );
+        for setting in &self.settings {
+            setting.write(&mut out, Some(1));
+            out.push('\n');
+        }
+        out.push_str("  <EventFiltering>\n");
+        for filter in &self.filters {
+            let group = XmlElement {
+                name: "RuleGroup".into(),
+                attrs: vec![
+                    ("name".into(), filter.group_name.clone()),
+                    (
+                        "groupRelation".into(),
+                        filter.group_relation.as_str().into(),
+                    ),
+                ],
+                text: String::new(),
+                children: vec![XmlElement {
+                    name: filter.event.clone(),
+                    attrs: vec![("onmatch".into(), filter.onmatch.as_str().into())],
+                    text: String::new(),
+                    children: filter.rules.clone(),
+                }],
+            };
+            group.write(&mut out, Some(2));
+            out.push('\n');
+        }
+        out.push_str("  </EventFiltering>\n</Sysmon>\n");
+        out
+    }
+}
+
+fn event_filter(event: XmlElement, group_name: &str, relation: GroupRelation) -> EventFilter {
+    let onmatch = match event.attr("onmatch") {
+        Some(v) if v.eq_ignore_ascii_case("exclude") => OnMatch::Exclude,
+        _ => OnMatch::Include,
+    };
+    EventFilter {
+        event: event.name,
+        onmatch,
+        group_name: group_name.to_string(),
+        group_relation: relation,
+        rules: event.children,
+    }
+}
+
+/// How to settle a rule that one config includes and another excludes for the same event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergePrecedence {
+    /// The config listed first wins.
+    ConfigOrder,
+    /// Keep the include, favouring coverage.
+    Include,
+    /// Keep the exclude, favouring lower volume.
+    Exclude,
+}
+
+pub struct MergeResult {
+    pub config: SysmonConfig,
+    /// Rules dropped because an identical rule was already present.
+    pub duplicates: usize,
+    /// Rules, or empty on/off blocks, dropped to resolve include/exclude conflicts.
+    pub conflicts: usize,
+    pub warnings: Vec<String>,
+}
+
+struct Collected {
+    rule: XmlElement,
+    config_index: usize,
+}
+
+/// Merged block: event, `onmatch`, relation, and for `and` groups an id so that
+/// only identical ones collapse (0 for `or` groups).
+type BlockKey = (String, OnMatch, GroupRelation, usize);
+
+/// Merge configs in precedence order: settings come from the first config that has
+/// them, and filter rules of `or` groups are unioned per event type and `onmatch`.
+/// `and` groups are kept as they are, since adding rules to one narrows what it matches.
+/// An empty block that logs a whole event (or none of it) against another config's rules
+/// for that event is settled by `precedence`, like a rule on both sides.
+pub fn merge_configs(configs: &[SysmonConfig], precedence: MergePrecedence) -> Result<MergeResult> {
+    let Some(first) = configs.first() else {
+        bail!("no Sysmon configs to merge");
+    };
+
+    let mut warnings = Vec::new();
+    for config in configs {
+        if config.schema_version < MIN_SCHEMA_VERSION || config.schema_version.major != 4 {
+            bail!(
+                "{}: schemaversion {} is not supported (need 4.x >= {})",
+                config.name,
+                config.schema_version,
+                MIN_SCHEMA_VERSION
+            );
+        }
+        if config.schema_version != first.schema_version {
+            warnings.push(format!(
+                "{} uses schemaversion {} (first config uses {})",
+                config.name, config.schema_version, first.schema_version
+            ));
+        }
+    }
+    let schema_version = configs.iter().map(|c| c.schema_version).max().unwrap();
+
+    let mut settings: Vec<XmlElement> = Vec::new();
+    for config in configs {
+        for setting in &config.settings {
+            if !settings.iter().any(|s| s.name == setting.name) {
+                settings.push(setting.clone());
+            }
+        }
+    }
+
+    // block -> rules in first-seen order
+    let mut order: Vec<BlockKey> = Vec::new();
+    let mut groups: HashMap<BlockKey, Vec<Collected>> = HashMap::new();
+    let mut group_names: HashMap<BlockKey, String> = HashMap::new();
+    let mut and_ids: HashMap<(String, OnMatch, Vec<String>), usize> = HashMap::new();
+    let mut seen: HashSet<(BlockKey, String)> = HashSet::new();
+    // (event, onmatch) -> first config switching the event wholly on (empty exclude)
+    // or off (empty include) without filtering it otherwise.
+    let mut switches: HashMap<(String, OnMatch), usize> = HashMap::new();
+    let mut duplicates = 0;
+
+    for (config_index, config) in configs.iter().enumerate() {
+        for filter in &config.filters {
+            let id = match filter.group_relation {
+                GroupRelation::Or => 0,
+                GroupRelation::And => {
+                    let rules = filter.rules.iter().map(XmlElement::canonical).collect();
+                    let next = and_ids.len() + 1;
+                    *and_ids
+                        .entry((filter.event.clone(), filter.onmatch, rules))
+                        .or_insert(next)
+                }
+            };
+            let key = (
+                filter.event.clone(),
+                filter.onmatch,
+                filter.group_relation,
+                id,
+            );
+            if !groups.contains_key(&key) {
+                order.push(key.clone());
+                group_names.insert(key.clone(), filter.group_name.clone());
+            }
+            if filter.rules.is_empty()
+                && !config
+                    .filters
+                    .iter()
+                    .any(|f| f.event == filter.event && !f.rules.is_empty())
+            {
+                switches
+                    .entry((filter.event.clone(), filter.onmatch))
+                    .or_insert(config_index);
+            }
+            let rules = groups.entry(key.clone()).or_default();
+            for rule in &filter.rules {
+                let canonical = rule.canonical();
+                if !seen.insert((key.clone(), canonical)) {
+                    duplicates += 1;
+                    continue;
+                }
+                rules.push(Collected {
+                    rule: rule.clone(),
+                    config_index,
+                });
+            }
+        }
+    }
+
+    let mut conflicts = 0;
+    let events: Vec<String> = order.iter().map(|(e, ..)| e.clone()).collect();
+    for event in events {
+        let inc_key = (event.clone(), OnMatch::Include, GroupRelation::Or, 0);
+        let exc_key = (event.clone(), OnMatch::Exclude, GroupRelation::Or, 0);
+        let (Some(includes), Some(excludes)) = (groups.get(&inc_key), groups.get(&exc_key)) else {
+            continue;
+        };
+
+        let inc_by_rule: HashMap<String, usize> = includes
+            .iter()
+            .map(|c| (c.rule.canonical(), c.config_index))
+            .collect();
+        let exc_by_rule: HashMap<String, usize> = excludes
+            .iter()
+            .map(|c| (c.rule.canonical(), c.config_index))
+            .collect();
+
+        let mut drop_include = HashSet::new();
+        let mut drop_exclude = HashSet::new();
+        for (canonical, &inc_idx) in &inc_by_rule {
+            let Some(&exc_idx) = exc_by_rule.get(canonical) else {
+                continue;
+            };
+            // A single config listing a rule on both sides meant it; leave it alone.
+            if inc_idx == exc_idx {
+                continue;
+            }
+            let keep_include = match precedence {
+                MergePrecedence::ConfigOrder => inc_idx < exc_idx,
+                MergePrecedence::Include => true,
+                MergePrecedence::Exclude => false,
+            };
+            if keep_include {
+                drop_exclude.insert(canonical.clone());
+            } else {
+                drop_include.insert(canonical.clone());
+            }
+            conflicts += 1;
+        }
+
+        if let Some(rules) = groups.get_mut(&inc_key) {
+            rules.retain(|c| !drop_include.contains(&c.rule.canonical()));
+        }
+        if let Some(rules) = groups.get_mut(&exc_key) {
+            rules.retain(|c| !drop_exclude.contains(&c.rule.canonical()));
+        }
+    }
+
+    // A config that logs every event of a type (or none) against another one's rules
+    // on the other side is a conflict too; dropping either side changes what is logged.
+    for ((event, onmatch), &switch_idx) in &switches {
+        let opposite: Vec<&BlockKey> = groups
+            .iter()
+            .filter(|((e, m, ..), rules)| e == event && m != onmatch && !rules.is_empty())
+            .map(|(key, _)| key)
+            .collect();
+        let Some(rules_idx) = opposite
+            .iter()
+            .flat_map(|key| groups[*key].iter().map(|c| c.config_index))
+            .min()
+        else {
+            continue;
+        };
+        let keep_switch = match precedence {
+            MergePrecedence::ConfigOrder => switch_idx < rules_idx,
+            MergePrecedence::Include => *onmatch == OnMatch::Exclude,
+            MergePrecedence::Exclude => *onmatch == OnMatch::Include,
+        };
+        if keep_switch {
+            let opposite: Vec<BlockKey> = opposite.into_iter().cloned().collect();
+            for key in &opposite {
+                conflicts += groups.remove(key).map_or(0, |rules| rules.len());
+            }
+            order.retain(|key| !opposite.contains(key));
+        } else {
+            // The empty block is left out below, since the event still has rules.
+            conflicts += 1;
+        }
+    }
+
+    let events_with_rules: HashSet<String> = groups
+        .iter()
+        .filter(|(_, rules)| !rules.is_empty())
+        .map(|((event, ..), _)| event.clone())
+        .collect();
+
+    let mut filters: Vec<EventFilter> = Vec::new();
+    for key in &order {
+        let rules = groups.remove(key).unwrap_or_default();
+        // An empty block only switches a whole event on or off. Keep the first one when
+        // nobody filters that event, otherwise it would just contradict the real rules.
+        if rules.is_empty()
+            && (events_with_rules.contains(&key.0) || filters.iter().any(|f| f.event == key.0))
+        {
+            continue;
+        }
+        filters.push(EventFilter {
+            event: key.0.clone(),
+            onmatch: key.1,
+            group_name: group_names.get(key).cloned().unwrap_or_default(),
+            group_relation: key.2,
+            rules: rules.into_iter().map(|c| c.rule).collect(),
+        });
+    }
+
+    let name = configs
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    Ok(MergeResult {
+        config: SysmonConfig {
+            name,
+            schema_version,
+            settings,
+            filters,
+        },
+        duplicates,
+        conflicts,
+        warnings,
+    })
+}
+
+/// Load, merge and write `paths` to `out_path`, then re-parse the output as a check.
+pub fn merge_files(
+    paths: &[PathBuf],
+    precedence: MergePrecedence,
+    out_path: &Path,
+) -> Result<MergeResult> {
+    let configs = paths
+        .iter()
+        .map(|p| SysmonConfig::load(p))
+        .collect::<Result<Vec<_>>>()?;
+    let result = merge_configs(&configs, precedence)?;
+
+    let xml = result.config.to_xml();
+    let reparsed = SysmonConfig::parse("merged output", &xml)?;
+    if reparsed.schema_version != result.config.schema_version {
+        bail!("merged output lost its schemaversion");
+    }
+
+    if let Some(parent) = out_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(out_path, xml).with_context(|| format!("writing {}", out_path.display()))?;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: &str = r#"<Sysmon schemaversion="4.50">
+  <HashAlgorithms>md5,sha256</HashAlgorithms>
+  <EventFiltering>
+    <RuleGroup name="" groupRelation="or">
+      <ProcessCreate onmatch="exclude">
+        <Image condition="is">C:\Windows\System32\svchost.exe</Image>
+      </ProcessCreate>
+    </RuleGroup>
+    <RuleGroup name="" groupRelation="or">
+      <NetworkConnect onmatch="include">
+        <Image condition="end with">powershell.exe</Image>
+      </NetworkConnect>
+    </RuleGroup>
+  </EventFiltering>
+</Sysmon>"#;
+
+    const B: &str = r#"<Sysmon schemaversion="4.90">
+  <HashAlgorithms>*</HashAlgorithms>
+  <CheckRevocation/>
+  <EventFiltering>
+    <ProcessCreate onmatch="include">
+      <Image condition="is">C:\Windows\System32\svchost.exe</Image>
+      <Rule name="technique_id=T1059,technique_name=Command" groupRelation="and">
+        <Image condition="end with">cmd.exe</Image>
+        <CommandLine condition="contains">&amp;&amp;</CommandLine>
+      </Rule>
+    </ProcessCreate>
+    <NetworkConnect onmatch="include">
+      <Image condition="end with">powershell.exe</Image>
+    </NetworkConnect>
+  </EventFiltering>
+</Sysmon>"#;
+
+    fn parse(name: &str, text: &str) -> SysmonConfig {
+        SysmonConfig::parse(name, text).unwrap()
+    }
+
+    #[test]
+    fn parses_grouped_and_bare_events() {
+        let b = parse("b.xml", B);
+        assert_eq!(
+            b.schema_version,
+            SchemaVersion {
+                major: 4,
+                minor: 90
+            }
+        );
+        assert_eq!(b.filters.len(), 2);
+        let rule = &b.filters[0].rules[1];
+        assert_eq!(rule.name, "Rule");
+        assert_eq!(rule.children[1].text, "&&");
+    }
+
+    #[test]
+    fn merge_dedupes_and_resolves_conflicts() {
+        let configs = [parse("a.xml", A), parse("b.xml", B)];
+
+        let merged = merge_configs(&configs, MergePrecedence::ConfigOrder).unwrap();
+        assert_eq!(merged.duplicates, 1);
+        assert_eq!(merged.conflicts, 1);
+        assert_eq!(merged.config.schema_version.to_string(), "4.90");
+        assert_eq!(merged.config.settings[0].text, "md5,sha256");
+        let include = merged
+            .config
+            .filters
+            .iter()
+            .find(|f| f.event == "ProcessCreate" && f.onmatch == OnMatch::Include)
+            .unwrap();
+        assert_eq!(include.rules.len(), 1); // svchost include lost to a.xml's exclude
+
+        let merged = merge_configs(&configs, MergePrecedence::Include).unwrap();
+        assert!(
+            !merged
+                .config
+                .filters
+                .iter()
+                .any(|f| f.event == "ProcessCreate" && f.onmatch == OnMatch::Exclude)
+        );
+    }
+
+    #[test]
+    fn output_round_trips() {
+        let merged = merge_configs(
+            &[parse("a.xml", A), parse("b.xml", B)],
+            MergePrecedence::Exclude,
+        )
+        .unwrap();
+        let xml = merged.config.to_xml();
+        let again = SysmonConfig::parse("out", &xml).unwrap();
+        assert_eq!(again.filters, merged.config.filters);
+    }
+
+    #[test]
+    fn keeps_and_groups_apart() {
+        let and = parse(
+            "and.xml",
+            r#"<Sysmon schemaversion="4.50"><EventFiltering>
+  <RuleGroup name="encoded" groupRelation="and">
+    <ProcessCreate onmatch="include">
+      <Image condition="end with">powershell.exe</Image>
+      <CommandLine condition="contains">-enc</CommandLine>
+    </ProcessCreate>
+  </RuleGroup>
+</EventFiltering></Sysmon>"#,
+        );
+        let merged = merge_configs(
+            &[and.clone(), parse("b.xml", B), and],
+            MergePrecedence::ConfigOrder,
+        )
+        .unwrap();
+        let includes: Vec<&EventFilter> = merged
+            .config
+            .filters
+            .iter()
+            .filter(|f| f.event == "ProcessCreate" && f.onmatch == OnMatch::Include)
+            .collect();
+        assert_eq!(includes.len(), 2);
+        assert_eq!(includes[0].group_relation, GroupRelation::And);
+        assert_eq!(includes[0].rules.len(), 2);
+        assert_eq!(includes[1].group_relation, GroupRelation::Or);
+        assert_eq!(merged.duplicates, 2);
+
+        let xml = merged.config.to_xml();
+        assert!(xml.contains(r#"<RuleGroup name="encoded" groupRelation="and">"#));
+        let again = SysmonConfig::parse("out", &xml).unwrap();
+        assert_eq!(again.filters, merged.config.filters);
+    }
+
+    #[test]
+    fn log_everything_conflicts_with_includes() {
+        let all = parse(
+            "all.xml",
+            r#"<Sysmon schemaversion="4.50"><EventFiltering>
+  <NetworkConnect onmatch="exclude"/>
+</EventFiltering></Sysmon>"#,
+        );
+        let network = |merged: &MergeResult| -> Vec<(OnMatch, usize)> {
+            merged
+                .config
+                .filters
+                .iter()
+                .filter(|f| f.event == "NetworkConnect")
+                .map(|f| (f.onmatch, f.rules.len()))
+                .collect()
+        };
+
+        let merged = merge_configs(
+            &[all.clone(), parse("b.xml", B)],
+            MergePrecedence::ConfigOrder,
+        )
+        .unwrap();
+        assert_eq!(network(&merged), vec![(OnMatch::Exclude, 0)]);
+        assert_eq!(merged.conflicts, 1);
+
+        let merged =
+            merge_configs(&[all.clone(), parse("b.xml", B)], MergePrecedence::Exclude).unwrap();
+        assert_eq!(network(&merged), vec![(OnMatch::Include, 1)]);
+        assert_eq!(merged.conflicts, 1);
+
+        let merged = merge_configs(&[parse("b.xml", B), all], MergePrecedence::Include).unwrap();
+        assert_eq!(network(&merged), vec![(OnMatch::Exclude, 0)]);
+    }
+
+    #[test]
+    fn rejects_old_schema() {
+        let old = parse(
+            "old.xml",
+            r#"<Sysmon schemaversion="3.30"><EventFiltering/></Sysmon>"#,
+        );
+        assert!(merge_configs(&[old], MergePrecedence::ConfigOrder).is_err());
+    }
+}
@@ -0,0 +1,71 @@
+use super::sysmon_analysis::{ConfigDiff, ConfigReport};
+use super::sysmon_config::{MergePrecedence, SysmonConfig};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_SYSMON_DIR: &str = "./rule_output/sysmon";
+
+pub struct SysmonToolsApp {
+    pub input_dir: String,
+    /// Discovered configs in precedence order, with their selection state.
+    pub configs: Vec<(PathBuf, bool)>,
+    pub precedence: MergePrecedence,
+    pub custom_path: Option<String>,
+    pub status: Option<String>,
+    /// Configs parsed by the last "Analyze" run, with their coverage reports.
+    pub loaded: Vec<SysmonConfig>,
+    pub reports: Vec<ConfigReport>,
+    pub diff_base: usize,
+    pub diff_candidate: usize,
+    pub diff: Option<ConfigDiff>,
+}
+
+impl Default for SysmonToolsApp {
+    fn default() -> Self {
+        let mut app = Self {
+            input_dir: DEFAULT_SYSMON_DIR.to_string(),
+            configs: Vec::new(),
+            precedence: MergePrecedence::ConfigOrder,
+            custom_path: None,
+            status: None,
+            loaded: Vec::new(),
+            reports: Vec::new(),
+            diff_base: 0,
+            diff_candidate: 0,
+            diff: None,
+        };
+        app.rescan();
+        app
+    }
+}
+
+impl SysmonToolsApp {
+    /// Reload the XML list from `input_dir`, keeping all files selected.
+    pub fn rescan(&mut self) {
+        self.configs = list_xml_files(Path::new(&self.input_dir))
+            .into_iter()
+            .map(|p| (p, true))
+            .collect();
+    }
+
+    pub fn selected_paths(&self) -> Vec<PathBuf> {
+        self.configs
+            .iter()
+            .filter(|(_, on)| *on)
+            .map(|(p, _)| p.clone())
+            .collect()
+    }
+}
+
+fn list_xml_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|e| e.eq_ignore_ascii_case("xml")))
+        .collect();
+    files.sort();
+    files
+}
@@ -0,0 +1,247 @@
+use crate::download::{
+    ProgressTriplet, ToolSpec, advance_progress, fetch_tool_sources, set_progress_label,
+};
+use eframe::egui::{self, Context};
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+/// A rule source the selector can download and post-process.
+///
+/// After the sources in [`spec`](DetectionTool::spec) are fetched into
+/// `<output_root>/<dest_subfolder>`, the hooks run in order on that folder:
+/// `validate`, `dedup`, `convert`, `export`. `dedup` only sees the files the run
+/// wrote, so files the user already had in the folder are never touched.
+pub trait DetectionTool: Send + Sync + ToolClone {
+    fn spec(&self) -> ToolSpec;
+
+    fn name(&self) -> &'static str {
+        self.spec().name
+    }
+
+    fn total_sources(&self) -> usize {
+        let spec = self.spec();
+        spec.repo_urls.len() + spec.page_urls.len()
+    }
+
+    /// Remove files that are not usable rules for this tool.
+    fn validate(&self, _dir: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Drop duplicates among `written` and return how many went. Off by default;
+    /// tools that pull the same rules from several repos opt in with
+    /// [`remove_duplicate_files`].
+    fn dedup(&self, _written: &[PathBuf]) -> io::Result<usize> {
+        Ok(0)
+    }
+
+    /// Turn downloaded content into another format next to it.
+    fn convert(&self, _dir: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Produce deployable artifacts from the processed folder.
+    fn export(&self, _dir: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Whether [`ui_options`](DetectionTool::ui_options) draws anything.
+    fn has_options(&self) -> bool {
+        false
+    }
+
+    /// Tool-specific settings shown in the selector while the tool is checked.
+    fn ui_options(&mut self, _ui: &mut egui::Ui) {}
+}
+
+/// Lets a `Box<dyn DetectionTool>` be cloned so a run can own a snapshot of the
+/// options while the selector keeps editing its own copy.
+pub trait ToolClone {
+    fn clone_box(&self) -> Box<dyn DetectionTool>;
+}
+
+impl<T: DetectionTool + Clone + 'static> ToolClone for T {
+    fn clone_box(&self) -> Box<dyn DetectionTool> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn DetectionTool> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// Build-time registration. Any crate linked into the binary can add a tool with
+/// `inventory::submit! { ToolRegistration::new(order, || Box::new(MyTool)) }`.
+pub struct ToolRegistration {
+    /// Position in the selector; built-in tools use multiples of 10.
+    pub order: u32,
+    pub make: fn() -> Box<dyn DetectionTool>,
+}
+
+impl ToolRegistration {
+    pub const fn new(order: u32, make: fn() -> Box<dyn DetectionTool>) -> Self {
+        Self { order, make }
+    }
+}
+
+inventory::collect!(ToolRegistration);
+
+/// Fresh instances of every registered tool, in selector order.
+pub fn registered_tools() -> Vec<Box<dyn DetectionTool>> {
+    let mut regs: Vec<&ToolRegistration> = inventory::iter::<ToolRegistration>().collect();
+    regs.sort_by_key(|r| r.order);
+    regs.into_iter().map(|r| (r.make)()).collect()
+}
+
+pub fn find_tool(name: &str) -> Option<Box<dyn DetectionTool>> {
+    registered_tools()
+        .into_iter()
+        .find(|t| t.name().eq_ignore_ascii_case(name))
+}
+
+/// Run the post-processing hooks on `dir`, where this run wrote `written`,
+/// logging and continuing past a failing stage.
+pub fn post_process(
+    tool: &dyn DetectionTool,
+    dir: &Path,
+    written: &[PathBuf],
+    progress: &ProgressTriplet,
+    ctx: &Context,
+) {
+    let report = |result: io::Result<()>, stage: &str| {
+        if let Err(e) = result {
+            eprintln!("❌ [{}] {} failed: {}", tool.name(), stage, e);
+        }
+    };
+
+    report(tool.validate(dir), "validate");
+    // `validate` may have removed some of the files.
+    let written: Vec<PathBuf> = written.iter().filter(|p| p.exists()).cloned().collect();
+    match tool.dedup(&written) {
+        Ok(0) => {}
+        Ok(removed) => set_progress_label(
+            progress,
+            ctx,
+            &format!("{}: removed {} duplicate files", tool.name(), removed),
+        ),
+        Err(e) => report(Err(e), "dedup"),
+    }
+    report(tool.convert(dir), "convert");
+    report(tool.export(dir), "export");
+}
+
+/// Download and post-process one tool on a background thread. Progress advances
+/// once per source plus once for post-processing.
+pub fn run_tool(
+    tool: Box<dyn DetectionTool>,
+    output_root: PathBuf,
+    progress: ProgressTriplet,
+    ctx: Context,
+    cancel_flag: Arc<AtomicBool>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let spec = tool.spec();
+        let dest_dir = output_root.join(spec.dest_subfolder);
+        if let Err(e) = fs::create_dir_all(&dest_dir) {
+            eprintln!(
+                "❌ [{}] cannot create {}: {}",
+                spec.name,
+                dest_dir.display(),
+                e
+            );
+        }
+
+        let written = fetch_tool_sources(&spec, &dest_dir, &progress, &ctx, &cancel_flag);
+
+        if !cancel_flag.load(Ordering::Relaxed) {
+            set_progress_label(&progress, &ctx, &format!("{}: post-processing", spec.name));
+            post_process(tool.as_ref(), &dest_dir, &written, &progress, &ctx);
+        }
+        advance_progress(&progress, &ctx);
+    })
+}
+
+/// Delete those of `files` whose bytes match an earlier one of them. Returns how
+/// many went.
+pub fn remove_duplicate_files(files: &[PathBuf]) -> io::Result<usize> {
+    let mut seen: HashMap<(u64, u64), Vec<PathBuf>> = HashMap::new();
+    let mut removed = 0;
+
+    let mut files = files.to_vec();
+    files.sort();
+    files.dedup();
+
+    for path in files {
+        let bytes = fs::read(&path)?;
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        let key = (bytes.len() as u64, hasher.finish());
+
+        let candidates = seen.entry(key).or_default();
+        let mut duplicate = false;
+        for earlier in candidates.iter() {
+            if fs::read(earlier)? == bytes {
+                duplicate = true;
+                break;
+            }
+        }
+        if duplicate {
+            fs::remove_file(&path)?;
+            removed += 1;
+        } else {
+            candidates.push(path);
+        }
+    }
+
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_tools_register_in_order() {
+        let names: Vec<&str> = registered_tools().iter().map(|t| t.name()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "Yara",
+                "Suricata",
+                "Sigma",
+                "Splunk",
+                "QRadar",
+                "Sysmon",
+                "Zeek",
+                "Velociraptor"
+            ]
+        );
+        assert!(find_tool("sigma").unwrap().has_options());
+    }
+
+    #[test]
+    fn removes_byte_identical_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.yml"), "title: x").unwrap();
+        fs::write(dir.path().join("b.yml"), "title: x").unwrap();
+        fs::write(dir.path().join("c.yml"), "title: y").unwrap();
+        // Already in the folder before the run; must survive.
+        fs::write(dir.path().join("mine.yml"), "title: x").unwrap();
+
+        let written = ["c.yml", "b.yml", "a.yml"].map(|f| dir.path().join(f));
+        assert_eq!(remove_duplicate_files(&written).unwrap(), 1);
+        assert!(dir.path().join("a.yml").exists());
+        assert!(!dir.path().join("b.yml").exists());
+        assert!(dir.path().join("c.yml").exists());
+        assert!(dir.path().join("mine.yml").exists());
+    }
+}
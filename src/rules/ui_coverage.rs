@@ -0,0 +1,223 @@
+use super::coverage_menu::{CoverageApp, CoverageScan};
+use eframe::egui;
+use egui::{Color32, Margin};
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+
+pub fn render_ui_coverage(
+    app: &mut CoverageApp,
+    ctx: &egui::Context,
+    mut back_to_menu: impl FnMut(),
+) {
+    egui::CentralPanel::default()
+        .frame(
+            egui::Frame::default()
+                .inner_margin(Margin::same(30))
+                .outer_margin(Margin::same(20)),
+        )
+        .show(ctx, |ui| {
+            egui::ScrollArea::both().show(ui, |ui| {
+                render_coverage_body(app, ui, &mut back_to_menu);
+            });
+        });
+}
+
+/// Grey for uncovered, then pale yellow through to red as the count nears `max`.
+pub fn heat_color(count: usize, max: usize) -> Color32 {
+    if count == 0 {
+        return Color32::from_gray(60);
+    }
+    let t = (count as f32 / max.max(1) as f32).clamp(0.0, 1.0);
+    let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t) as u8;
+    Color32::from_rgb(lerp(255, 200), lerp(235, 30), lerp(130, 30))
+}
+
+fn heat_cell(ui: &mut egui::Ui, text: String, count: usize, max: usize, hover: Cow<str>) {
+    let fill = heat_color(count, max);
+    let color = if count == 0 {
+        Color32::LIGHT_GRAY
+    } else {
+        Color32::BLACK
+    };
+    ui.add(
+        egui::Button::new(egui::RichText::new(text).color(color).small())
+            .fill(fill)
+            .min_size(egui::vec2(150.0, 0.0)),
+    )
+    .on_hover_text(hover.as_ref());
+}
+
+fn rule_list<'a>(scan: &'a CoverageScan, technique: &str, tool: Option<&str>) -> Cow<'a, str> {
+    scan.rule_lists
+        .get(technique)
+        .and_then(|list| list.get(tool))
+        .map_or_else(|| format!("{}: no rules", technique).into(), Cow::Borrowed)
+}
+
+fn render_coverage_body(app: &mut CoverageApp, ui: &mut egui::Ui, back_to_menu: &mut impl FnMut()) {
+    ui.heading("ATT&CK coverage");
+    ui.add_space(10.0);
+
+    ui.horizontal(|ui| {
+        ui.label("Rule folder:");
+        ui.text_edit_singleline(&mut app.input_dir);
+        if ui.button("Browse").clicked()
+            && let Some(path) = rfd::FileDialog::new().pick_folder()
+        {
+            app.input_dir = path.display().to_string();
+        }
+    });
+    ui.add_space(5.0);
+
+    let scanning = app.scanning.load(Ordering::Relaxed);
+    if scanning {
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.label("Scanning rules…");
+        });
+    } else if ui.button("Scan rules").clicked() {
+        app.start(ui.ctx());
+    }
+
+    let scan = Arc::clone(&app.scan);
+    if !scanning && let Some(scan) = scan.lock().unwrap().as_ref() {
+        render_results(app, ui, scan);
+    }
+
+    ui.add_space(30.0);
+    ui.separator();
+    ui.add_space(40.0);
+    if ui
+        .add(
+            egui::Button::new(egui::RichText::new("⬅ Back to Menu").color(Color32::WHITE))
+                .fill(Color32::from_rgb(255, 140, 0)),
+        )
+        .clicked()
+    {
+        back_to_menu();
+    }
+}
+
+fn render_results(app: &mut CoverageApp, ui: &mut egui::Ui, scan: &CoverageScan) {
+    let map = &scan.map;
+    let tools: Vec<String> = map.tools().into_iter().map(str::to_string).collect();
+    let filter = app.tool_filter.as_deref();
+
+    ui.add_space(10.0);
+    ui.label(format!(
+        "{} techniques covered{}",
+        map.covered(filter),
+        filter.map_or(String::new(), |t| format!(" by {}", t))
+    ));
+    if map.skipped_files > 0 {
+        ui.label(format!("⚠ {} files could not be read", map.skipped_files));
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("Tool:");
+        egui::ComboBox::from_id_salt("coverage_tool")
+            .selected_text(app.tool_filter.as_deref().unwrap_or("All tools"))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut app.tool_filter, None, "All tools");
+                for tool in &tools {
+                    ui.selectable_value(&mut app.tool_filter, Some(tool.clone()), tool);
+                }
+            });
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Export folder:");
+        ui.text_edit_singleline(&mut app.export_dir);
+        if ui.button("Export CSV").clicked() {
+            app.export_csv(map);
+        }
+        if ui
+            .button("Export Navigator layer")
+            .on_hover_text("ATT&CK Navigator layer (v4.5) for the selected tool")
+            .clicked()
+        {
+            app.export_layer(scan);
+        }
+    });
+    if let Some(status) = &app.status {
+        ui.label(status);
+    }
+
+    ui.add_space(10.0);
+    let filter = app.tool_filter.as_deref();
+    match &scan.attack {
+        Some(attack) => {
+            ui.label(format!(
+                "ATT&CK {} {} — counts include sub-techniques",
+                attack.domain.label(),
+                attack.version_label()
+            ));
+            let max = attack
+                .techniques
+                .values()
+                .filter(|t| t.parent.is_none())
+                .map(|t| map.count_with_subtechniques(&t.id, filter))
+                .max()
+                .unwrap_or(0);
+            ui.horizontal_top(|ui| {
+                for tactic in &attack.tactics {
+                    ui.vertical(|ui| {
+                        ui.strong(&tactic.name);
+                        for id in attack.techniques_for_tactic(tactic) {
+                            let technique = &attack.techniques[&id];
+                            if technique.parent.is_some() {
+                                continue;
+                            }
+                            let count = map.count_with_subtechniques(&id, filter);
+                            heat_cell(
+                                ui,
+                                format!("{} {}", id, technique.name),
+                                count,
+                                max,
+                                rule_list(scan, &id, filter),
+                            );
+                        }
+                    });
+                }
+            });
+        }
+        None => {
+            ui.label("No cached ATT&CK bundle — showing referenced techniques only.");
+            let max = map
+                .techniques
+                .values()
+                .map(|c| c.count(filter))
+                .max()
+                .unwrap_or(0);
+            egui::Grid::new("coverage_grid")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.strong("Technique");
+                    for tool in &tools {
+                        ui.strong(tool);
+                    }
+                    ui.strong("Total");
+                    ui.end_row();
+                    for (id, c) in &map.techniques {
+                        if c.count(filter) == 0 {
+                            continue;
+                        }
+                        ui.label(id);
+                        for tool in &tools {
+                            let n = c.count(Some(tool));
+                            heat_cell(ui, n.to_string(), n, max, rule_list(scan, id, Some(tool)));
+                        }
+                        heat_cell(
+                            ui,
+                            c.total().to_string(),
+                            c.total(),
+                            max,
+                            rule_list(scan, id, None),
+                        );
+                        ui.end_row();
+                    }
+                });
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use super::hunt_menu::HuntApp;
+use super::hunt_pack::HuntPack;
+use eframe::egui;
+use egui::{Color32, Margin};
+use std::sync::atomic::Ordering;
+
+pub fn render_ui_hunt(app: &mut HuntApp, ctx: &egui::Context, mut back_to_menu: impl FnMut()) {
+    egui::CentralPanel::default()
+        .frame(
+            egui::Frame::default()
+                .inner_margin(Margin::same(30))
+                .outer_margin(Margin::same(20)),
+        )
+        .show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                render_hunt_body(app, ui, &mut back_to_menu);
+            });
+        });
+}
+
+fn folder_row(ui: &mut egui::Ui, label: &str, value: &mut String) {
+    ui.label(label);
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(value);
+        if ui.button("Browse").clicked()
+            && let Some(path) = rfd::FileDialog::new().pick_folder()
+        {
+            *value = path.display().to_string();
+        }
+    });
+    ui.end_row();
+}
+
+fn render_hunt_body(app: &mut HuntApp, ui: &mut egui::Ui, back_to_menu: &mut impl FnMut()) {
+    ui.heading("Threat-actor hunt pack");
+    ui.add_space(10.0);
+    ui.label(
+        "Pick an ATT&CK group to collect the rules and IOC files covering its techniques \
+         or naming it. ATT&CK data comes from the bundle configured on the MITRE screen.",
+    );
+    ui.add_space(10.0);
+
+    egui::Grid::new("hunt_inputs").show(ui, |ui| {
+        ui.label("Group");
+        ui.add(egui::TextEdit::singleline(&mut app.group).hint_text("G0016, APT29, Cozy Bear"));
+        ui.end_row();
+        folder_row(ui, "Rule folder", &mut app.rule_dir);
+        folder_row(ui, "IOC folder", &mut app.ioc_dir);
+        folder_row(ui, "Output folder", &mut app.out_dir);
+    });
+
+    ui.add_space(10.0);
+    if app.running.load(Ordering::Relaxed) {
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.label("Building hunt pack…");
+        });
+    } else if ui
+        .add_enabled(
+            !app.group.trim().is_empty(),
+            egui::Button::new("Build hunt pack"),
+        )
+        .clicked()
+    {
+        app.start(ui.ctx());
+    }
+
+    if let Some(result) = app.result.lock().unwrap().as_ref() {
+        ui.add_space(10.0);
+        match result {
+            Ok(HuntPack { plan, dir }) => {
+                let group = &plan.group;
+                ui.label(format!(
+                    "✅ {} ({}) hunt pack written to {}",
+                    group.name,
+                    group.id,
+                    dir.display()
+                ));
+                ui.label(format!(
+                    "{} techniques, {} IOC files",
+                    group.techniques.len(),
+                    plan.iocs.len()
+                ));
+                for (tool, count) in plan.rule_counts() {
+                    ui.label(format!("{}: {} rule files", tool, count));
+                }
+                if !plan.gaps.is_empty() {
+                    egui::CollapsingHeader::new(format!(
+                        "⚠ {} techniques without rules",
+                        plan.gaps.len()
+                    ))
+                    .show(ui, |ui| {
+                        ui.label(plan.gaps.join(", "));
+                    });
+                }
+            }
+            Err(e) => {
+                ui.label(format!("❌ {}", e));
+            }
+        }
+    }
+
+    ui.add_space(30.0);
+    ui.separator();
+    ui.add_space(40.0);
+    if ui
+        .add(
+            egui::Button::new(egui::RichText::new("⬅ Back to Menu").color(Color32::WHITE))
+                .fill(Color32::from_rgb(255, 140, 0)),
+        )
+        .clicked()
+    {
+        back_to_menu();
+    }
+}
@@ -0,0 +1,188 @@
+use super::attack_data::AttackDomain;
+use super::grab_mitre::{PRIORITIES, TACTICS, split_inputs};
+use super::mitre_menu::MitreApp;
+use crate::settings::Settings;
+use eframe::egui;
+use egui::Margin;
+use std::sync::atomic::Ordering;
+
+pub fn render_ui_mitre(app: &mut MitreApp, ctx: &egui::Context, mut back_to_menu: impl FnMut()) {
+    egui::CentralPanel::default()
+        .frame(
+            egui::Frame::default()
+                .inner_margin(Margin::same(30))
+                .outer_margin(Margin::same(20)),
+        )
+        .show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                render_mitre_body(app, ui, &mut back_to_menu);
+            });
+        });
+}
+
+fn render_mitre_body(app: &mut MitreApp, ui: &mut egui::Ui, back_to_menu: &mut impl FnMut()) {
+    ui.heading("MITRE ATT&CK analytics");
+    ui.add_space(10.0);
+
+    if let Some((done, total, current)) = app.progress.lock().unwrap().clone() {
+        let fraction = done as f32 / total.max(1) as f32;
+        ui.label(format!("Progress: {}/{}", done, total));
+        ui.add(egui::ProgressBar::new(fraction).show_percentage());
+        if !current.is_empty() {
+            ui.label(format!("Currently processing: {}", current));
+        }
+        if ui.button("Cancel").clicked() {
+            app.cancel_flag.store(true, Ordering::Relaxed);
+        }
+        return;
+    }
+
+    ui.label("Technique IDs (T1055, T1003.001) or a tactic (TA0002, Execution):");
+    ui.add(
+        egui::TextEdit::multiline(&mut app.input)
+            .desired_rows(3)
+            .hint_text("T1055, T1003.001\nCredential Access"),
+    );
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Tactics:");
+        for (id, name) in TACTICS {
+            if ui.small_button(name).on_hover_text(id).clicked() {
+                if !app.input.trim().is_empty() {
+                    app.input.push('\n');
+                }
+                app.input.push_str(name);
+            }
+        }
+    });
+
+    ui.add_space(10.0);
+    egui::CollapsingHeader::new("Analytic settings").show(ui, |ui| {
+        egui::Grid::new("mitre_settings").show(ui, |ui| {
+            for (label, value) in [
+                ("Creator", &mut app.settings.creator),
+                ("Index macro", &mut app.settings.index_macro),
+                ("Whitelist macro", &mut app.settings.whitelist_macro),
+                ("ATT&CK version (scraping)", &mut app.settings.mitre_version),
+            ] {
+                ui.label(label);
+                ui.text_edit_singleline(value);
+                ui.end_row();
+            }
+            ui.label("Priority");
+            egui::ComboBox::from_id_salt("mitre_priority")
+                .selected_text(&app.settings.priority)
+                .show_ui(ui, |ui| {
+                    for p in PRIORITIES {
+                        ui.selectable_value(&mut app.settings.priority, p.to_string(), p);
+                    }
+                });
+            ui.end_row();
+            ui.label("Output folder");
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut app.settings.output_dir);
+                if ui.button("Choose").clicked()
+                    && let Some(path) = rfd::FileDialog::new().pick_folder()
+                {
+                    app.settings.output_dir = path.display().to_string();
+                }
+            });
+            ui.end_row();
+        });
+        ui.add_space(10.0);
+        ui.checkbox(
+            &mut app.settings.offline,
+            "Use the ATT&CK STIX bundle (offline) instead of scraping attack.mitre.org",
+        );
+        ui.add_enabled_ui(app.settings.offline, |ui| {
+            egui::Grid::new("mitre_stix").show(ui, |ui| {
+                ui.label("Matrix");
+                egui::ComboBox::from_id_salt("mitre_domain")
+                    .selected_text(app.settings.domain.label())
+                    .show_ui(ui, |ui| {
+                        for domain in AttackDomain::ALL {
+                            ui.selectable_value(&mut app.settings.domain, domain, domain.label());
+                        }
+                    });
+                ui.end_row();
+                ui.label("Local bundle");
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut app.settings.stix_file)
+                            .hint_text("empty = cached download"),
+                    );
+                    if ui.button("Browse").clicked()
+                        && let Some(path) = rfd::FileDialog::new()
+                            .add_filter("STIX bundle", &["json"])
+                            .pick_file()
+                    {
+                        app.settings.stix_file = path.display().to_string();
+                    }
+                });
+                ui.end_row();
+                ui.label("Cache folder");
+                ui.text_edit_singleline(&mut app.settings.cache_dir);
+                ui.end_row();
+            });
+            if ui.button("Download / refresh ATT&CK data").clicked() {
+                app.refresh_attack_data(ui.ctx());
+            }
+            if let Some(status) = app.attack_status.lock().unwrap().as_ref() {
+                ui.label(status);
+            }
+        });
+
+        ui.add_space(5.0);
+        if ui.button("Save as default").clicked() {
+            let mut settings = Settings::load();
+            settings.mitre = app.settings.clone();
+            if let Err(e) = settings.save() {
+                eprintln!("❌ Failed to save settings: {}", e);
+            }
+        }
+    });
+
+    ui.add_space(20.0);
+    let has_input = !split_inputs(&app.input).is_empty();
+    if ui
+        .add_enabled(has_input, egui::Button::new("Generate Analytics"))
+        .clicked()
+    {
+        app.start(ui.ctx());
+    }
+
+    if let Some(result) = app.result.lock().unwrap().as_ref() {
+        ui.add_space(10.0);
+        match result {
+            Ok(summary) => {
+                ui.label(format!(
+                    "✅ {} analytics written for {} techniques to {}",
+                    summary.files.len(),
+                    summary.techniques,
+                    app.settings.output_dir
+                ));
+                if let Some(version) = &summary.attack_version {
+                    ui.label(format!("ATT&CK {}", version));
+                }
+                for (id, err) in &summary.failures {
+                    ui.label(format!("❌ {}: {}", id, err));
+                }
+            }
+            Err(e) => {
+                ui.label(format!("❌ {}", e));
+            }
+        }
+    }
+
+    ui.add_space(30.0);
+    ui.separator();
+    ui.add_space(40.0);
+    if ui
+        .add(
+            egui::Button::new(egui::RichText::new("⬅ Back to Menu").color(egui::Color32::WHITE))
+                .fill(egui::Color32::from_rgb(255, 140, 0)),
+        )
+        .clicked()
+    {
+        back_to_menu();
+    }
+}
@@ -0,0 +1,311 @@
+use super::sysmon_analysis::{ConfigDiff, analyze, diff, event_ids};
+use super::sysmon_config::{MergePrecedence, OnMatch, SysmonConfig, merge_files};
+use super::sysmon_menu::SysmonToolsApp;
+use crate::download::render_output_path_selector;
+use eframe::egui;
+use egui::Margin;
+use std::path::Path;
+
+const DEFAULT_MERGE_OUTPUT: &str = "./rule_output/sysmon_merged";
+
+pub fn render_ui_sysmon(
+    app: &mut SysmonToolsApp,
+    ctx: &egui::Context,
+    mut back_to_menu: impl FnMut(),
+) {
+    egui::CentralPanel::default()
+        .frame(
+            egui::Frame::default()
+                .inner_margin(Margin::same(30))
+                .outer_margin(Margin::same(20)),
+        )
+        .show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                render_sysmon_body(app, ui, &mut back_to_menu);
+            });
+        });
+}
+
+fn render_sysmon_body(
+    app: &mut SysmonToolsApp,
+    ui: &mut egui::Ui,
+    back_to_menu: &mut impl FnMut(),
+) {
+    ui.heading("Sysmon configs");
+    ui.add_space(10.0);
+
+    ui.horizontal(|ui| {
+        ui.label(format!("Configs from: {}", app.input_dir));
+        if ui.button("Choose Folder").clicked()
+            && let Some(path) = rfd::FileDialog::new().pick_folder()
+        {
+            app.input_dir = path.display().to_string();
+            app.rescan();
+        }
+        if ui.button("Rescan").clicked() {
+            app.rescan();
+        }
+    });
+
+    ui.add_space(10.0);
+    if app.configs.is_empty() {
+        ui.label("No .xml files found. Download the Sysmon rules first.");
+    } else {
+        ui.label("Higher in the list = higher precedence:");
+    }
+
+    let mut move_up = None;
+    let len = app.configs.len();
+    for (i, (path, on)) in app.configs.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            if ui.add_enabled(i > 0, egui::Button::new("⬆")).clicked() {
+                move_up = Some(i);
+            }
+            if ui
+                .add_enabled(i + 1 < len, egui::Button::new("⬇"))
+                .clicked()
+            {
+                move_up = Some(i + 1);
+            }
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            ui.checkbox(on, name);
+        });
+    }
+    if let Some(i) = move_up {
+        app.configs.swap(i - 1, i);
+    }
+
+    ui.add_space(10.0);
+    ui.label("When one config includes what another excludes:");
+    ui.radio_value(
+        &mut app.precedence,
+        MergePrecedence::ConfigOrder,
+        "Higher precedence config wins",
+    );
+    ui.radio_value(
+        &mut app.precedence,
+        MergePrecedence::Include,
+        "Keep the include (more coverage)",
+    );
+    ui.radio_value(
+        &mut app.precedence,
+        MergePrecedence::Exclude,
+        "Keep the exclude (less noise)",
+    );
+
+    ui.add_space(10.0);
+    ui.separator();
+    ui.add_space(10.0);
+    render_output_path_selector(ui, &mut app.custom_path, DEFAULT_MERGE_OUTPUT);
+
+    ui.add_space(20.0);
+    let selected = app.selected_paths();
+    if ui
+        .add_enabled(!selected.is_empty(), egui::Button::new("Merge Selected"))
+        .clicked()
+    {
+        let out_dir = app
+            .custom_path
+            .clone()
+            .unwrap_or_else(|| DEFAULT_MERGE_OUTPUT.to_string());
+        let out_path = Path::new(&out_dir).join("sysmonconfig.xml");
+        app.status = Some(match merge_files(&selected, app.precedence, &out_path) {
+            Ok(result) => {
+                let mut msg = format!(
+                    "✅ Wrote {} (schema {}, {} duplicates removed, {} conflicts resolved)",
+                    out_path.display(),
+                    result.config.schema_version,
+                    result.duplicates,
+                    result.conflicts
+                );
+                for warning in result.warnings {
+                    msg.push_str(&format!("\n⚠ {}", warning));
+                }
+                msg
+            }
+            Err(e) => format!("❌ Merge failed: {:#}", e),
+        });
+    }
+
+    if let Some(status) = &app.status {
+        ui.add_space(10.0);
+        ui.label(status);
+    }
+
+    ui.add_space(20.0);
+    ui.separator();
+    ui.heading("Coverage and diff");
+    ui.add_space(10.0);
+    if ui
+        .add_enabled(!selected.is_empty(), egui::Button::new("Analyze Selected"))
+        .clicked()
+    {
+        let mut errors = Vec::new();
+        app.loaded = selected
+            .iter()
+            .filter_map(|p| {
+                SysmonConfig::load(p)
+                    .map_err(|e| errors.push(format!("❌ {:#}", e)))
+                    .ok()
+            })
+            .collect();
+        app.reports = app.loaded.iter().map(analyze).collect();
+        app.diff_base = 0;
+        app.diff_candidate = app.loaded.len().saturating_sub(1).min(1);
+        app.diff = None;
+        if !errors.is_empty() {
+            app.status = Some(errors.join("\n"));
+        }
+    }
+
+    if !app.reports.is_empty() {
+        render_coverage(app, ui);
+        ui.add_space(10.0);
+        render_diff(app, ui);
+    }
+
+    ui.add_space(30.0);
+    ui.separator();
+    ui.add_space(40.0);
+    if ui
+        .add(
+            egui::Button::new(egui::RichText::new("⬅ Back to Menu").color(egui::Color32::WHITE))
+                .fill(egui::Color32::from_rgb(255, 140, 0)),
+        )
+        .clicked()
+    {
+        back_to_menu();
+    }
+}
+
+/// Side-by-side include/exclude counts per event, one column per config.
+fn render_coverage(app: &SysmonToolsApp, ui: &mut egui::Ui) {
+    let mut events: Vec<&str> = Vec::new();
+    for report in &app.reports {
+        for e in &report.events {
+            if !events.contains(&e.event.as_str()) {
+                events.push(&e.event);
+            }
+        }
+    }
+    events.sort_by_key(|e| event_ids(e).first().copied().unwrap_or(u32::MAX));
+
+    egui::ScrollArea::horizontal().show(ui, |ui| {
+        egui::Grid::new("sysmon_coverage")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.strong("Event");
+                ui.strong("IDs");
+                for report in &app.reports {
+                    ui.strong(format!("{} ({})", report.name, report.schema_version));
+                }
+                ui.end_row();
+
+                for event in &events {
+                    ui.label(*event);
+                    let ids: Vec<String> = event_ids(event).iter().map(|i| i.to_string()).collect();
+                    ui.label(ids.join(","));
+                    for report in &app.reports {
+                        match report.event(event) {
+                            Some(c) => {
+                                let state = if c.enabled { "✅" } else { "⛔" };
+                                ui.label(format!("{} +{} / -{}", state, c.includes, c.excludes));
+                            }
+                            None => {
+                                ui.label("—");
+                            }
+                        }
+                    }
+                    ui.end_row();
+                }
+
+                ui.strong("ATT&CK techniques");
+                ui.label("");
+                for report in &app.reports {
+                    ui.label(report.techniques.len().to_string());
+                }
+                ui.end_row();
+            });
+    });
+
+    for report in &app.reports {
+        egui::CollapsingHeader::new(format!("Techniques in {}", report.name))
+            .id_salt(("sysmon_techniques", &report.name))
+            .show(ui, |ui| {
+                let list: Vec<String> = report
+                    .techniques
+                    .iter()
+                    .map(|(t, n)| format!("{} ({})", t, n))
+                    .collect();
+                ui.label(list.join(", "));
+            });
+    }
+}
+
+fn render_diff(app: &mut SysmonToolsApp, ui: &mut egui::Ui) {
+    if app.loaded.len() < 2 {
+        ui.label("Analyze at least two configs to compare them.");
+        return;
+    }
+
+    let names: Vec<String> = app.loaded.iter().map(|c| c.name.clone()).collect();
+    ui.horizontal(|ui| {
+        ui.label("Baseline:");
+        egui::ComboBox::from_id_salt("sysmon_diff_base")
+            .selected_text(&names[app.diff_base])
+            .show_ui(ui, |ui| {
+                for (i, name) in names.iter().enumerate() {
+                    ui.selectable_value(&mut app.diff_base, i, name);
+                }
+            });
+        ui.label("Switch to:");
+        egui::ComboBox::from_id_salt("sysmon_diff_candidate")
+            .selected_text(&names[app.diff_candidate])
+            .show_ui(ui, |ui| {
+                for (i, name) in names.iter().enumerate() {
+                    ui.selectable_value(&mut app.diff_candidate, i, name);
+                }
+            });
+        if ui.button("Compare").clicked() {
+            app.diff = Some(diff(
+                &app.loaded[app.diff_base],
+                &app.loaded[app.diff_candidate],
+            ));
+        }
+    });
+
+    let Some(d) = &app.diff else {
+        return;
+    };
+    diff_section(ui, "Events lost", &d.events_lost);
+    diff_section(ui, "Events gained", &d.events_gained);
+    diff_section(ui, "Techniques lost", &d.techniques_lost);
+    diff_section(ui, "Techniques gained", &d.techniques_gained);
+    diff_section(ui, "Rules lost", &rule_lines(d, true));
+    diff_section(ui, "Rules gained", &rule_lines(d, false));
+}
+
+fn rule_lines(d: &ConfigDiff, lost: bool) -> Vec<String> {
+    let rules = if lost { &d.rules_lost } else { &d.rules_gained };
+    rules
+        .iter()
+        .map(|(event, onmatch, rule)| {
+            let sign = if *onmatch == OnMatch::Include {
+                "+"
+            } else {
+                "-"
+            };
+            format!("{} {} {}", event, sign, rule)
+        })
+        .collect()
+}
+
+fn diff_section(ui: &mut egui::Ui, title: &str, items: &[String]) {
+    egui::CollapsingHeader::new(format!("{} ({})", title, items.len()))
+        .id_salt(("sysmon_diff", title))
+        .show(ui, |ui| {
+            for item in items {
+                ui.monospace(item);
+            }
+        });
+}
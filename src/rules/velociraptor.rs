@@ -0,0 +1,55 @@
+use super::tool::{DetectionTool, ToolRegistration};
+use crate::download::ToolSpec;
+use serde_yaml::Value;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+#[derive(Clone, Default)]
+pub struct VelociraptorArtifacts;
+
+impl DetectionTool for VelociraptorArtifacts {
+    fn spec(&self) -> ToolSpec {
+        ToolSpec {
+            name: "Velociraptor",
+            dest_subfolder: "velociraptor",
+            repo_urls: VELOCIRAPTOR_REPOS,
+            page_urls: VELOCIRAPTOR_PAGES,
+            allowed_exts: &["yaml", "yml"],
+            file_filter: Some(Arc::new(is_artifact)),
+            keep_structure: false,
+            repo_paths: VELOCIRAPTOR_PATHS,
+        }
+    }
+}
+
+inventory::submit! {
+    ToolRegistration::new(80, || Box::new(VelociraptorArtifacts))
+}
+
+/// Velociraptor artifacts are YAML documents with a `name` and either `sources`
+/// or a `type`; the docs repo also carries site config YAML that must be skipped.
+pub fn is_artifact(path: &Path) -> bool {
+    let Ok(text) = fs::read_to_string(path) else {
+        return false;
+    };
+    let Ok(doc) = serde_yaml::from_str::<Value>(&text) else {
+        return false;
+    };
+    doc.get("name").and_then(Value::as_str).is_some()
+        && (doc.get("sources").is_some() || doc.get("type").is_some())
+}
+
+// Artifact exchange (velociraptor-docs) and DetectRaptor's generated detection artifacts.
+static VELOCIRAPTOR_REPOS: &[&str] = &[
+    "https://github.com/mgreen27/DetectRaptor.git",
+    "https://github.com/Velocidex/velociraptor-docs.git",
+];
+
+// The docs site itself is large; the exchange artifacts are one folder of it.
+static VELOCIRAPTOR_PATHS: &[(&str, &str)] = &[(
+    "https://github.com/Velocidex/velociraptor-docs.git",
+    "content/exchange/artifacts",
+)];
+
+static VELOCIRAPTOR_PAGES: &[&str] = &[];
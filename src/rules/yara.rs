@@ -1,36 +1,33 @@
-use crate::download::{process_tool, ToolSpec};
-use eframe::egui::Context;
-use std::path::Path;
-use std::sync::{Arc, Mutex};
-use std::sync::atomic::AtomicBool;
+use super::tool::{DetectionTool, ToolRegistration, remove_duplicate_files};
+use crate::download::ToolSpec;
+use std::io;
+use std::path::PathBuf;
 
-pub fn yara_total_sources() -> usize {
-    YARA_REPOS.len() + YARA_PAGES.len()
-}
+#[derive(Clone, Default)]
+pub struct YaraRules;
+
+impl DetectionTool for YaraRules {
+    fn spec(&self) -> ToolSpec {
+        ToolSpec {
+            name: "Yara",
+            dest_subfolder: "yara",
+            repo_urls: &YARA_REPOS,
+            page_urls: &YARA_PAGES,
+            allowed_exts: &["yar", "yara", "txt"],
+            file_filter: None,
+            keep_structure: false,
+            repo_paths: &[],
+        }
+    }
 
-pub fn yara_spec() -> ToolSpec {
-    ToolSpec {
-        name: "Yara",
-        dest_subfolder: "yara",
-        repo_urls: &YARA_REPOS,
-        page_urls: &YARA_PAGES,
-        allowed_exts: &["yar", "yara", "txt"],
+    /// Many of these repos mirror each other's rules verbatim.
+    fn dedup(&self, written: &[PathBuf]) -> io::Result<usize> {
+        remove_duplicate_files(written)
     }
 }
 
-pub fn process_yara(
-    output_root: &str,
-    progress_triplet: Arc<Mutex<Option<(usize, usize, String)>>>,
-    ctx: Context,
-    cancel_flag: Arc<AtomicBool>,
-) {
-    let _ = process_tool(
-        &yara_spec(),
-        Path::new(output_root),
-        progress_triplet,
-        ctx,
-        cancel_flag,
-    );
+inventory::submit! {
+    ToolRegistration::new(10, || Box::new(YaraRules))
 }
 
 static YARA_REPOS: [&str; 70] = [
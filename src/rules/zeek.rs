@@ -1,42 +1,44 @@
-use crate::download::{process_tool, ToolSpec};
-use eframe::egui::Context;
-use std::path::Path;
-use std::sync::{Arc, Mutex};
-use std::sync::atomic::AtomicBool;
+use super::tool::{DetectionTool, ToolRegistration};
+use crate::download::ToolSpec;
 
-pub fn sigma_total_sources() -> usize {
-    ZEEK_REPOS.len() + ZEEK_PAGES.len()
-}
+#[derive(Clone, Default)]
+pub struct ZeekScripts;
 
-pub fn sigma_spec() -> ToolSpec {
-    ToolSpec {
-        name: "Sigma",
-        dest_subfolder: "sigma",
-        repo_urls: &ZEEK_REPOS,
-        page_urls: &ZEEK_PAGES,
-        allowed_exts: &["yml", "yaml"],
+impl DetectionTool for ZeekScripts {
+    fn spec(&self) -> ToolSpec {
+        ToolSpec {
+            name: "Zeek",
+            dest_subfolder: "zeek",
+            repo_urls: ZEEK_REPOS,
+            page_urls: ZEEK_PAGES,
+            allowed_exts: &["zeek", "sig"],
+            file_filter: None,
+            keep_structure: true, // packages @load their own scripts by relative path
+            repo_paths: ZEEK_PATHS,
+        }
     }
 }
 
-pub fn process_sigma(
-    output_root: &str,
-    progress_triplet: Arc<Mutex<Option<(usize, usize, String)>>>,
-    ctx: Context,
-    cancel_flag: Arc<AtomicBool>,
-) {
-    let _ = process_tool(
-        &sigma_spec(),
-        Path::new(output_root),
-        progress_triplet,
-        ctx,
-        cancel_flag,
-    );
+inventory::submit! {
+    ToolRegistration::new(70, || Box::new(ZeekScripts))
 }
 
+// Zeek core policy scripts plus standalone detection packages (zkg repos).
 static ZEEK_REPOS: &[&str] = &[
     "https://github.com/zeek/zeek.git",
+    "https://github.com/salesforce/ja3.git",
+    "https://github.com/salesforce/hassh.git",
+    "https://github.com/corelight/CVE-2021-44228.git",
+    "https://github.com/corelight/CVE-2021-31166.git",
+    "https://github.com/corelight/cve-2020-16898.git",
+    "https://github.com/corelight/zerologon.git",
+    "https://github.com/corelight/SIGRed.git",
+    "https://github.com/corelight/callstranger-detector.git",
+    "https://github.com/corelight/detect-ransomware-filenames.git",
+    "https://github.com/corelight/zeek-long-connections.git",
 ];
 
-static ZEEK_PAGES: &[&str] = &[
-    "",
-];
+// The core repo is mostly tests and base scripts; only the policy scripts detect things.
+static ZEEK_PATHS: &[(&str, &str)] = &[("https://github.com/zeek/zeek.git", "scripts/policy")];
+
+static ZEEK_PAGES: &[&str] = &[];
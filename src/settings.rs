@@ -0,0 +1,54 @@
+use crate::ioc::database::ExpirySettings;
+use crate::ioc::suricata::SuricataIocSettings;
+use crate::ioc::warninglist::WarninglistSettings;
+use crate::ioc::yara::YaraIocSettings;
+use crate::rules::grab_mitre::MitreSettings;
+use crate::rules::sigma::SigmaFilter;
+use crate::rules::splunk_app::SplunkAppSettings;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Settings file read from the working directory at startup.
+pub const SETTINGS_FILE: &str = "detection_wizard.json";
+
+/// User defaults shared by the GUI and CLI. Missing keys fall back to their defaults,
+/// so older settings files keep loading as new sections are added.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub sigma_filter: SigmaFilter,
+    pub splunk_app: SplunkAppSettings,
+    pub mitre: MitreSettings,
+    pub warninglists: WarninglistSettings,
+    pub ioc_expiry: ExpirySettings,
+    pub ioc_suricata: SuricataIocSettings,
+    pub ioc_yara: YaraIocSettings,
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        Self::load_from(Path::new(SETTINGS_FILE))
+    }
+
+    /// Load settings, falling back to defaults when the file is missing or invalid.
+    pub fn load_from(path: &Path) -> Self {
+        let Ok(text) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        serde_json::from_str(&text).unwrap_or_else(|e| {
+            eprintln!("❌ Ignoring invalid settings in {}: {}", path.display(), e);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        self.save_to(Path::new(SETTINGS_FILE))
+    }
+
+    pub fn save_to(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+}
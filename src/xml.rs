@@ -0,0 +1,130 @@
+use anyhow::{Result, anyhow, bail};
+use quick_xml::Reader;
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+
+/// Minimal XML element tree, used for Sysmon configs and OpenIOC files. Comments
+/// and processing instructions are dropped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmlElement {
+    pub name: String,
+    pub attrs: Vec<(String, String)>,
+    pub text: String,
+    pub children: Vec<XmlElement>,
+}
+
+impl XmlElement {
+    fn from_start(start: &BytesStart) -> Result<Self> {
+        let mut attrs = Vec::new();
+        for attr in start.attributes() {
+            let attr = attr?;
+            let key = String::from_utf8_lossy(attr.key.as_ref()).to_string();
+            attrs.push((key, attr.unescape_value()?.to_string()));
+        }
+        Ok(Self {
+            name: String::from_utf8_lossy(start.name().as_ref()).to_string(),
+            attrs,
+            text: String::new(),
+            children: Vec::new(),
+        })
+    }
+
+    pub fn attr(&self, key: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn child(&self, name: &str) -> Option<&XmlElement> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    /// Stable single-line form used to compare rules across configs.
+    pub fn canonical(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out, None);
+        out
+    }
+
+    /// Append the element to `out`, pretty-printed from depth `indent` when given,
+    /// otherwise on one line.
+    pub fn write(&self, out: &mut String, indent: Option<usize>) {
+        let pad = indent.map(|d| "  ".repeat(d)).unwrap_or_default();
+        out.push_str(&pad);
+        out.push('<');
+        out.push_str(&self.name);
+        for (k, v) in &self.attrs {
+            out.push_str(&format!(" {}=\"{}\"", k, escape(v.as_str())));
+        }
+
+        if self.children.is_empty() && self.text.is_empty() {
+            out.push_str("/>");
+        } else if self.children.is_empty() {
+            out.push('>');
+            out.push_str(&escape(self.text.as_str()));
+            out.push_str(&format!("</{}>", self.name));
+        } else {
+            out.push('>');
+            for child in &self.children {
+                if indent.is_some() {
+                    out.push('\n');
+                }
+                child.write(out, indent.map(|d| d + 1));
+            }
+            if indent.is_some() {
+                out.push('\n');
+                out.push_str(&pad);
+            }
+            out.push_str(&format!("</{}>", self.name));
+        }
+    }
+}
+
+/// Parse an XML document into its root element.
+pub fn parse_xml(text: &str) -> Result<XmlElement> {
+    let mut reader = Reader::from_str(text);
+    reader.config_mut().trim_text(true);
+
+    let mut stack: Vec<XmlElement> = Vec::new();
+    let mut root = None;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(start) => stack.push(XmlElement::from_start(&start)?),
+            Event::Empty(start) => {
+                let element = XmlElement::from_start(&start)?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => root = Some(element),
+                }
+            }
+            Event::End(_) => {
+                let element = stack
+                    .pop()
+                    .ok_or_else(|| anyhow!("unbalanced closing tag"))?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => root = Some(element),
+                }
+            }
+            Event::Text(t) => {
+                if let Some(current) = stack.last_mut() {
+                    current.text.push_str(&t.unescape()?);
+                }
+            }
+            Event::CData(t) => {
+                if let Some(current) = stack.last_mut() {
+                    current.text.push_str(&String::from_utf8_lossy(&t));
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !stack.is_empty() {
+        bail!("unclosed element <{}>", stack[stack.len() - 1].name);
+    }
+    root.ok_or_else(|| anyhow!("document has no root element"))
+}
@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod tests {
-use eframe::egui::{Context};
-use detection_wizard::main_menu::{MainApp, Screen};
+    use detection_wizard::main_menu::{MainApp, Screen};
+    use eframe::egui::Context;
 
     fn mock_context() -> Context {
         Context::default()
@@ -19,7 +19,7 @@ use detection_wizard::main_menu::{MainApp, Screen};
     #[test]
     fn test_rules_button_sets_tool_selector() {
         let mut app = MainApp::default();
-        let ctx = mock_context();
+        let _ctx = mock_context();
         // Simulate click on Rules button
         app.screen = Screen::Menu;
 
@@ -36,7 +36,7 @@ use detection_wizard::main_menu::{MainApp, Screen};
     #[test]
     fn test_iocs_button_sets_ioc_downloader() {
         let mut app = MainApp::default();
-        let ctx = mock_context();
+        let _ctx = mock_context();
 
         app.screen = Screen::Menu;
         app.screen = Screen::IOCDownloader(Default::default());